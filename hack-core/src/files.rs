@@ -0,0 +1,32 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub fn handle_input(input: &Path, extension: &str) -> Result<Vec<PathBuf>, io::Error> {
+    /*
+     *  Every tool takes either a single source file or a directory of them.
+     *  Returns the file itself, or the files in the directory with the given
+     *  extension in name order, so the output doesn't depend on the filesystem.
+     */
+    let has_extension = |path: &Path| path.is_file() && path.extension().is_some_and(|ext| ext == extension);
+
+    if has_extension(input) {
+        return Ok(vec![input.to_path_buf()]);
+    }
+    if !input.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} isn't a .{} file or a directory", input.display(), extension)
+        ));
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(input)? {
+        let path = entry?.path();
+        if has_extension(&path) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
@@ -0,0 +1,62 @@
+// Mnemonic -> bits for each field of a C-instruction (111accccccdddjjj).
+// The assembler reads them one way, the disassembler the other.
+pub const DEST_CODES: [(&str, &str); 8] = [
+    ("",    "000"),
+    ("M",   "001"),
+    ("D",   "010"),
+    ("MD",  "011"),
+    ("A",   "100"),
+    ("AM",  "101"),
+    ("AD",  "110"),
+    ("AMD", "111")
+];
+
+pub const COMP_CODES: [(&str, &str); 28] = [
+    ("0",   "0101010"), // a = 0
+    ("1",   "0111111"),
+    ("-1",  "0111010"),
+    ("D",   "0001100"),
+    ("A",   "0110000"),
+    ("!D",  "0001101"),
+    ("!A",  "0110001"),
+    ("-D",  "0001111"),
+    ("-A",  "0110011"),
+    ("D+1", "0011111"),
+    ("A+1", "0110111"),
+    ("D-1", "0001110"),
+    ("A-1", "0110010"),
+    ("D+A", "0000010"),
+    ("D-A", "0010011"),
+    ("A-D", "0000111"),
+    ("D&A", "0000000"),
+    ("D|A", "0010101"),
+    ("M",   "1110000"), // a = 1
+    ("!M",  "1110001"),
+    ("-M",  "1110011"),
+    ("M+1", "1110111"),
+    ("M-1", "1110010"),
+    ("D+M", "1000010"),
+    ("D-M", "1010011"),
+    ("M-D", "1000111"),
+    ("D&M", "1000000"),
+    ("D|M", "1010101")
+];
+
+pub const JUMP_CODES: [(&str, &str); 8] = [
+    ("",    "000"),
+    ("JGT", "001"),
+    ("JEQ", "010"),
+    ("JGE", "011"),
+    ("JLT", "100"),
+    ("JNE", "101"),
+    ("JLE", "110"),
+    ("JMP", "111")
+];
+
+pub fn lookup_code<'a>(table: &[(&str, &'a str)], mnemonic: &str) -> Option<&'a str> {
+    table.iter().find(|(m, _)| *m == mnemonic).map(|(_, code)| *code)
+}
+
+pub fn lookup_mnemonic<'a>(table: &[(&'a str, &str)], code: &str) -> Option<&'a str> {
+    table.iter().find(|(_, c)| *c == code).map(|(m, _)| *m)
+}
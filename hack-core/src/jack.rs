@@ -1,10 +1,10 @@
-// Lexer
+// Jack tokenizer, shared by the syntax analyzer (project 10) and the compiler (project 11)
 // Reads in the input line by line and then disects each line into tokens
 
 use std::fmt;
 use std::fs::File;
 use std::io::{self, BufRead, BufReader};
-use std::path::PathBuf;
+use std::path::Path;
 
 const SYMBOLS: [char; 20] = [
     '{', '}', '(', ')', '[', ']', '.', ',', ';', '+', '-', '*', '/', '&', '|', ',', '<', '>', '=',
@@ -62,7 +62,7 @@ pub struct Tokenizer {
 }
 
 impl Tokenizer {
-    pub fn new(source_file: &PathBuf) -> Result<Tokenizer, io::Error> {
+    pub fn new(source_file: &Path) -> Result<Tokenizer, io::Error> {
         let file = File::open(source_file)?;
         let reader = BufReader::new(file);
 
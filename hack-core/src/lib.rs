@@ -0,0 +1,12 @@
+// Shared pieces of the Hack toolchain.
+// The assembler, CPU emulator, VM translators and Jack compilers all build on the
+// same instruction encoding, VM command model and Jack tokenizer from here.
+
+pub mod files;
+pub mod instruction;
+pub mod jack;
+pub mod vm;
+
+pub use crate::files::handle_input;
+pub use crate::jack::{Token, TokenType, Tokenizer};
+pub use crate::vm::{parse_command, Command, CommandType, Parser, VmError};
@@ -0,0 +1,308 @@
+// The commands of the Hack virtual machine, as read from a .vm file
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+pub const ARITH_COMMANDS: &[&str] = &[
+    "add", "sub", "neg",
+    "eq",  "gt",  "lt",
+    "and", "or",  "not"
+];
+
+pub const MEM_SEGMENTS: &[&str] = &[
+    "argument", "local",
+    "static",   "constant",
+    "this",     "that",
+    "pointer",  "temp"
+];
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CommandType {
+    Arithmetic,
+    Call,
+    Function,
+    Goto,
+    If,
+    Label,
+    Pop,
+    Push,
+    Return
+}
+
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Command {
+    arg1:         String,
+    arg2:         Option<String>,
+    command_type: CommandType,
+    file_name:    String,    // This is needed since static variables need to be xxx.j where xxx is the name of the vm file
+    line:         usize      // Where the command is in its file, 0 if it didn't come from the Parser
+}
+
+
+// Something wrong with a .vm file. Lines start at 1.
+#[derive(Debug)]
+pub enum VmError {
+    Io { file: String, error: io::Error },
+    NoSources { input: String },
+    Syntax { file: String, line: usize, text: String, message: String },       // The line isn't a VM command
+    Translation { file: String, line: usize, text: String, message: String }   // The command can't be turned into assembly
+}
+
+
+impl Command {
+    pub fn new(command_type: CommandType, arg1: &str, arg2: Option<&str>, file_name: &str) -> Command {
+        Command {
+            arg1: arg1.to_string(),
+            arg2: arg2.map(str::to_string),
+            command_type,
+            file_name: file_name.to_string(),
+            line: 0
+        }
+    }
+
+    pub fn get_arg1(&self) -> &String {
+        /*
+         * Returns the first argument of the current command.
+         * In the case of C_ARITHMETIC, the command itself (add, sub, ...) is returned.
+         * For C_RETURN it's just "return".
+         */
+        &self.arg1
+    }
+
+    pub fn get_arg2(&self) -> Option<&String> {
+        /*
+         * Returns the second argument of the current command.
+         * Only present if the current command is C_PUSH, C_POP, C_FUNCTION or C_CALL.
+         */
+        self.arg2.as_ref()
+    }
+
+    pub fn get_command_type(&self) -> &CommandType {
+        &self.command_type
+    }
+
+    pub fn get_file_name(&self) -> &String {
+        &self.file_name
+    }
+
+    pub fn get_line(&self) -> usize {
+        self.line
+    }
+
+    pub fn to_source(&self) -> String {
+        // The command as it would be written in a .vm file
+        match (self.command_type, &self.arg2) {
+            (CommandType::Push, Some(index)) => format!("push {} {}", self.arg1, index),
+            (CommandType::Pop, Some(index))  => format!("pop {} {}", self.arg1, index),
+            (CommandType::Label, _)          => format!("label {}", self.arg1),
+            (CommandType::Goto, _)           => format!("goto {}", self.arg1),
+            (CommandType::If, _)             => format!("if-goto {}", self.arg1),
+            (CommandType::Function, Some(n)) => format!("function {} {}", self.arg1, n),
+            (CommandType::Call, Some(n))     => format!("call {} {}", self.arg1, n),
+            _                                => self.arg1.clone()
+        }
+    }
+
+    pub fn error(&self, message: &str) -> VmError {
+        // A translation error about this command
+        VmError::Translation {
+            file: self.file_name.clone(),
+            line: self.line,
+            text: self.to_source(),
+            message: message.to_string()
+        }
+    }
+}
+
+
+/* ==========================================================================
+ *
+ *   Parser:
+ * - Handles the parsing of a single .vm file
+ * - Reads a VM command, parses the command into its lexical components,
+ *   and provides convenient access to these components
+ * - Ignores whitespace and comments
+ *
+ * ========================================================================== */
+pub struct Parser {
+    current_command:   Option<Command>,
+    reader:            BufReader<File>,
+    has_more_commands: bool,
+    input_file:        String,
+    file_name:         String,
+    line:              usize     // The line last read
+}
+
+
+impl Parser {
+    pub fn new(input_file: &str) -> Result<Parser, VmError> {
+        let io_error = |error| VmError::Io { file: input_file.to_string(), error };
+        let file = File::open(input_file).map_err(io_error)?;
+
+        let file_name = Path::new(input_file).file_name()
+                                             .and_then(|f| f.to_str())
+                                             .ok_or_else(|| io_error(io::ErrorKind::InvalidInput.into()))?;
+
+        Ok(Parser {
+            current_command: None,
+            reader: BufReader::new(file),
+            has_more_commands: true,
+            input_file: input_file.to_string(),
+            file_name: file_name.to_string(),
+            line: 0
+        })
+    }
+
+    pub fn advance(&mut self) -> Result<(), VmError> {
+        // ======================================================
+        // Reads the next command from the input and makes it the
+        // *current command*, skipping comments and blank lines.
+        // Only called if there's more commands.
+        // Initially there is not current command.
+        // A bad line is still read past, so calling advance again
+        // carries on with the next one.
+        // ======================================================
+        self.current_command = None;
+
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = self.reader.read_line(&mut line)
+                                  .map_err(|error| VmError::Io { file: self.input_file.clone(), error })?;
+            if read == 0 {
+                // EOF
+                self.has_more_commands = false;
+                return Ok(());
+            }
+            self.line += 1;
+
+            // Remove inline comments and white space
+            let command = line.split("//").next().unwrap_or_default().trim();
+            if !command.is_empty() {
+                let mut parsed = parse_command(command, &self.file_name).map_err(|message| VmError::Syntax {
+                    file: self.input_file.clone(),
+                    line: self.line,
+                    text: command.to_string(),
+                    message
+                })?;
+                parsed.line = self.line;
+                self.current_command = Some(parsed);
+                return Ok(());
+            }
+        }
+    }
+
+    pub fn get_current_command(&self) -> Option<&Command> {
+        self.current_command.as_ref()
+    }
+
+    pub fn has_more_commands(&self) -> bool {
+        self.has_more_commands
+    }
+}
+
+
+pub fn parse_command(line: &str, file_name: &str) -> Result<Command, String> {
+    /*
+     *  Parses one line of VM code, with any comment already stripped off.
+     *  file_name is the .vm file the line came from, for static variables.
+     */
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    let arg = |index: usize, error_msg: &str| parts.get(index).copied().ok_or_else(|| error_msg.to_string());
+
+    let c = arg(0, &format!("Invalid command: {}", line))?;
+
+    // --- Arithmetic ---
+    if ARITH_COMMANDS.contains(&c) {
+        return Ok(Command::new(CommandType::Arithmetic, c, None, file_name));
+    }
+
+    match c {
+        "push" | "pop" => {
+            let segment = arg(1, "push/pop command requires a memory segment.")?;
+            let index   = arg(2, "push/pop command requires an index.")?;
+
+            if !MEM_SEGMENTS.contains(&segment) {
+                return Err(format!("Invalid memory segment: {}", segment));
+            }
+            // Check that the index can be parsed as u32 otherwise it's invalid
+            if index.parse::<u32>().is_err() {
+                return Err(format!("Invalid index for push/pop command: {}", index));
+            }
+
+            let command_type = if c == "push" { CommandType::Push } else { CommandType::Pop };
+            Ok(Command::new(command_type, segment, Some(index), file_name))
+        },
+        "label" => {
+            let label_name = arg(1, "'label' command requires a name.")?;
+            Ok(Command::new(CommandType::Label, label_name, None, file_name))
+        },
+        "if-goto" => {
+            let label_name = arg(1, "'if-goto' command requires a label name.")?;
+            Ok(Command::new(CommandType::If, label_name, None, file_name))
+        },
+        "goto" => {
+            let label_name = arg(1, "'goto' command requires a label name.")?;
+            Ok(Command::new(CommandType::Goto, label_name, None, file_name))
+        },
+        "function" => {
+            let function_name = arg(1, "'function' command requires a name.")?;
+            let local_vars    = arg(2, "'function' command requires a local variable count.")?;
+            Ok(Command::new(CommandType::Function, function_name, Some(local_vars), file_name))
+        },
+        "call" => {
+            let function_name = arg(1, "'call' command requires a function name.")?;
+            let arg_count     = arg(2, "'call' command requires an argument count.")?;
+            Ok(Command::new(CommandType::Call, function_name, Some(arg_count), file_name))
+        },
+        "return" => Ok(Command::new(CommandType::Return, c, None, file_name)),
+        _ => Err(format!("Invalid command: {}", line))
+    }
+}
+
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Arg1: {}, Arg2: {}, Command Type: {}",
+            self.arg1,
+            self.arg2.as_deref().unwrap_or("None"),
+            self.command_type
+        )
+    }
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VmError::Io { file, error } => write!(f, "{}: {}", file, error),
+            VmError::NoSources { input } => write!(f, "{}: no .vm files to translate", input),
+            VmError::Syntax { file, line, text, message }
+            | VmError::Translation { file, line, text, message } => {
+                write!(f, "{}:{}: {}\n    {}", file, line, message, text)
+            }
+        }
+    }
+}
+
+impl std::error::Error for VmError {}
+
+impl fmt::Display for CommandType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommandType::Arithmetic => write!(f, "Arithmetic"),
+            CommandType::Call       => write!(f, "Call"),
+            CommandType::Function   => write!(f, "Function"),
+            CommandType::Goto       => write!(f, "Goto"),
+            CommandType::If         => write!(f, "If"),
+            CommandType::Label      => write!(f, "Label"),
+            CommandType::Pop        => write!(f, "Pop"),
+            CommandType::Push       => write!(f, "Push"),
+            CommandType::Return     => write!(f, "Return")
+        }
+    }
+}
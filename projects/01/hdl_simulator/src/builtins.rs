@@ -0,0 +1,316 @@
+use std::path::Path;
+
+use cpu_emulator::cpu::{alu, load_program};
+
+// Interfaces of the chips that are simulated natively rather than from HDL, the
+// same as the .hdl files in tools/builtInChips. Nand and DFF are the primitives
+// everything else is elaborated into, the rest have a BuiltinChip model.
+pub const BUILTIN_CHIPS: [&str; 35] = [
+    "CHIP Nand { IN a, b; OUT out; BUILTIN Nand; }",
+    "CHIP DFF { IN in; OUT out; BUILTIN DFF; CLOCKED in; }",
+
+    // Project 01
+    "CHIP Not { IN in; OUT out; BUILTIN Not; }",
+    "CHIP And { IN a, b; OUT out; BUILTIN And; }",
+    "CHIP Or { IN a, b; OUT out; BUILTIN Or; }",
+    "CHIP Xor { IN a, b; OUT out; BUILTIN Xor; }",
+    "CHIP Mux { IN a, b, sel; OUT out; BUILTIN Mux; }",
+    "CHIP DMux { IN in, sel; OUT a, b; BUILTIN DMux; }",
+    "CHIP Not16 { IN in[16]; OUT out[16]; BUILTIN Not16; }",
+    "CHIP And16 { IN a[16], b[16]; OUT out[16]; BUILTIN And16; }",
+    "CHIP Or16 { IN a[16], b[16]; OUT out[16]; BUILTIN Or16; }",
+    "CHIP Mux16 { IN a[16], b[16], sel; OUT out[16]; BUILTIN Mux16; }",
+    "CHIP Or8Way { IN in[8]; OUT out; BUILTIN Or8Way; }",
+    "CHIP Mux4Way16 { IN a[16], b[16], c[16], d[16], sel[2]; OUT out[16]; BUILTIN Mux4Way16; }",
+    "CHIP Mux8Way16 { IN a[16], b[16], c[16], d[16], e[16], f[16], g[16], h[16], sel[3]; OUT out[16]; BUILTIN Mux8Way16; }",
+    "CHIP DMux4Way { IN in, sel[2]; OUT a, b, c, d; BUILTIN DMux4Way; }",
+    "CHIP DMux8Way { IN in, sel[3]; OUT a, b, c, d, e, f, g, h; BUILTIN DMux8Way; }",
+
+    // Project 02
+    "CHIP HalfAdder { IN a, b; OUT sum, carry; BUILTIN HalfAdder; }",
+    "CHIP FullAdder { IN a, b, c; OUT sum, carry; BUILTIN FullAdder; }",
+    "CHIP Add16 { IN a[16], b[16]; OUT out[16]; BUILTIN Add16; }",
+    "CHIP Inc16 { IN in[16]; OUT out[16]; BUILTIN Inc16; }",
+    "CHIP ALU { IN x[16], y[16], zx, nx, zy, ny, f, no; OUT out[16], zr, ng; BUILTIN ALU; }",
+
+    // Project 03
+    "CHIP Bit { IN in, load; OUT out; BUILTIN Bit; CLOCKED in, load; }",
+    "CHIP Register { IN in[16], load; OUT out[16]; BUILTIN Register; CLOCKED in, load; }",
+    "CHIP ARegister { IN in[16], load; OUT out[16]; BUILTIN ARegister; CLOCKED in, load; }",
+    "CHIP DRegister { IN in[16], load; OUT out[16]; BUILTIN DRegister; CLOCKED in, load; }",
+    "CHIP PC { IN in[16], load, inc, reset; OUT out[16]; BUILTIN PC; CLOCKED in, load, inc, reset; }",
+    "CHIP RAM8 { IN in[16], load, address[3]; OUT out[16]; BUILTIN RAM8; CLOCKED in, load; }",
+    "CHIP RAM64 { IN in[16], load, address[6]; OUT out[16]; BUILTIN RAM64; CLOCKED in, load; }",
+    "CHIP RAM512 { IN in[16], load, address[9]; OUT out[16]; BUILTIN RAM512; CLOCKED in, load; }",
+    "CHIP RAM4K { IN in[16], load, address[12]; OUT out[16]; BUILTIN RAM4K; CLOCKED in, load; }",
+    "CHIP RAM16K { IN in[16], load, address[14]; OUT out[16]; BUILTIN RAM16K; CLOCKED in, load; }",
+
+    // Project 05
+    "CHIP ROM32K { IN address[15]; OUT out[16]; BUILTIN ROM32K; }",
+    "CHIP Screen { IN in[16], load, address[13]; OUT out[16]; BUILTIN Screen; CLOCKED in, load; }",
+    "CHIP Keyboard { OUT out[16]; BUILTIN Keyboard; }",
+];
+
+// A natively simulated chip. Pin values are passed in the order the pins are
+// declared, one u16 per pin, and only the bits that fit a pin are used.
+pub trait BuiltinChip {
+    // Sets the outputs from the inputs and whatever the chip remembers
+    fn eval(&mut self, inputs: &[u16], outputs: &mut [u16]);
+
+    // Clocked chips sample their inputs on tick and show the result on tock
+    fn tick(&mut self, _inputs: &[u16]) {}
+    fn tock(&mut self) {}
+
+    // Internal state for test scripts, e.g. DRegister[] or RAM16K[5]
+    fn get(&self, _index: usize) -> Option<u16> {
+        None
+    }
+    fn set(&mut self, _index: usize, _value: u16) -> bool {
+        false
+    }
+
+    // `ROM32K load Max.hack`
+    fn load(&mut self, file: &Path) -> Result<(), String> {
+        Err(format!("can't load '{}' into this chip", file.display()))
+    }
+}
+
+pub fn model(builtin: &str) -> Option<Box<dyn BuiltinChip>> {
+    // The BUILTIN name of a chip decides its model. As in the Java simulator, the
+    // bitwise gates work for any width, so e.g. `BUILTIN And` also serves And16.
+    let combinational = |f: fn(&[u16], &mut [u16])| -> Option<Box<dyn BuiltinChip>> { Some(Box::new(Combinational(f))) };
+
+    match builtin {
+        "Not" | "Not16" => combinational(|i, o| o[0] = !i[0]),
+        "And" | "And16" => combinational(|i, o| o[0] = i[0] & i[1]),
+        "Or" | "Or16"   => combinational(|i, o| o[0] = i[0] | i[1]),
+        "Xor"           => combinational(|i, o| o[0] = i[0] ^ i[1]),
+        "Mux" | "Mux16" => combinational(|i, o| o[0] = if i[2] & 1 != 0 { i[1] } else { i[0] }),
+        "Mux4Way16"     => combinational(|i, o| o[0] = i[(i[4] & 0b11) as usize]),
+        "Mux8Way16"     => combinational(|i, o| o[0] = i[(i[8] & 0b111) as usize]),
+        "DMux"          => combinational(|i, o| demux(i[0], i[1] & 1, o)),
+        "DMux4Way"      => combinational(|i, o| demux(i[0], i[1] & 0b11, o)),
+        "DMux8Way"      => combinational(|i, o| demux(i[0], i[1] & 0b111, o)),
+        "Or8Way"        => combinational(|i, o| o[0] = (i[0] & 0xFF != 0) as u16),
+        "HalfAdder"     => combinational(|i, o| add_bits(&[i[0], i[1]], o)),
+        "FullAdder"     => combinational(|i, o| add_bits(&[i[0], i[1], i[2]], o)),
+        "Add16"         => combinational(|i, o| o[0] = i[0].wrapping_add(i[1])),
+        "Inc16"         => combinational(|i, o| o[0] = i[0].wrapping_add(1)),
+        "ALU"           => combinational(|i, o| {
+            // zx nx zy ny f no are the ALU's control bits, most significant first
+            let control = i[2..8].iter().fold(0, |control, bit| control << 1 | (bit & 1));
+            o[0] = alu(i[0], i[1], control);
+            o[1] = (o[0] == 0) as u16;
+            o[2] = o[0] >> 15;
+        }),
+        "Bit" | "Register" | "ARegister" | "DRegister" => Some(Box::<Register>::default()),
+        "PC"       => Some(Box::<Counter>::default()),
+        "RAM8"     => Some(Box::new(Ram::new(8))),
+        "RAM64"    => Some(Box::new(Ram::new(64))),
+        "RAM512"   => Some(Box::new(Ram::new(512))),
+        "RAM4K"    => Some(Box::new(Ram::new(4096))),
+        "RAM16K"   => Some(Box::new(Ram::new(16384))),
+        "Screen"   => Some(Box::new(Ram::new(8192))),
+        "ROM32K"   => Some(Box::new(Rom { words: vec![0; 32768] })),
+        "Keyboard" => Some(Box::<Keyboard>::default()),
+        _ => None
+    }
+}
+
+fn demux(input: u16, sel: u16, outputs: &mut [u16]) {
+    for (i, out) in outputs.iter_mut().enumerate() {
+        *out = if i == sel as usize { input } else { 0 };
+    }
+}
+
+fn add_bits(bits: &[u16], outputs: &mut [u16]) {
+    // sum, carry
+    let total: u16 = bits.iter().map(|b| b & 1).sum();
+    outputs[0] = total & 1;
+    outputs[1] = total >> 1;
+}
+
+struct Combinational(fn(&[u16], &mut [u16]));
+
+impl BuiltinChip for Combinational {
+    fn eval(&mut self, inputs: &[u16], outputs: &mut [u16]) {
+        (self.0)(inputs, outputs)
+    }
+}
+
+// Bit, Register, ARegister, DRegister: IN in, load; OUT out
+#[derive(Default)]
+struct Register {
+    value: u16,
+    out: u16
+}
+
+impl BuiltinChip for Register {
+    fn eval(&mut self, _inputs: &[u16], outputs: &mut [u16]) {
+        outputs[0] = self.out;
+    }
+
+    fn tick(&mut self, inputs: &[u16]) {
+        if inputs[1] & 1 != 0 {
+            self.value = inputs[0];
+        }
+    }
+
+    fn tock(&mut self) {
+        self.out = self.value;
+    }
+
+    fn get(&self, _index: usize) -> Option<u16> {
+        Some(self.value)
+    }
+
+    fn set(&mut self, _index: usize, value: u16) -> bool {
+        self.value = value;
+        self.out = value;
+        true
+    }
+}
+
+// PC: IN in, load, inc, reset; OUT out
+#[derive(Default)]
+struct Counter {
+    value: u16,
+    out: u16
+}
+
+impl BuiltinChip for Counter {
+    fn eval(&mut self, _inputs: &[u16], outputs: &mut [u16]) {
+        outputs[0] = self.out;
+    }
+
+    fn tick(&mut self, inputs: &[u16]) {
+        // reset beats load, which beats inc
+        self.value = if inputs[3] & 1 != 0 {
+            0
+        } else if inputs[1] & 1 != 0 {
+            inputs[0]
+        } else if inputs[2] & 1 != 0 {
+            self.out.wrapping_add(1)
+        } else {
+            self.out
+        };
+    }
+
+    fn tock(&mut self) {
+        self.out = self.value;
+    }
+
+    fn get(&self, _index: usize) -> Option<u16> {
+        Some(self.value)
+    }
+
+    fn set(&mut self, _index: usize, value: u16) -> bool {
+        self.value = value;
+        self.out = value;
+        true
+    }
+}
+
+// RAM8 ... RAM16K and Screen: IN in, load, address; OUT out
+struct Ram {
+    words: Vec<u16>,
+    write: Option<(usize, u16)>
+}
+
+impl Ram {
+    fn new(size: usize) -> Ram {
+        Ram { words: vec![0; size], write: None }
+    }
+
+    fn index(&self, address: u16) -> usize {
+        address as usize % self.words.len()
+    }
+}
+
+impl BuiltinChip for Ram {
+    fn eval(&mut self, inputs: &[u16], outputs: &mut [u16]) {
+        outputs[0] = self.words[self.index(inputs[2])];
+    }
+
+    fn tick(&mut self, inputs: &[u16]) {
+        // The write only shows up on out after tock
+        self.write = (inputs[1] & 1 != 0).then(|| (self.index(inputs[2]), inputs[0]));
+    }
+
+    fn tock(&mut self) {
+        if let Some((index, value)) = self.write.take() {
+            self.words[index] = value;
+        }
+    }
+
+    fn get(&self, index: usize) -> Option<u16> {
+        self.words.get(index).copied()
+    }
+
+    fn set(&mut self, index: usize, value: u16) -> bool {
+        match self.words.get_mut(index) {
+            Some(word) => {
+                *word = value;
+                true
+            },
+            None => false
+        }
+    }
+}
+
+// ROM32K: IN address; OUT out
+struct Rom {
+    words: Vec<u16>
+}
+
+impl BuiltinChip for Rom {
+    fn eval(&mut self, inputs: &[u16], outputs: &mut [u16]) {
+        outputs[0] = self.words[inputs[0] as usize % self.words.len()];
+    }
+
+    fn get(&self, index: usize) -> Option<u16> {
+        self.words.get(index).copied()
+    }
+
+    fn set(&mut self, index: usize, value: u16) -> bool {
+        match self.words.get_mut(index) {
+            Some(word) => {
+                *word = value;
+                true
+            },
+            None => false
+        }
+    }
+
+    fn load(&mut self, file: &Path) -> Result<(), String> {
+        // .hack files, or .asm files which are assembled first
+        let words = load_program(&file.to_string_lossy()).map_err(|errors| {
+            errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("\n")
+        })?;
+        let len = words.len().min(self.words.len());
+        self.words.fill(0);
+        self.words[..len].copy_from_slice(&words[..len]);
+        Ok(())
+    }
+}
+
+// Keyboard: OUT out. Headless there are no keys, but a script can `set Keyboard[] 75`.
+#[derive(Default)]
+struct Keyboard {
+    key: u16
+}
+
+impl BuiltinChip for Keyboard {
+    fn eval(&mut self, _inputs: &[u16], outputs: &mut [u16]) {
+        outputs[0] = self.key;
+    }
+
+    fn get(&self, _index: usize) -> Option<u16> {
+        Some(self.key)
+    }
+
+    fn set(&mut self, _index: usize, value: u16) -> bool {
+        self.key = value;
+        true
+    }
+}
@@ -0,0 +1,106 @@
+use std::fmt;
+use std::io;
+
+use assembler::Location;
+
+#[derive(Debug)]
+pub enum HdlError {
+    Io { file: String, error: io::Error },
+
+    // Parsing
+    Syntax { loc: Location, text: String },
+    WrongChipName { loc: Location, text: String, expected: String },
+
+    // Elaboration
+    UnknownChip { loc: Location, text: String },
+    NoBuiltin { loc: Location, text: String },
+    RecursiveChip { loc: Location, text: String },
+    UnknownPin { loc: Location, text: String },
+    BadSlice { loc: Location, text: String },
+    SlicedInternalPin { loc: Location, text: String },
+    WidthMismatch { loc: Location, text: String, expected: usize, found: usize },
+    ConstantOutput { loc: Location, text: String },
+
+    // Lint
+    UndrivenPin { loc: Location, text: String },
+    UnassignedOutput { loc: Location, text: String },
+    DrivenTwice { loc: Location, text: String },
+    DrivenInput { loc: Location, text: String },
+    CombinationalCycle { loc: Location, text: String },
+}
+
+impl HdlError {
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            HdlError::Io { .. } => None,
+            HdlError::Syntax { loc, .. }
+            | HdlError::WrongChipName { loc, .. }
+            | HdlError::UnknownChip { loc, .. }
+            | HdlError::NoBuiltin { loc, .. }
+            | HdlError::RecursiveChip { loc, .. }
+            | HdlError::UnknownPin { loc, .. }
+            | HdlError::BadSlice { loc, .. }
+            | HdlError::SlicedInternalPin { loc, .. }
+            | HdlError::WidthMismatch { loc, .. }
+            | HdlError::ConstantOutput { loc, .. }
+            | HdlError::UndrivenPin { loc, .. }
+            | HdlError::UnassignedOutput { loc, .. }
+            | HdlError::DrivenTwice { loc, .. }
+            | HdlError::DrivenInput { loc, .. }
+            | HdlError::CombinationalCycle { loc, .. } => Some(loc),
+        }
+    }
+}
+
+impl fmt::Display for HdlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HdlError::Io { file, error } => write!(f, "{}: {}", file, error),
+            HdlError::Syntax { loc, text } => write!(f, "{}: {}", loc, text),
+            HdlError::WrongChipName { loc, text, expected } => {
+                write!(f, "{}: chip '{}' should be called '{}' to match its file name", loc, text, expected)
+            }
+            HdlError::UnknownChip { loc, text } => {
+                write!(f, "{}: can't find chip '{}'", loc, text)
+            }
+            HdlError::NoBuiltin { loc, text } => {
+                write!(f, "{}: there is no built-in implementation of '{}'", loc, text)
+            }
+            HdlError::RecursiveChip { loc, text } => {
+                write!(f, "{}: chip '{}' contains itself", loc, text)
+            }
+            HdlError::UnknownPin { loc, text } => {
+                write!(f, "{}: unknown pin '{}'", loc, text)
+            }
+            HdlError::BadSlice { loc, text } => {
+                write!(f, "{}: '{}' is outside the bus", loc, text)
+            }
+            HdlError::SlicedInternalPin { loc, text } => {
+                write!(f, "{}: internal pin '{}' can't be subscripted", loc, text)
+            }
+            HdlError::WidthMismatch { loc, text, expected, found } => {
+                write!(f, "{}: '{}' is {} bit(s) wide but is connected to {} bit(s)", loc, text, expected, found)
+            }
+            HdlError::ConstantOutput { loc, text } => {
+                write!(f, "{}: output pin '{}' can't be connected to a constant", loc, text)
+            }
+            HdlError::UndrivenPin { loc, text } => {
+                write!(f, "{}: internal pin '{}' is read but nothing drives it", loc, text)
+            }
+            HdlError::UnassignedOutput { loc, text } => {
+                write!(f, "{}: output '{}' is never assigned", loc, text)
+            }
+            HdlError::DrivenTwice { loc, text } => {
+                write!(f, "{}: '{}' is driven more than once", loc, text)
+            }
+            HdlError::DrivenInput { loc, text } => {
+                write!(f, "{}: input pin '{}' can't be driven by a part", loc, text)
+            }
+            HdlError::CombinationalCycle { loc, text } => {
+                write!(f, "{}: combinational cycle {} isn't broken by a DFF", loc, text)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HdlError {}
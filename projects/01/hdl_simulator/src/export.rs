@@ -0,0 +1,425 @@
+use std::collections::HashMap;
+use std::io::{Error, Write};
+use std::rc::Rc;
+
+use crate::error::HdlError;
+use crate::library::ChipLibrary;
+use crate::parser::{ChipDef, PinRef, Signal};
+
+// Names that can't be used as Verilog identifiers, which get a '_' on the end
+const VERILOG_KEYWORDS: [&str; 32] = [
+    "always", "and", "assign", "begin", "buf", "case", "default", "else", "end", "endcase",
+    "endmodule", "for", "function", "if", "initial", "inout", "input", "integer", "module",
+    "nand", "nor", "not", "or", "output", "parameter", "reg", "repeat", "task", "tri",
+    "while", "wire", "xor",
+];
+
+pub fn write_verilog(out: &mut dyn Write, chip: &ChipDef, lib: &mut ChipLibrary) -> Result<(), Error> {
+    /*
+     *  One module per CHIP in the hierarchy, each written once, parts before the
+     *  chips using them. Built-in chips become behavioural modules, and every
+     *  module with a DFF or clocked built-in somewhere inside gets a `clk` input.
+     */
+    let mut chips: Vec<Rc<ChipDef>> = Vec::new();
+    collect_chips(chip, lib, &mut chips, &mut Vec::new()).map_err(other)?;
+
+    let mut clocked: HashMap<String, bool> = HashMap::new();
+    for chip in &chips {
+        let has_clock = match &chip.builtin {
+            Some(_) => !chip.clocked.is_empty(),
+            None => chip.parts.iter().any(|p| clocked.get(&p.chip).copied().unwrap_or(false))
+        };
+        clocked.insert(chip.name.clone(), has_clock);
+    }
+
+    writeln!(out, "// Generated from {} by hdl_simulator", chip.loc.file)?;
+    for chip in &chips {
+        writeln!(out)?;
+        write_module(out, chip, &chips, &clocked)?;
+    }
+    Ok(())
+}
+
+fn other(e: HdlError) -> Error {
+    Error::other(e.to_string())
+}
+
+fn collect_chips(chip: &ChipDef, lib: &mut ChipLibrary, chips: &mut Vec<Rc<ChipDef>>, stack: &mut Vec<String>) -> Result<(), HdlError> {
+    // Depth first, so each chip comes after all of its parts
+    stack.push(chip.name.clone());
+    for part in &chip.parts {
+        if stack.contains(&part.chip) {
+            return Err(HdlError::RecursiveChip { loc: part.loc.clone(), text: part.chip.clone() });
+        }
+        if chips.iter().any(|c| c.name == part.chip) {
+            continue;
+        }
+        let def = lib.find(&part.chip, &part.loc)?;
+        collect_chips(&def, lib, chips, stack)?;
+    }
+    stack.pop();
+
+    if !chips.iter().any(|c| c.name == chip.name) {
+        chips.push(Rc::new(chip.clone()));
+    }
+    Ok(())
+}
+
+fn ident(name: &str) -> String {
+    if VERILOG_KEYWORDS.contains(&name) { format!("{}_", name) } else { name.to_string() }
+}
+
+fn range(width: usize) -> String {
+    if width > 1 { format!("[{}:0] ", width - 1) } else { String::new() }
+}
+
+fn find<'c>(chips: &'c [Rc<ChipDef>], name: &str) -> &'c ChipDef {
+    chips.iter().find(|c| c.name == name).expect("parts are collected before the chips using them")
+}
+
+// Where one bit of a part's input comes from
+#[derive(Clone, PartialEq)]
+enum Source {
+    Const(bool),
+    Bit(String, usize, usize)   // signal, bit, width of the signal
+}
+
+fn write_module(out: &mut dyn Write, chip: &ChipDef, chips: &[Rc<ChipDef>], clocked: &HashMap<String, bool>) -> Result<(), Error> {
+    let is_clocked = clocked[&chip.name];
+
+    let mut ports: Vec<String> = Vec::new();
+    if is_clocked {
+        ports.push("input clk".to_string());
+    }
+    for pin in &chip.inputs {
+        ports.push(format!("input {}{}", range(pin.width), ident(&pin.name)));
+    }
+    for pin in &chip.outputs {
+        let reg = if chip.builtin.as_deref().is_some_and(is_register) { "reg " } else { "" };
+        ports.push(format!("output {}{}{}", reg, range(pin.width), ident(&pin.name)));
+    }
+
+    writeln!(out, "module {} (", chip.name)?;
+    writeln!(out, "    {}", ports.join(",\n    "))?;
+    writeln!(out, ");")?;
+
+    match &chip.builtin {
+        Some(builtin) => write_builtin(out, chip, builtin)?,
+        None => write_parts(out, chip, chips, clocked)?
+    }
+
+    writeln!(out, "endmodule")
+}
+
+fn internal_pins(chip: &ChipDef, chips: &[Rc<ChipDef>]) -> Vec<(String, usize)> {
+    // An internal pin is as wide as whatever it's first connected to
+    let mut pins: Vec<(String, usize)> = Vec::new();
+    for part in &chip.parts {
+        let def = find(chips, &part.chip);
+        for conn in &part.connections {
+            let Signal::Pin(signal) = &conn.signal else {
+                continue;
+            };
+            if chip.pin(&signal.name).is_some() || pins.iter().any(|(n, _)| *n == signal.name) {
+                continue;
+            }
+            let width = def.pin(&conn.pin.name).map_or(1, |p| p.width);
+            let width = conn.pin.slice.map_or(width, |(low, high)| high + 1 - low);
+            pins.push((signal.name.clone(), width));
+        }
+    }
+    pins
+}
+
+fn signal_expr(signal: &PinRef) -> String {
+    match signal.slice {
+        None => ident(&signal.name),
+        Some((low, high)) if low == high => format!("{}[{}]", ident(&signal.name), low),
+        Some((low, high)) => format!("{}[{}:{}]", ident(&signal.name), high, low)
+    }
+}
+
+fn concat(bits: &[Source]) -> String {
+    /*
+     *  The bits of an input port, least significant first, as a Verilog
+     *  expression, e.g. {x[3:0], 4'b0} or just `a` when the whole of `a` is used.
+     */
+    let mut parts: Vec<String> = Vec::new();
+    let mut i = bits.len();
+    while i > 0 {
+        let top = &bits[i - 1];
+        let mut len = 1;
+        while len < i && match (top, &bits[i - 1 - len]) {
+            (Source::Const(a), Source::Const(b)) => a == b,
+            (Source::Bit(a, ai, _), Source::Bit(b, bi, _)) => a == b && *ai >= len && ai - len == *bi,
+            _ => false
+        } {
+            len += 1;
+        }
+
+        parts.push(match top {
+            Source::Const(false) => format!("{}'b0", len),
+            Source::Const(true) if len == 1 => "1'b1".to_string(),
+            Source::Const(true) => format!("{{{}{{1'b1}}}}", len),
+            Source::Bit(name, high, width) => {
+                let low = high + 1 - len;
+                if len == *width {
+                    ident(name)
+                } else if len == 1 {
+                    format!("{}[{}]", ident(name), high)
+                } else {
+                    format!("{}[{}:{}]", ident(name), high, low)
+                }
+            }
+        });
+        i -= len;
+    }
+
+    if parts.len() == 1 { parts.remove(0) } else { format!("{{{}}}", parts.join(", ")) }
+}
+
+fn write_parts(out: &mut dyn Write, chip: &ChipDef, chips: &[Rc<ChipDef>], clocked: &HashMap<String, bool>) -> Result<(), Error> {
+    let internal = internal_pins(chip, chips);
+    let width_of = |name: &str| {
+        chip.pin(name).map(|p| p.width)
+            .or_else(|| internal.iter().find(|(n, _)| n == name).map(|(_, w)| *w))
+            .unwrap_or(1)
+    };
+
+    for (name, width) in &internal {
+        writeln!(out, "    wire {}{};", range(*width), ident(name))?;
+    }
+
+    let mut assigns: Vec<String> = Vec::new();
+
+    for (i, part) in chip.parts.iter().enumerate() {
+        let def = find(chips, &part.chip);
+        let instance = format!("part{}", i);
+        let mut ports: Vec<String> = Vec::new();
+
+        if clocked[&def.name] {
+            ports.push(".clk(clk)".to_string());
+        }
+
+        for pin in &def.inputs {
+            // Unconnected input bits are false
+            let mut bits = vec![Source::Const(false); pin.width];
+            for conn in part.connections.iter().filter(|c| c.pin.name == pin.name) {
+                let (low, high) = conn.pin.slice.unwrap_or((0, pin.width - 1));
+                for (n, bit) in (low..=high).enumerate() {
+                    bits[bit] = match &conn.signal {
+                        Signal::Const(value) => Source::Const(*value),
+                        Signal::Pin(signal) => {
+                            let first = signal.slice.map_or(0, |(low, _)| low);
+                            Source::Bit(signal.name.clone(), first + n, width_of(&signal.name))
+                        }
+                    };
+                }
+            }
+            ports.push(format!(".{}({})", ident(&pin.name), concat(&bits)));
+        }
+
+        for pin in &def.outputs {
+            let conns: Vec<_> = part.connections.iter().filter(|c| c.pin.name == pin.name).collect();
+            match conns.as_slice() {
+                [] => (),
+                [conn] if conn.pin.slice.is_none() => {
+                    if let Signal::Pin(signal) = &conn.signal {
+                        ports.push(format!(".{}({})", ident(&pin.name), signal_expr(signal)));
+                    }
+                },
+                _ => {
+                    // Fanned out or split up, so the port gets a wire of its own
+                    let wire = format!("{}_{}", instance, pin.name);
+                    writeln!(out, "    wire {}{};", range(pin.width), wire)?;
+                    ports.push(format!(".{}({})", ident(&pin.name), wire));
+
+                    for conn in conns {
+                        if let Signal::Pin(signal) = &conn.signal {
+                            let source = match conn.pin.slice {
+                                None => wire.clone(),
+                                Some((low, high)) if low == high => format!("{}[{}]", wire, low),
+                                Some((low, high)) => format!("{}[{}:{}]", wire, high, low)
+                            };
+                            assigns.push(format!("    assign {} = {};", signal_expr(signal), source));
+                        }
+                    }
+                }
+            }
+        }
+
+        writeln!(out, "    {} {} ({});", def.name, instance, ports.join(", "))?;
+    }
+
+    for assign in assigns {
+        writeln!(out, "{}", assign)?;
+    }
+    Ok(())
+}
+
+fn is_register(builtin: &str) -> bool {
+    matches!(builtin, "DFF" | "Bit" | "Register" | "ARegister" | "DRegister" | "PC")
+}
+
+fn write_builtin(out: &mut dyn Write, chip: &ChipDef, builtin: &str) -> Result<(), Error> {
+    // Behavioural Verilog for the built-in chips
+    let body = match builtin {
+        "Nand"          => "    assign out = ~(a & b);".to_string(),
+        "Not" | "Not16" => "    assign out = ~in;".to_string(),
+        "And" | "And16" => "    assign out = a & b;".to_string(),
+        "Or" | "Or16"   => "    assign out = a | b;".to_string(),
+        "Xor"           => "    assign out = a ^ b;".to_string(),
+        "Mux" | "Mux16" => "    assign out = sel ? b : a;".to_string(),
+        "Mux4Way16"     => "    assign out = sel[1] ? (sel[0] ? d : c) : (sel[0] ? b : a);".to_string(),
+        "Mux8Way16"     => [
+            "    assign out = sel[2] ? (sel[1] ? (sel[0] ? h : g) : (sel[0] ? f : e))",
+            "                        : (sel[1] ? (sel[0] ? d : c) : (sel[0] ? b : a));",
+        ].join("\n"),
+        "DMux" | "DMux4Way" | "DMux8Way" => {
+            chip.outputs.iter()
+                        .enumerate()
+                        .map(|(i, pin)| format!("    assign {} = sel == {} ? in : 1'b0;", pin.name, i))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+        },
+        "Or8Way"        => "    assign out = |in;".to_string(),
+        "HalfAdder"     => "    assign {carry, sum} = a + b;".to_string(),
+        "FullAdder"     => "    assign {carry, sum} = a + b + c;".to_string(),
+        "Add16"         => "    assign out = a + b;".to_string(),
+        "Inc16"         => "    assign out = in + 16'd1;".to_string(),
+        "ALU" => [
+            "    wire [15:0] zxed = zx ? 16'd0 : x;",
+            "    wire [15:0] nxed = nx ? ~zxed : zxed;",
+            "    wire [15:0] zyed = zy ? 16'd0 : y;",
+            "    wire [15:0] nyed = ny ? ~zyed : zyed;",
+            "    wire [15:0] result = f ? nxed + nyed : nxed & nyed;",
+            "    assign out = no ? ~result : result;",
+            "    assign zr = out == 16'd0;",
+            "    assign ng = out[15];",
+        ].join("\n"),
+        "DFF" => "    always @(posedge clk) out <= in;".to_string(),
+        "Bit" | "Register" | "ARegister" | "DRegister" => {
+            "    always @(posedge clk) if (load) out <= in;".to_string()
+        },
+        "PC" => [
+            "    always @(posedge clk)",
+            "        if (reset) out <= 16'd0;",
+            "        else if (load) out <= in;",
+            "        else if (inc) out <= out + 16'd1;",
+        ].join("\n"),
+        "RAM8" | "RAM64" | "RAM512" | "RAM4K" | "RAM16K" | "Screen" => {
+            let words = 1 << chip.input("address").map_or(0, |p| p.width);
+            [
+                format!("    reg [15:0] memory [0:{}];", words - 1),
+                "    assign out = memory[address];".to_string(),
+                "    always @(posedge clk) if (load) memory[address] <= in;".to_string(),
+            ].join("\n")
+        },
+        "ROM32K" => [
+            "    // The program, e.g. from `assembler --format memb Prog.asm`",
+            "    parameter PROGRAM = \"program.mem\";",
+            "    reg [15:0] memory [0:32767];",
+            "    initial $readmemb(PROGRAM, memory);",
+            "    assign out = memory[address];",
+        ].join("\n"),
+        "Keyboard" => [
+            "    // Drive `key` from a real keyboard to use this on an FPGA",
+            "    reg [15:0] key = 16'd0;",
+            "    assign out = key;",
+        ].join("\n"),
+        _ => format!("    // No behavioural model for BUILTIN {}", builtin)
+    };
+
+    writeln!(out, "{}", body)
+}
+
+pub fn write_dot(out: &mut dyn Write, chip: &ChipDef, lib: &mut ChipLibrary) -> Result<(), Error> {
+    /*
+     *  The chip's PARTS as a Graphviz graph: the chip's inputs on the left, its
+     *  outputs on the right, and each part as a box with its input pins on one
+     *  side and output pins on the other. Edges are labelled with the signal.
+     */
+    writeln!(out, "digraph {} {{", chip.name)?;
+    writeln!(out, "    rankdir=LR;")?;
+    writeln!(out, "    node [fontname=\"Helvetica\"];")?;
+    writeln!(out, "    edge [fontname=\"Helvetica\", fontsize=10];")?;
+    writeln!(out)?;
+
+    for pin in &chip.inputs {
+        writeln!(out, "    \"in:{}\" [label=\"{}\", shape=cds];", pin.name, describe_decl(&pin.name, pin.width))?;
+    }
+    for pin in &chip.outputs {
+        writeln!(out, "    \"out:{}\" [label=\"{}\", shape=cds];", pin.name, describe_decl(&pin.name, pin.width))?;
+    }
+
+    // Node and port for every driver and reader of each signal
+    let mut drivers: HashMap<String, Vec<(String, Option<String>)>> = HashMap::new();
+    let mut readers: Vec<(String, String, String)> = Vec::new();   // (signal, node:port, label)
+    let mut constants = Vec::new();
+
+    for pin in &chip.inputs {
+        drivers.entry(pin.name.clone()).or_default().push((format!("\"in:{}\"", pin.name), None));
+    }
+    for pin in &chip.outputs {
+        readers.push((pin.name.clone(), format!("\"out:{}\"", pin.name), String::new()));
+    }
+
+    for (i, part) in chip.parts.iter().enumerate() {
+        let def = lib.find(&part.chip, &part.loc).map_err(other)?;
+        let node = format!("part{}", i);
+
+        let inputs: Vec<String> = def.inputs.iter().map(|p| format!("<{0}> {0}", p.name)).collect();
+        let outputs: Vec<String> = def.outputs.iter().map(|p| format!("<{0}> {0}", p.name)).collect();
+        writeln!(out, "    {} [shape=record, label=\"{{{{{}}}|{}\\nline {}|{{{}}}}}\"];",
+                 node, inputs.join("|"), part.chip, part.loc.line, outputs.join("|"))?;
+
+        for conn in &part.connections {
+            let port = format!("{}:\"{}\"", node, conn.pin.name);
+            let is_output = def.output(&conn.pin.name).is_some();
+
+            match &conn.signal {
+                Signal::Const(value) => {
+                    let constant = format!("const{}", constants.len());
+                    constants.push(format!("    {} [label=\"{}\", shape=plaintext];", constant, value));
+                    readers.push((constant.clone(), port, describe_ref(&conn.pin)));
+                    drivers.entry(constant.clone()).or_default().push((constant, None));
+                },
+                Signal::Pin(signal) if is_output => {
+                    let label = match (conn.pin.slice, signal.slice) {
+                        (None, None) => None,
+                        _ => Some(format!("{} = {}", describe_ref(&conn.pin), describe_ref(signal)))
+                    };
+                    drivers.entry(signal.name.clone()).or_default().push((port, label));
+                },
+                Signal::Pin(signal) => readers.push((signal.name.clone(), port, describe_ref(signal)))
+            }
+        }
+    }
+
+    for constant in &constants {
+        writeln!(out, "{}", constant)?;
+    }
+    writeln!(out)?;
+
+    for (signal, reader, label) in &readers {
+        for (driver, driver_label) in drivers.get(signal).map_or(&[][..], |d| d.as_slice()) {
+            let label = driver_label.clone().unwrap_or_else(|| if label.is_empty() { signal.clone() } else { label.clone() });
+            let label = if driver.starts_with("const") { String::new() } else { label };
+            writeln!(out, "    {} -> {} [label=\"{}\"];", driver, reader, label)?;
+        }
+    }
+
+    writeln!(out, "}}")
+}
+
+fn describe_decl(name: &str, width: usize) -> String {
+    if width > 1 { format!("{}[{}]", name, width) } else { name.to_string() }
+}
+
+fn describe_ref(pin: &PinRef) -> String {
+    match pin.slice {
+        None => pin.name.clone(),
+        Some((low, high)) if low == high => format!("{}[{}]", pin.name, low),
+        Some((low, high)) => format!("{}[{}..{}]", pin.name, low, high)
+    }
+}
@@ -0,0 +1,20 @@
+// HDL simulator for the chips of projects 01-05, so their .tst scripts can be run
+// without the Java HardwareSimulator. Chips are parsed, flattened into Nand gates
+// and DFFs, and simulated one net at a time.
+
+pub mod builtins;
+pub mod error;
+pub mod export;
+pub mod library;
+pub mod lint;
+pub mod netlist;
+pub mod parser;
+pub mod report;
+pub mod simulator;
+
+pub use crate::error::HdlError;
+pub use crate::library::ChipLibrary;
+pub use crate::lint::lint;
+pub use crate::netlist::{elaborate, Netlist};
+pub use crate::parser::{parse_chip, parse_file, ChipDef};
+pub use crate::simulator::HdlSimulator;
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use assembler::Location;
+
+use crate::builtins::BUILTIN_CHIPS;
+use crate::error::HdlError;
+use crate::parser::{parse_chip, parse_file, ChipDef};
+
+// Finds chips by name. A part `Foo(...)` is looked up as Foo.hdl in each of the
+// search directories in turn, then among the built-in chips. So a chip of your
+// own is always used in place of the built-in one of the same name.
+#[derive(Default)]
+pub struct ChipLibrary {
+    dirs: Vec<PathBuf>,
+    chips: HashMap<String, Rc<ChipDef>>
+}
+
+impl ChipLibrary {
+    pub fn new() -> ChipLibrary {
+        ChipLibrary::default()
+    }
+
+    pub fn add_dir(&mut self, dir: &Path) {
+        if !self.dirs.iter().any(|d| d == dir) {
+            self.dirs.push(dir.to_path_buf());
+        }
+    }
+
+    pub fn load_file(&mut self, file: &Path) -> Result<Rc<ChipDef>, HdlError> {
+        // The directory of the chip being loaded is searched before any other
+        let dir = file.parent().unwrap_or(Path::new("")).to_path_buf();
+        self.dirs.retain(|d| *d != dir);
+        self.dirs.insert(0, dir);
+
+        let chip = Rc::new(parse_file(file)?);
+        self.chips.clear();
+        self.chips.insert(chip.name.clone(), chip.clone());
+        Ok(chip)
+    }
+
+    pub fn find(&mut self, name: &str, loc: &Location) -> Result<Rc<ChipDef>, HdlError> {
+        if let Some(chip) = self.chips.get(name) {
+            return Ok(chip.clone());
+        }
+
+        let file = self.dirs.iter().map(|d| d.join(format!("{}.hdl", name))).find(|f| f.is_file());
+        let chip = match file {
+            Some(file) => parse_file(&file)?,
+            None => match builtin_chip(name) {
+                Some(chip) => chip,
+                None => return Err(HdlError::UnknownChip { loc: loc.clone(), text: name.to_string() })
+            }
+        };
+
+        let chip = Rc::new(chip);
+        self.chips.insert(name.to_string(), chip.clone());
+        Ok(chip)
+    }
+}
+
+pub fn builtin_chip(name: &str) -> Option<ChipDef> {
+    let prefix = format!("CHIP {} {{", name);
+    let source = BUILTIN_CHIPS.iter().find(|source| source.starts_with(&prefix))?;
+    parse_chip("<builtin>", source).ok()
+}
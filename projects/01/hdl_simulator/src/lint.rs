@@ -0,0 +1,411 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use assembler::Location;
+
+use crate::builtins::model;
+use crate::error::HdlError;
+use crate::library::ChipLibrary;
+use crate::parser::{ChipDef, PinRef, Signal};
+
+// For each output pin of a chip, which of its input pins it depends on before
+// the next clock edge. Indexed [output][input] in declaration order.
+type Dependencies = Vec<Vec<bool>>;
+
+pub fn lint(chip: &ChipDef, lib: &mut ChipLibrary) -> Vec<HdlError> {
+    /*
+     *  Checks a chip and every chip it's built from, each one once, reporting all
+     *  the problems found rather than stopping at the first:
+     *
+     *  - internal pins that are read but never driven
+     *  - output pins (or bits of them) that are never assigned
+     *  - buses connected to pins of a different width
+     *  - pins driven by more than one part, and input pins driven at all
+     *  - combinational cycles, i.e. loops that don't go through a DFF or a
+     *    clocked input of a built-in chip
+     */
+    let mut linter = Linter { lib, summaries: HashMap::new(), stack: Vec::new(), errors: Vec::new() };
+    linter.chip(chip);
+
+    let mut errors = linter.errors;
+    errors.sort_by_key(|e| e.location().map(|loc| (loc.file.clone(), loc.line, loc.column)));
+    errors
+}
+
+struct Linter<'a> {
+    lib: &'a mut ChipLibrary,
+    summaries: HashMap<String, Rc<Dependencies>>,
+    stack: Vec<String>,
+    errors: Vec<HdlError>
+}
+
+// A pin of the chip being checked. Each bit of it is a node of the dependency graph.
+struct Wire {
+    name: String,
+    first: usize,
+    width: usize,
+    kind: WireKind
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum WireKind {
+    Input,
+    Output,
+    Internal
+}
+
+impl Linter<'_> {
+    fn dependencies(&mut self, chip: &ChipDef) -> Rc<Dependencies> {
+        if let Some(summary) = self.summaries.get(&chip.name) {
+            return summary.clone();
+        }
+
+        let summary = match &chip.builtin {
+            // A built-in's outputs depend on all of its inputs but the clocked ones.
+            // DFF is clocked on its only input.
+            Some(builtin) if builtin == "Nand" || builtin == "DFF" || model(builtin).is_some() => {
+                let combinational: Vec<bool> = chip.inputs.iter().map(|p| !chip.clocked.contains(&p.name)).collect();
+                vec![combinational; chip.outputs.len()]
+            },
+            Some(builtin) => {
+                self.errors.push(HdlError::NoBuiltin { loc: chip.loc.clone(), text: builtin.clone() });
+                vec![vec![false; chip.inputs.len()]; chip.outputs.len()]
+            },
+            None => self.chip(chip)
+        };
+
+        let summary = Rc::new(summary);
+        self.summaries.insert(chip.name.clone(), summary.clone());
+        summary
+    }
+
+    fn chip(&mut self, chip: &ChipDef) -> Dependencies {
+        self.stack.push(chip.name.clone());
+
+        let mut wires: Vec<Wire> = Vec::new();
+        let mut nodes = 0;
+        for (decls, kind) in [(&chip.inputs, WireKind::Input), (&chip.outputs, WireKind::Output)] {
+            for decl in decls {
+                wires.push(Wire { name: decl.name.clone(), first: nodes, width: decl.width, kind });
+                nodes += decl.width;
+            }
+        }
+
+        // Who drives each bit, and where each bit is first read
+        let mut drivers: Vec<Option<Location>> = vec![None; nodes];
+        let mut readers: Vec<Option<Location>> = vec![None; nodes];
+
+        // node -> (node, part location), through a part's combinational paths
+        let mut edges: Vec<(usize, usize, Location)> = Vec::new();
+
+        for part in &chip.parts {
+            if self.stack.contains(&part.chip) {
+                self.errors.push(HdlError::RecursiveChip { loc: part.loc.clone(), text: part.chip.clone() });
+                continue;
+            }
+            let def = match self.lib.find(&part.chip, &part.loc) {
+                Ok(def) => def,
+                Err(e) => {
+                    self.errors.push(e);
+                    continue;
+                }
+            };
+            let deps = self.dependencies(&def);
+
+            // The nodes connected to each bit of each of the part's pins
+            let mut part_inputs: Vec<Vec<Option<usize>>> = def.inputs.iter().map(|p| vec![None; p.width]).collect();
+            let mut part_outputs: Vec<Vec<Option<usize>>> = def.outputs.iter().map(|p| vec![None; p.width]).collect();
+
+            for conn in &part.connections {
+                let (bits, is_output) = match def.inputs.iter().position(|p| p.name == conn.pin.name) {
+                    Some(i) => (&mut part_inputs[i], false),
+                    None => match def.outputs.iter().position(|p| p.name == conn.pin.name) {
+                        Some(i) => (&mut part_outputs[i], true),
+                        None => {
+                            self.errors.push(HdlError::UnknownPin { loc: conn.pin.loc.clone(), text: conn.pin.name.clone() });
+                            continue;
+                        }
+                    }
+                };
+
+                let Some(range) = self.slice(bits.len(), &conn.pin) else {
+                    continue;
+                };
+                if range.clone().any(|bit| bits[bit].is_some()) && !is_output {
+                    self.errors.push(HdlError::DrivenTwice { loc: conn.loc.clone(), text: describe(&conn.pin) });
+                    continue;
+                }
+
+                let signal = match &conn.signal {
+                    Signal::Const(_) if is_output => {
+                        self.errors.push(HdlError::ConstantOutput { loc: conn.loc.clone(), text: conn.pin.name.clone() });
+                        continue;
+                    },
+                    Signal::Const(_) => {
+                        // Constants can't be part of a cycle, just mark the bits as connected
+                        for bit in range {
+                            bits[bit] = Some(usize::MAX);
+                        }
+                        continue;
+                    },
+                    Signal::Pin(signal) => signal
+                };
+
+                let wire = match wires.iter().position(|w| w.name == signal.name) {
+                    Some(w) => w,
+                    None if signal.slice.is_some() => {
+                        self.errors.push(HdlError::SlicedInternalPin { loc: signal.loc.clone(), text: signal.name.clone() });
+                        continue;
+                    },
+                    None => {
+                        // An internal pin is as wide as whatever it's first connected to
+                        wires.push(Wire { name: signal.name.clone(), first: nodes, width: range.len(), kind: WireKind::Internal });
+                        nodes += range.len();
+                        drivers.resize(nodes, None);
+                        readers.resize(nodes, None);
+                        wires.len() - 1
+                    }
+                };
+                let wire = &wires[wire];
+
+                let signal_range = if wire.kind == WireKind::Internal {
+                    Some(0..wire.width)
+                } else {
+                    self.slice(wire.width, signal)
+                };
+                let Some(signal_range) = signal_range else {
+                    continue;
+                };
+
+                if signal_range.len() != range.len() {
+                    self.errors.push(HdlError::WidthMismatch {
+                        loc: conn.loc.clone(),
+                        text: conn.pin.name.clone(),
+                        expected: range.len(),
+                        found: signal_range.len()
+                    });
+                    continue;
+                }
+
+                if is_output && wire.kind == WireKind::Input {
+                    self.errors.push(HdlError::DrivenInput { loc: signal.loc.clone(), text: signal.name.clone() });
+                    continue;
+                }
+
+                for (bit, signal_bit) in range.zip(signal_range) {
+                    let node = wire.first + signal_bit;
+                    bits[bit] = Some(node);
+
+                    if !is_output {
+                        readers[node].get_or_insert_with(|| signal.loc.clone());
+                    } else if drivers[node].is_some() {
+                        self.errors.push(HdlError::DrivenTwice { loc: signal.loc.clone(), text: describe(signal) });
+                        break;
+                    } else {
+                        drivers[node] = Some(signal.loc.clone());
+                    }
+                }
+            }
+
+            for (o, outputs) in part_outputs.iter().enumerate() {
+                for (i, inputs) in part_inputs.iter().enumerate() {
+                    if !deps[o][i] {
+                        continue;
+                    }
+                    for from in inputs.iter().flatten().filter(|n| **n != usize::MAX) {
+                        for to in outputs.iter().flatten() {
+                            edges.push((*from, *to, part.loc.clone()));
+                        }
+                    }
+                }
+            }
+        }
+
+        self.stack.pop();
+
+        for wire in &wires {
+            let bits = wire.first..wire.first + wire.width;
+            match wire.kind {
+                WireKind::Internal => {
+                    if let Some(loc) = bits.clone().find_map(|n| readers[n].clone().filter(|_| drivers[n].is_none())) {
+                        self.errors.push(HdlError::UndrivenPin { loc, text: wire.name.clone() });
+                    }
+                },
+                WireKind::Output => {
+                    let undriven: Vec<usize> = bits.filter(|&n| drivers[n].is_none()).map(|n| n - wire.first).collect();
+                    if !undriven.is_empty() {
+                        let loc = chip.output(&wire.name).map_or(chip.loc.clone(), |p| p.loc.clone());
+                        let text = if undriven.len() == wire.width {
+                            wire.name.clone()
+                        } else {
+                            format!("{}[{}]", wire.name, bit_ranges(&undriven))
+                        };
+                        self.errors.push(HdlError::UnassignedOutput { loc, text });
+                    }
+                },
+                WireKind::Input => ()
+            }
+        }
+
+        let name_of = |node: usize| {
+            let wire = wires.iter().find(|w| (w.first..w.first + w.width).contains(&node)).unwrap();
+            wire.name.clone()
+        };
+        for cycle in cycles(nodes, &edges) {
+            let mut names: Vec<String> = Vec::new();
+            for (node, _) in &cycle {
+                let name = name_of(*node);
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+            names.push(names[0].clone());
+            self.errors.push(HdlError::CombinationalCycle { loc: cycle[0].1.clone(), text: names.join(" -> ") });
+        }
+
+        // Which outputs each input reaches, for the chips this one is a part of
+        let inputs: Vec<&Wire> = wires.iter().filter(|w| w.kind == WireKind::Input).collect();
+        let outputs: Vec<&Wire> = wires.iter().filter(|w| w.kind == WireKind::Output).collect();
+        let mut deps = vec![vec![false; inputs.len()]; outputs.len()];
+
+        for (i, input) in inputs.iter().enumerate() {
+            let reached = reachable(nodes, &edges, input.first..input.first + input.width);
+            for (o, output) in outputs.iter().enumerate() {
+                deps[o][i] = (output.first..output.first + output.width).any(|n| reached[n]);
+            }
+        }
+        deps
+    }
+
+    fn slice(&mut self, width: usize, pin: &PinRef) -> Option<std::ops::Range<usize>> {
+        match pin.slice {
+            None => Some(0..width),
+            Some((low, high)) if low <= high && high < width => Some(low..high + 1),
+            Some(_) => {
+                self.errors.push(HdlError::BadSlice { loc: pin.loc.clone(), text: describe(pin) });
+                None
+            }
+        }
+    }
+}
+
+fn describe(pin: &PinRef) -> String {
+    match pin.slice {
+        None => pin.name.clone(),
+        Some((low, high)) if low == high => format!("{}[{}]", pin.name, low),
+        Some((low, high)) => format!("{}[{}..{}]", pin.name, low, high)
+    }
+}
+
+fn bit_ranges(bits: &[usize]) -> String {
+    // [0, 1, 2, 5] -> "0..2, 5"
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &bit in bits {
+        match ranges.last_mut() {
+            Some((_, high)) if *high + 1 == bit => *high = bit,
+            _ => ranges.push((bit, bit))
+        }
+    }
+    ranges.iter()
+          .map(|(low, high)| if low == high { low.to_string() } else { format!("{}..{}", low, high) })
+          .collect::<Vec<_>>()
+          .join(", ")
+}
+
+fn reachable(nodes: usize, edges: &[(usize, usize, Location)], from: std::ops::Range<usize>) -> Vec<bool> {
+    let mut reached = vec![false; nodes];
+    let mut todo: Vec<usize> = from.collect();
+    while let Some(node) = todo.pop() {
+        if reached[node] {
+            continue;
+        }
+        reached[node] = true;
+        todo.extend(edges.iter().filter(|(f, _, _)| *f == node).map(|(_, t, _)| *t));
+    }
+    reached
+}
+
+fn cycles(nodes: usize, edges: &[(usize, usize, Location)]) -> Vec<Vec<(usize, Location)>> {
+    /*
+     *  One cycle per strongly connected component (Tarjan), as the list of
+     *  nodes on it with the part each one feeds.
+     */
+    let mut out: Vec<Vec<(usize, &Location)>> = vec![Vec::new(); nodes];
+    for (from, to, loc) in edges {
+        out[*from].push((*to, loc));
+    }
+
+    struct Tarjan<'e> {
+        out: Vec<Vec<(usize, &'e Location)>>,
+        index: Vec<Option<usize>>,
+        low: Vec<usize>,
+        stack: Vec<usize>,
+        on_stack: Vec<bool>,
+        next: usize,
+        found: Vec<Vec<usize>>
+    }
+
+    impl Tarjan<'_> {
+        fn visit(&mut self, node: usize) {
+            self.index[node] = Some(self.next);
+            self.low[node] = self.next;
+            self.next += 1;
+            self.stack.push(node);
+            self.on_stack[node] = true;
+
+            for k in 0..self.out[node].len() {
+                let to = self.out[node][k].0;
+                match self.index[to] {
+                    None => {
+                        self.visit(to);
+                        self.low[node] = self.low[node].min(self.low[to]);
+                    },
+                    Some(index) if self.on_stack[to] => self.low[node] = self.low[node].min(index),
+                    _ => ()
+                }
+            }
+
+            if Some(self.low[node]) == self.index[node] {
+                let mut component = Vec::new();
+                while let Some(n) = self.stack.pop() {
+                    self.on_stack[n] = false;
+                    component.push(n);
+                    if n == node {
+                        break;
+                    }
+                }
+                let looped = component.len() > 1 || self.out[node].iter().any(|(to, _)| *to == node);
+                if looped {
+                    component.reverse();
+                    self.found.push(component);
+                }
+            }
+        }
+    }
+
+    let mut tarjan = Tarjan {
+        out,
+        index: vec![None; nodes],
+        low: vec![0; nodes],
+        stack: Vec::new(),
+        on_stack: vec![false; nodes],
+        next: 0,
+        found: Vec::new()
+    };
+    for node in 0..nodes {
+        if tarjan.index[node].is_none() {
+            tarjan.visit(node);
+        }
+    }
+
+    tarjan.found.iter().map(|component| {
+        component.iter().map(|&node| {
+            // The part this node feeds into, on the way round the cycle
+            let loc = tarjan.out[node].iter()
+                                      .find(|(to, _)| component.contains(to))
+                                      .map(|(_, loc)| (*loc).clone())
+                                      .unwrap();
+            (node, loc)
+        }).collect()
+    }).collect()
+}
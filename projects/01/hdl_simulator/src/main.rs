@@ -0,0 +1,154 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::{env, process};
+
+use cpu_emulator::Script;
+use hdl_simulator::export::{write_dot, write_verilog};
+use hdl_simulator::report::write_report;
+use hdl_simulator::{lint, ChipLibrary, HdlSimulator};
+
+// The projects with chips in them, --report looks for parts in all of them
+const HARDWARE_PROJECTS: [&str; 5] = ["01", "02", "03/a", "03/b", "05"];
+
+const USAGE: &str = "Usage: hdl_simulator [-L <dir>]... [--lint | --report | --verilog | --dot] <path-to-tst-or-hdl-file>";
+
+fn main() {
+    let mut lib = ChipLibrary::new();
+    let mut positional: Vec<String> = Vec::new();
+    let mut lint_only = false;
+    let mut report = false;
+    let mut export: Option<&str> = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-L" => match args.next() {           // Another directory to look for parts in
+                Some(dir) => lib.add_dir(&PathBuf::from(dir)),
+                None => usage_error("Expected a directory after -L")
+            },
+            "--lint" => lint_only = true,            // Check the chip instead of simulating it
+            "--report" => report = true,             // Gate counts and the critical path
+            "--verilog" => export = Some("v"),       // Write the chip out as Verilog...
+            "--dot" => export = Some("dot"),         // ...or as a Graphviz diagram of its parts
+            flag if flag.starts_with('-') => usage_error(&format!("Unknown option {}", flag)),
+            _ => positional.push(arg)
+        }
+    }
+
+    if positional.len() != 1 {
+        usage_error("Expected a test script or a chip");
+    }
+    let file = &positional[0];
+
+    if lint_only {
+        lint_chip(&mut lib, file);
+        return;
+    }
+    if let Some(extension) = export {
+        export_chip(&mut lib, file, extension);
+        return;
+    }
+    if report {
+        // After any -L, so those still come first
+        for dir in project_dirs(Path::new(file)) {
+            lib.add_dir(&dir);
+        }
+    }
+    let mut sim = HdlSimulator::new(lib);
+
+    if file.ends_with(".hdl") {
+        // Just elaborate the chip and say what it came to
+        match sim.load_chip(Path::new(file)) {
+            Ok(netlist) if report => {
+                let stdout = io::stdout();
+                if let Err(e) = write_report(&mut stdout.lock(), netlist) {
+                    exit_with_error(&e);
+                }
+            },
+            Ok(netlist) => {
+                let (nands, dffs) = netlist.count();
+                println!("CHIP {}: {} Nand, {} DFF, {} built-in parts", netlist.chip.name, nands, dffs, netlist.builtins.len());
+            },
+            Err(e) => exit_with_error(&e)
+        }
+        return;
+    }
+
+    let script = Script::from_file(file).unwrap_or_else(|e| exit_with_error(&e));
+    match script.run(&mut sim) {
+        Ok(report) => {
+            if let Some(output_file) = report.output_file {
+                println!("{} lines -> {}", report.lines, output_file.display());
+            }
+            if report.compare_file.is_some() {
+                println!("Comparison ended successfully");
+            }
+        },
+        Err(e) => exit_with_error(&e)
+    }
+}
+
+fn lint_chip(lib: &mut ChipLibrary, file: &str) {
+    let chip = lib.load_file(Path::new(file)).unwrap_or_else(|e| exit_with_error(&e));
+    let errors = lint(&chip, lib);
+
+    for e in &errors {
+        eprintln!("error: {}", e);
+    }
+    if !errors.is_empty() {
+        process::exit(-1);
+    }
+    println!("{}: no problems found", file);
+}
+
+fn export_chip(lib: &mut ChipLibrary, file: &str, extension: &str) {
+    // Written next to the chip, e.g. Mux.hdl -> Mux.v
+    let chip = lib.load_file(Path::new(file)).unwrap_or_else(|e| exit_with_error(&e));
+    let out_path = Path::new(file).with_extension(extension);
+    let out = File::create(&out_path).unwrap_or_else(|e| exit_with_error(&e));
+
+    let mut out = BufWriter::new(out);
+    let result = match extension {
+        "v" => write_verilog(&mut out, &chip, lib),
+        _ => write_dot(&mut out, &chip, lib)
+    };
+    if let Err(e) = result {
+        exit_with_error(&e);
+    }
+    println!("{} -> {}", file, out_path.display());
+}
+
+fn project_dirs(chip: &Path) -> Vec<PathBuf> {
+    /*
+     *  The hardware projects next to the one the chip is in, e.g. 02/ALU.hdl -> 02/../01,
+     *  02/../02, 02/../03/a... Relative to the chip like a -L would be, so the report
+     *  shows short paths. Nothing for a chip that isn't in a projects/NN directory.
+     */
+    let dir = chip.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let Ok(absolute) = dir.canonicalize() else {
+        return Vec::new();
+    };
+    let is_project = |dir: &Path| dir.file_name()
+                                      .and_then(|name| name.to_str())
+                                      .is_some_and(|name| name.len() == 2 && name.bytes().all(|b| b.is_ascii_digit()));
+    let Some(depth) = absolute.ancestors().position(is_project) else {
+        return Vec::new();
+    };
+
+    let mut projects = dir.to_path_buf();
+    for _ in 0..=depth {
+        projects.push("..");
+    }
+    HARDWARE_PROJECTS.iter().map(|project| projects.join(project)).filter(|dir| dir.is_dir()).collect()
+}
+
+fn exit_with_error(e: &dyn std::error::Error) -> ! {
+    eprintln!("error: {}", e);
+    process::exit(-1);
+}
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("{}\n{}", msg, USAGE);
+    process::exit(-1);
+}
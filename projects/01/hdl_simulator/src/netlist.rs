@@ -0,0 +1,366 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use assembler::Location;
+
+use crate::builtins::model;
+use crate::error::HdlError;
+use crate::library::ChipLibrary;
+use crate::parser::{ChipDef, PinDecl, PinRef, Signal};
+
+// A single wire. Nets 0 and 1 are the constants false and true.
+pub type Net = usize;
+
+pub const FALSE: Net = 0;
+pub const TRUE: Net  = 1;
+
+#[derive(Debug, Clone)]
+pub enum Gate {
+    Nand { a: Net, b: Net, out: Net },
+    Dff { input: Net, out: Net },
+    Builtin { part: usize },
+}
+
+// A part simulated by a BuiltinChip model, with the nets of each of its pins
+#[derive(Debug, Clone)]
+pub struct BuiltinPart {
+    pub chip: String,
+    pub builtin: String,
+    pub inputs: Vec<Vec<Net>>,
+    pub outputs: Vec<Vec<Net>>,
+
+    // Which inputs are CLOCKED, and so don't affect the outputs until the next tock
+    pub clocked: Vec<bool>
+}
+
+// One part in the chip hierarchy. Instance 0 is the chip itself.
+#[derive(Debug, Clone)]
+pub struct Instance {
+    pub chip: String,
+    pub parent: Option<usize>,
+    pub loc: Location
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinKind {
+    Input,
+    Output,
+    Internal
+}
+
+// A pin of the top-level chip and the nets of its bits, least significant first
+#[derive(Debug, Clone)]
+pub struct Pin {
+    pub name: String,
+    pub kind: PinKind,
+    pub nets: Vec<Net>
+}
+
+// A chip flattened down to Nand gates, DFFs and built-in parts
+pub struct Netlist {
+    pub chip: Rc<ChipDef>,
+    pub pins: Vec<Pin>,
+    pub gates: Vec<Gate>,
+    pub builtins: Vec<BuiltinPart>,
+    pub net_count: usize,
+
+    // The part each gate is, e.g. the Nand inside the Not inside a Mux
+    pub instances: Vec<Instance>,
+    pub origins: Vec<usize>,
+
+    // Nand gates and built-in parts in an order where each one comes after the
+    // gates driving it. Gates in a combinational loop can't be ordered and are
+    // left in `looped`.
+    pub order: Vec<usize>,
+    pub looped: Vec<usize>
+}
+
+impl Netlist {
+    pub fn pin(&self, name: &str) -> Option<&Pin> {
+        self.pins.iter().find(|p| p.name == name)
+    }
+
+    pub fn count(&self) -> (usize, usize) {
+        // (Nand gates, DFFs), not counting built-in parts
+        let nands = self.gates.iter().filter(|g| matches!(g, Gate::Nand { .. })).count();
+        let dffs = self.gates.iter().filter(|g| matches!(g, Gate::Dff { .. })).count();
+        (nands, dffs)
+    }
+
+    pub fn builtin(&self, chip: &str) -> Option<usize> {
+        // The first built-in part of a kind, which is what `RAM16K[0]` or `DRegister[]` refer to
+        self.builtins.iter().position(|b| b.chip == chip)
+    }
+
+    pub fn combinational_inputs(&self, gate: &Gate) -> Vec<Net> {
+        // The nets a gate's outputs depend on before the next clock edge
+        match gate {
+            Gate::Nand { a, b, .. } => vec![*a, *b],
+            Gate::Dff { .. } => Vec::new(),
+            Gate::Builtin { part } => {
+                let part = &self.builtins[*part];
+                part.inputs.iter()
+                           .zip(&part.clocked)
+                           .filter(|(_, clocked)| !**clocked)
+                           .flat_map(|(nets, _)| nets.iter().copied())
+                           .collect()
+            }
+        }
+    }
+
+    pub fn outputs(&self, gate: &Gate) -> Vec<Net> {
+        match gate {
+            Gate::Nand { out, .. } | Gate::Dff { out, .. } => vec![*out],
+            Gate::Builtin { part } => self.builtins[*part].outputs.concat()
+        }
+    }
+}
+
+pub fn elaborate(chip: Rc<ChipDef>, lib: &mut ChipLibrary) -> Result<Netlist, HdlError> {
+    /*
+     *  Every pin of every part gets fresh nets, and each `pin=signal` connection
+     *  merges the nets on its two sides (union-find). Once the whole hierarchy has
+     *  been instantiated the merged nets are numbered densely, so the simulator
+     *  only ever sees Nand gates, DFFs and built-in parts wired to plain net numbers.
+     */
+    let mut elab = Elaborator {
+        lib,
+        parent: vec![FALSE, TRUE],
+        gates: Vec::new(),
+        builtins: Vec::new(),
+        instances: vec![Instance { chip: chip.name.clone(), parent: None, loc: chip.loc.clone() }],
+        origins: Vec::new(),
+        stack: vec![chip.name.clone()]
+    };
+
+    let mut io = Vec::new();
+    for (decl, kind) in chip.inputs.iter().map(|p| (p, PinKind::Input))
+                           .chain(chip.outputs.iter().map(|p| (p, PinKind::Output))) {
+        let nets = elab.new_nets(decl.width);
+        io.push(Pin { name: decl.name.clone(), kind, nets });
+    }
+
+    let internal = elab.instantiate(&chip, &io, 0)?;
+    io.extend(internal.into_iter().map(|(name, nets)| Pin { name, kind: PinKind::Internal, nets }));
+
+    // Number the merged nets 0, 1, 2, ... keeping the constants at 0 and 1
+    let mut ids = HashMap::from([(FALSE, FALSE), (TRUE, TRUE)]);
+    let mut id = |elab: &mut Elaborator, net: Net| {
+        let root = elab.find(net);
+        let next = ids.len();
+        *ids.entry(root).or_insert(next)
+    };
+
+    let mut gates = Vec::new();
+    for gate in elab.gates.clone() {
+        gates.push(match gate {
+            Gate::Nand { a, b, out } => Gate::Nand { a: id(&mut elab, a), b: id(&mut elab, b), out: id(&mut elab, out) },
+            Gate::Dff { input, out } => Gate::Dff { input: id(&mut elab, input), out: id(&mut elab, out) },
+            Gate::Builtin { part } => Gate::Builtin { part }
+        });
+    }
+    let mut builtins = elab.builtins.clone();
+    for part in builtins.iter_mut() {
+        for net in part.inputs.iter_mut().chain(part.outputs.iter_mut()).flatten() {
+            *net = id(&mut elab, *net);
+        }
+    }
+    for pin in io.iter_mut() {
+        for net in pin.nets.iter_mut() {
+            *net = id(&mut elab, *net);
+        }
+    }
+
+    let mut netlist = Netlist {
+        chip,
+        pins: io,
+        gates,
+        builtins,
+        net_count: ids.len(),
+        instances: elab.instances,
+        origins: elab.origins,
+        order: Vec::new(),
+        looped: Vec::new()
+    };
+    (netlist.order, netlist.looped) = evaluation_order(&netlist);
+    Ok(netlist)
+}
+
+struct Elaborator<'a> {
+    lib: &'a mut ChipLibrary,
+    parent: Vec<Net>,
+    gates: Vec<Gate>,
+    builtins: Vec<BuiltinPart>,
+    instances: Vec<Instance>,
+    origins: Vec<usize>,
+    stack: Vec<String>
+}
+
+impl Elaborator<'_> {
+    fn new_nets(&mut self, width: usize) -> Vec<Net> {
+        let first = self.parent.len();
+        self.parent.extend(first..first + width);
+        (first..first + width).collect()
+    }
+
+    fn find(&mut self, mut net: Net) -> Net {
+        while self.parent[net] != net {
+            self.parent[net] = self.parent[self.parent[net]];
+            net = self.parent[net];
+        }
+        net
+    }
+
+    fn union(&mut self, a: Net, b: Net) {
+        // The lower net becomes the root, so the constants stay at 0 and 1
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.parent[a.max(b)] = a.min(b);
+        }
+    }
+
+    fn instantiate(&mut self, chip: &ChipDef, io: &[Pin], instance: usize) -> Result<Vec<(String, Vec<Net>)>, HdlError> {
+        // Returns the chip's internal pins
+        let nets = |name: &str| io.iter().find(|p| p.name == name).map(|p| p.nets.clone());
+
+        if let Some(builtin) = &chip.builtin {
+            let bit = |name: &str| nets(name).map_or(FALSE, |n| n[0]);
+            match builtin.as_str() {
+                "Nand" => self.gates.push(Gate::Nand { a: bit("a"), b: bit("b"), out: bit("out") }),
+                "DFF"  => self.gates.push(Gate::Dff { input: bit("in"), out: bit("out") }),
+                _ if model(builtin).is_some() => {
+                    let pin_nets = |decls: &[PinDecl]| decls.iter().map(|d| nets(&d.name).unwrap_or_default()).collect();
+                    self.gates.push(Gate::Builtin { part: self.builtins.len() });
+                    self.builtins.push(BuiltinPart {
+                        chip: chip.name.clone(),
+                        builtin: builtin.clone(),
+                        inputs: pin_nets(&chip.inputs),
+                        outputs: pin_nets(&chip.outputs),
+                        clocked: chip.inputs.iter().map(|d| chip.clocked.contains(&d.name)).collect()
+                    });
+                },
+                _ => return Err(HdlError::NoBuiltin { loc: chip.loc.clone(), text: builtin.clone() })
+            }
+            self.origins.push(instance);
+            return Ok(Vec::new());
+        }
+
+        let mut internal: Vec<(String, Vec<Net>)> = Vec::new();
+
+        for part in &chip.parts {
+            if self.stack.contains(&part.chip) {
+                return Err(HdlError::RecursiveChip { loc: part.loc.clone(), text: part.chip.clone() });
+            }
+            let def = self.lib.find(&part.chip, &part.loc)?;
+
+            let mut part_io = Vec::new();
+            for (decl, kind) in def.inputs.iter().map(|p| (p, PinKind::Input))
+                                  .chain(def.outputs.iter().map(|p| (p, PinKind::Output))) {
+                let nets = self.new_nets(decl.width);
+                part_io.push(Pin { name: decl.name.clone(), kind, nets });
+            }
+
+            for conn in &part.connections {
+                let pin = part_io.iter()
+                                 .find(|p| p.name == conn.pin.name)
+                                 .ok_or_else(|| HdlError::UnknownPin { loc: conn.pin.loc.clone(), text: conn.pin.name.clone() })?;
+                let pin_bits = slice(&pin.nets, &conn.pin)?;
+
+                let signal_bits = match &conn.signal {
+                    Signal::Const(_) if pin.kind == PinKind::Output => {
+                        return Err(HdlError::ConstantOutput { loc: conn.loc.clone(), text: conn.pin.name.clone() });
+                    },
+                    Signal::Const(value) => vec![if *value { TRUE } else { FALSE }; pin_bits.len()],
+                    Signal::Pin(signal) => match nets(&signal.name) {
+                        Some(nets) => slice(&nets, signal)?,
+                        None if signal.slice.is_some() => {
+                            return Err(HdlError::SlicedInternalPin { loc: signal.loc.clone(), text: signal.name.clone() });
+                        },
+                        None => match internal.iter().find(|(name, _)| *name == signal.name) {
+                            Some((_, nets)) => nets.clone(),
+                            None => {
+                                // An internal pin is as wide as whatever it's first connected to
+                                let nets = self.new_nets(pin_bits.len());
+                                internal.push((signal.name.clone(), nets.clone()));
+                                nets
+                            }
+                        }
+                    }
+                };
+
+                if signal_bits.len() != pin_bits.len() {
+                    return Err(HdlError::WidthMismatch {
+                        loc: conn.loc.clone(),
+                        text: conn.pin.name.clone(),
+                        expected: pin_bits.len(),
+                        found: signal_bits.len()
+                    });
+                }
+                for (a, b) in pin_bits.iter().zip(signal_bits) {
+                    self.union(*a, b);
+                }
+            }
+
+            self.instances.push(Instance { chip: part.chip.clone(), parent: Some(instance), loc: part.loc.clone() });
+            self.stack.push(part.chip.clone());
+            self.instantiate(&def, &part_io, self.instances.len() - 1)?;
+            self.stack.pop();
+        }
+
+        Ok(internal)
+    }
+}
+
+fn slice(nets: &[Net], pin: &PinRef) -> Result<Vec<Net>, HdlError> {
+    // a, a[i] or a[i..j]
+    match pin.slice {
+        None => Ok(nets.to_vec()),
+        Some((low, high)) if low <= high && high < nets.len() => Ok(nets[low..=high].to_vec()),
+        Some((low, high)) => {
+            let text = if low == high { format!("{}[{}]", pin.name, low) } else { format!("{}[{}..{}]", pin.name, low, high) };
+            Err(HdlError::BadSlice { loc: pin.loc.clone(), text })
+        }
+    }
+}
+
+fn evaluation_order(netlist: &Netlist) -> (Vec<usize>, Vec<usize>) {
+    // Topological sort of the combinational gates. DFF outputs, clocked parts,
+    // inputs and constants are where the ordering starts from.
+    let gates = &netlist.gates;
+    let is_combinational = |i: usize| !matches!(gates[i], Gate::Dff { .. });
+
+    let mut driver = vec![None; netlist.net_count];
+    for (i, gate) in gates.iter().enumerate() {
+        if is_combinational(i) {
+            for out in netlist.outputs(gate) {
+                driver[out] = Some(i);
+            }
+        }
+    }
+
+    let mut readers = vec![Vec::new(); gates.len()];
+    let mut waiting = vec![0; gates.len()];
+    for (i, gate) in gates.iter().enumerate() {
+        for input in netlist.combinational_inputs(gate) {
+            if let Some(d) = driver[input] {
+                readers[d].push(i);
+                waiting[i] += 1;
+            }
+        }
+    }
+
+    let mut order: Vec<usize> = (0..gates.len()).filter(|&i| is_combinational(i) && waiting[i] == 0).collect();
+    let mut next = 0;
+    while next < order.len() {
+        for &reader in &readers[order[next]] {
+            waiting[reader] -= 1;
+            if waiting[reader] == 0 {
+                order.push(reader);
+            }
+        }
+        next += 1;
+    }
+
+    let looped = (0..gates.len()).filter(|&i| is_combinational(i) && waiting[i] > 0).collect();
+    (order, looped)
+}
@@ -0,0 +1,337 @@
+use std::fs;
+use std::path::Path;
+
+use assembler::Location;
+
+use crate::error::HdlError;
+
+// `name` or `name[width]` in an IN or OUT list
+#[derive(Debug, Clone)]
+pub struct PinDecl {
+    pub name: String,
+    pub width: usize,
+    pub loc: Location
+}
+
+// `name`, `name[i]` or `name[i..j]` on either side of a connection
+#[derive(Debug, Clone)]
+pub struct PinRef {
+    pub name: String,
+    pub slice: Option<(usize, usize)>,
+    pub loc: Location
+}
+
+#[derive(Debug, Clone)]
+pub enum Signal {
+    Pin(PinRef),
+    Const(bool)
+}
+
+// `pin=signal` inside a part, where `pin` belongs to the part
+#[derive(Debug, Clone)]
+pub struct Connection {
+    pub pin: PinRef,
+    pub signal: Signal,
+    pub loc: Location
+}
+
+#[derive(Debug, Clone)]
+pub struct Part {
+    pub chip: String,
+    pub connections: Vec<Connection>,
+    pub loc: Location
+}
+
+// A parsed CHIP. Built-in chips have `builtin` set and no parts.
+#[derive(Debug, Clone)]
+pub struct ChipDef {
+    pub name: String,
+    pub inputs: Vec<PinDecl>,
+    pub outputs: Vec<PinDecl>,
+    pub parts: Vec<Part>,
+    pub builtin: Option<String>,
+    pub clocked: Vec<String>,
+    pub loc: Location
+}
+
+impl ChipDef {
+    pub fn input(&self, name: &str) -> Option<&PinDecl> {
+        self.inputs.iter().find(|p| p.name == name)
+    }
+
+    pub fn output(&self, name: &str) -> Option<&PinDecl> {
+        self.outputs.iter().find(|p| p.name == name)
+    }
+
+    pub fn pin(&self, name: &str) -> Option<&PinDecl> {
+        self.input(name).or_else(|| self.output(name))
+    }
+}
+
+struct Token {
+    text: String,
+    loc: Location
+}
+
+pub fn parse_file(file: &Path) -> Result<ChipDef, HdlError> {
+    let name = file.display().to_string();
+    let source = fs::read_to_string(file).map_err(|error| HdlError::Io { file: name.clone(), error })?;
+    let chip = parse_chip(&name, &source)?;
+
+    // Like the Java simulator, Foo.hdl has to contain CHIP Foo
+    let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+    if chip.name != stem {
+        return Err(HdlError::WrongChipName { loc: chip.loc.clone(), text: chip.name, expected: stem.to_string() });
+    }
+    Ok(chip)
+}
+
+pub fn parse_chip(file: &str, source: &str) -> Result<ChipDef, HdlError> {
+    /*
+     *  CHIP Name {
+     *      IN  a[16], b, ...;
+     *      OUT out[16], ...;
+     *      PARTS:
+     *      Part(pin=signal, pin[i..j]=signal[k], pin=true, ...);
+     *      ...
+     *  }
+     *
+     *  Instead of PARTS a chip can be `BUILTIN Name;`, optionally followed by
+     *  `CLOCKED pin, ...;`, as in tools/builtInChips.
+     */
+    let tokens = tokenize(file, source)?;
+    let mut parser = Parser { tokens, pos: 0, file };
+
+    let loc = parser.expect("CHIP")?;
+    let name = parser.identifier()?;
+    parser.expect("{")?;
+
+    let inputs = parser.pin_list("IN")?;
+    let outputs = parser.pin_list("OUT")?;
+
+    let mut chip = ChipDef { name, inputs, outputs, parts: Vec::new(), builtin: None, clocked: Vec::new(), loc };
+
+    if parser.accept("BUILTIN") {
+        chip.builtin = Some(parser.identifier()?);
+        parser.expect(";")?;
+        if parser.accept("CLOCKED") {
+            chip.clocked = parser.name_list()?;
+        }
+    } else {
+        parser.expect("PARTS")?;
+        parser.expect(":")?;
+        while !parser.peek("}") {
+            chip.parts.push(parser.part()?);
+        }
+    }
+
+    parser.expect("}")?;
+    if let Some(token) = parser.tokens.get(parser.pos) {
+        return Err(HdlError::Syntax { loc: token.loc.clone(), text: format!("unexpected '{}' after the chip", token.text) });
+    }
+    Ok(chip)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    file: &'a str
+}
+
+impl Parser<'_> {
+    fn peek(&self, text: &str) -> bool {
+        self.tokens.get(self.pos).is_some_and(|t| t.text == text)
+    }
+
+    fn accept(&mut self, text: &str) -> bool {
+        let found = self.peek(text);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn next(&mut self, expected: &str) -> Result<&Token, HdlError> {
+        match self.tokens.get(self.pos) {
+            Some(token) => {
+                self.pos += 1;
+                Ok(token)
+            },
+            None => {
+                let loc = self.tokens.last().map_or(Location::new(self.file, 1, 1), |t| t.loc.clone());
+                Err(HdlError::Syntax { loc, text: format!("expected {} but the file ended", expected) })
+            }
+        }
+    }
+
+    fn expect(&mut self, text: &str) -> Result<Location, HdlError> {
+        let token = self.next(&format!("'{}'", text))?;
+        if token.text == text {
+            Ok(token.loc.clone())
+        } else {
+            Err(HdlError::Syntax { loc: token.loc.clone(), text: format!("expected '{}' but found '{}'", text, token.text) })
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String, HdlError> {
+        let token = self.next("a name")?;
+        if token.text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            Ok(token.text.clone())
+        } else {
+            Err(HdlError::Syntax { loc: token.loc.clone(), text: format!("expected a name but found '{}'", token.text) })
+        }
+    }
+
+    fn number(&mut self) -> Result<usize, HdlError> {
+        let token = self.next("a number")?;
+        token.text.parse().map_err(|_| HdlError::Syntax {
+            loc: token.loc.clone(),
+            text: format!("expected a number but found '{}'", token.text)
+        })
+    }
+
+    fn name_list(&mut self) -> Result<Vec<String>, HdlError> {
+        // name, name, ... ;
+        let mut names = vec![self.identifier()?];
+        while self.accept(",") {
+            names.push(self.identifier()?);
+        }
+        self.expect(";")?;
+        Ok(names)
+    }
+
+    fn pin_list(&mut self, keyword: &str) -> Result<Vec<PinDecl>, HdlError> {
+        // IN a, b[16], ... ;  The whole list is optional
+        let mut pins = Vec::new();
+        if !self.accept(keyword) {
+            return Ok(pins);
+        }
+
+        loop {
+            let loc = self.tokens.get(self.pos).map(|t| t.loc.clone());
+            let name = self.identifier()?;
+            let width = if self.accept("[") {
+                let width = self.number()?;
+                self.expect("]")?;
+                width
+            } else {
+                1
+            };
+
+            let loc = loc.unwrap_or_else(|| Location::new(self.file, 1, 1));
+            if width == 0 || width > 16 {
+                return Err(HdlError::Syntax { loc, text: format!("pin '{}' must be 1 to 16 bits wide", name) });
+            }
+            pins.push(PinDecl { name, width, loc });
+
+            if !self.accept(",") {
+                break;
+            }
+        }
+        self.expect(";")?;
+        Ok(pins)
+    }
+
+    fn pin_ref(&mut self) -> Result<PinRef, HdlError> {
+        let loc = self.tokens.get(self.pos).map(|t| t.loc.clone()).unwrap_or_else(|| Location::new(self.file, 1, 1));
+        let name = self.identifier()?;
+
+        let slice = if self.accept("[") {
+            let low = self.number()?;
+            let high = if self.accept("..") { self.number()? } else { low };
+            self.expect("]")?;
+            Some((low, high))
+        } else {
+            None
+        };
+
+        Ok(PinRef { name, slice, loc })
+    }
+
+    fn part(&mut self) -> Result<Part, HdlError> {
+        // Chip(pin=signal, ...);
+        let loc = self.tokens.get(self.pos).map(|t| t.loc.clone()).unwrap_or_else(|| Location::new(self.file, 1, 1));
+        let chip = self.identifier()?;
+        self.expect("(")?;
+
+        let mut connections = Vec::new();
+        loop {
+            let pin = self.pin_ref()?;
+            self.expect("=")?;
+
+            let signal = if self.accept("true") {
+                Signal::Const(true)
+            } else if self.accept("false") {
+                Signal::Const(false)
+            } else {
+                Signal::Pin(self.pin_ref()?)
+            };
+
+            connections.push(Connection { loc: pin.loc.clone(), pin, signal });
+            if !self.accept(",") {
+                break;
+            }
+        }
+
+        self.expect(")")?;
+        self.expect(";")?;
+        Ok(Part { chip, connections, loc })
+    }
+}
+
+fn tokenize(file: &str, source: &str) -> Result<Vec<Token>, HdlError> {
+    /*
+     *  Names, numbers, the punctuation { } ( ) [ ] , ; : = and '..'
+     *  Comments are // to the end of the line, /* ... */ and /** ... */
+     */
+    let mut tokens = Vec::new();
+
+    let chars: Vec<char> = source.chars().collect();
+    let (mut i, mut line, mut line_start) = (0, 1, 0);
+
+    while i < chars.len() {
+        let c = chars[i];
+        let loc = Location::new(file, line, i - line_start + 1);
+
+        if c == '\n' {
+            line += 1;
+            line_start = i + 1;
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if chars[i..].starts_with(&['/', '/']) {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if chars[i..].starts_with(&['/', '*']) {
+            i += 2;
+            while i < chars.len() && !chars[i..].starts_with(&['*', '/']) {
+                if chars[i] == '\n' {
+                    line += 1;
+                    line_start = i + 1;
+                }
+                i += 1;
+            }
+            if i >= chars.len() {
+                return Err(HdlError::Syntax { loc, text: "unterminated comment".to_string() });
+            }
+            i += 2;
+        } else if chars[i..].starts_with(&['.', '.']) {
+            tokens.push(Token { text: "..".to_string(), loc });
+            i += 2;
+        } else if "{}()[],;:=".contains(c) {
+            tokens.push(Token { text: c.to_string(), loc });
+            i += 1;
+        } else if c.is_ascii_alphanumeric() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token { text: chars[start..i].iter().collect(), loc });
+        } else {
+            return Err(HdlError::Syntax { loc, text: format!("unexpected character '{}'", c) });
+        }
+    }
+
+    Ok(tokens)
+}
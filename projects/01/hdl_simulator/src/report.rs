@@ -0,0 +1,172 @@
+use std::io::{Error, Write};
+
+use crate::netlist::{Gate, Net, Netlist, FALSE, TRUE};
+
+// The Nand gates and DFFs one part of the chip elaborated to
+pub struct PartCount {
+    pub instance: usize,
+    pub nands: usize,
+    pub dffs: usize,
+    pub builtins: usize
+}
+
+pub fn count_parts(netlist: &Netlist) -> Vec<PartCount> {
+    // One entry per part in the chip's PARTS section, in order
+    let mut parts: Vec<PartCount> = netlist.instances.iter()
+                                                     .enumerate()
+                                                     .filter(|(_, i)| i.parent == Some(0))
+                                                     .map(|(instance, _)| PartCount { instance, nands: 0, dffs: 0, builtins: 0 })
+                                                     .collect();
+
+    for (gate, &origin) in netlist.gates.iter().zip(&netlist.origins) {
+        let Some(part) = parts.iter_mut().find(|p| p.instance == top_level_part(netlist, origin)) else {
+            continue;
+        };
+        match gate {
+            Gate::Nand { .. } => part.nands += 1,
+            Gate::Dff { .. } => part.dffs += 1,
+            Gate::Builtin { .. } => part.builtins += 1
+        }
+    }
+    parts
+}
+
+// The gates on the longest combinational path, in order, and the nets it starts and ends on
+pub struct CriticalPath {
+    pub gates: Vec<usize>,
+    pub from: Net,
+    pub to: Net
+}
+
+pub fn critical_path(netlist: &Netlist) -> CriticalPath {
+    /*
+     *  The longest chain of Nand gates between a chip input or DFF output and
+     *  anything else, as the gates on it in order. Each Nand is one gate delay.
+     *  Built-in parts other than Nand count as no delay, since there's no way of
+     *  knowing how they'd be built.
+     */
+    let mut arrival = vec![0; netlist.net_count];
+    let mut through: Vec<Option<(usize, Net)>> = vec![None; netlist.net_count];
+
+    for &g in &netlist.order {
+        let gate = &netlist.gates[g];
+        let delay = matches!(gate, Gate::Nand { .. }) as usize;
+
+        // Ties go to real signals rather than the constants
+        let inputs = netlist.combinational_inputs(gate);
+        let Some(latest) = inputs.into_iter().max_by_key(|&n| (arrival[n], n > TRUE)) else {
+            continue;
+        };
+        for out in netlist.outputs(gate) {
+            arrival[out] = arrival[latest] + delay;
+            through[out] = Some((g, latest));
+        }
+    }
+
+    let to = (0..netlist.net_count).max_by_key(|&n| arrival[n]).unwrap_or(0);
+    let mut gates = Vec::new();
+    let mut from = to;
+    while let Some((gate, input)) = through[from] {
+        gates.push(gate);
+        from = input;
+    }
+    gates.reverse();
+    CriticalPath { gates, from, to }
+}
+
+pub fn write_report(out: &mut dyn Write, netlist: &Netlist) -> Result<(), Error> {
+    let parts = count_parts(netlist);
+    let (nands, dffs) = netlist.count();
+    let builtins = netlist.builtins.len();
+
+    writeln!(out, "CHIP {}: {} Nand, {} DFF", netlist.chip.name, nands, dffs)?;
+    if builtins > 0 {
+        writeln!(out, "{} built-in part(s) weren't elaborated and aren't counted, use -L to find their .hdl", builtins)?;
+    }
+    writeln!(out)?;
+
+    writeln!(out, "{:<20} {:<24} {:>7} {:>6}", "Part", "Location", "Nand", "DFF")?;
+    for part in &parts {
+        let instance = &netlist.instances[part.instance];
+        let loc = format!("{}:{}", instance.loc.file, instance.loc.line);
+        let nands = if part.builtins > 0 { "built-in".to_string() } else { part.nands.to_string() };
+        writeln!(out, "{:<20} {:<24} {:>7} {:>6}", instance.chip, loc, nands, part.dffs)?;
+    }
+    writeln!(out, "{:<20} {:<24} {:>7} {:>6}", "Total", "", nands, dffs)?;
+    writeln!(out)?;
+
+    /*
+     *  The critical path is shown a part at a time, e.g.
+     *
+     *        4  Mux16 > Mux > Or          ALU.hdl:30
+     *
+     *  is 4 gate delays in the Mux16 on line 30, starting in the Or of one of its Muxes.
+     */
+    let critical = critical_path(netlist);
+    let path = &critical.gates;
+    let delays = path.iter().filter(|&&g| matches!(netlist.gates[g], Gate::Nand { .. })).count();
+    if delays == 0 {
+        return writeln!(out, "Critical path: there are no Nand gates to go through");
+    }
+    writeln!(out, "Critical path: {} gate delay(s) from {} to {}",
+             delays, net_name(netlist, critical.from), net_name(netlist, critical.to))?;
+
+    let mut i = 0;
+    while i < path.len() {
+        let part = top_level_part(netlist, netlist.origins[path[i]]);
+        let len = path[i..].iter().take_while(|&&g| top_level_part(netlist, netlist.origins[g]) == part).count();
+        let delays = path[i..i + len].iter().filter(|&&g| matches!(netlist.gates[g], Gate::Nand { .. })).count();
+
+        let instance = &netlist.instances[part];
+        writeln!(out, "{:>7}  {:<40} {}:{}", delays, chain(netlist, netlist.origins[path[i]]), instance.loc.file, instance.loc.line)?;
+        i += len;
+    }
+    Ok(())
+}
+
+fn top_level_part(netlist: &Netlist, mut instance: usize) -> usize {
+    // The part of the chip's own PARTS section an instance is inside of
+    while let Some(parent) = netlist.instances[instance].parent {
+        if parent == 0 {
+            return instance;
+        }
+        instance = parent;
+    }
+    instance
+}
+
+fn chain(netlist: &Netlist, instance: usize) -> String {
+    // "Mux16 > Mux > Or" for a Nand inside an Or inside a Mux inside a Mux16
+    let mut chips = Vec::new();
+    let mut next = netlist.instances[instance].parent;
+    let mut instance = instance;
+    while let Some(parent) = next {
+        chips.push(netlist.instances[instance].chip.as_str());
+        instance = parent;
+        next = netlist.instances[parent].parent;
+    }
+    if chips.len() > 1 && chips[0] == "Nand" {
+        chips.remove(0);
+    }
+    chips.reverse();
+    chips.join(" > ")
+}
+
+fn net_name(netlist: &Netlist, net: Net) -> String {
+    for pin in &netlist.pins {
+        if let Some(bit) = pin.nets.iter().position(|&n| n == net) {
+            return if pin.nets.len() == 1 { pin.name.clone() } else { format!("{}[{}]", pin.name, bit) };
+        }
+    }
+    if net == FALSE || net == TRUE {
+        return (net == TRUE).to_string();
+    }
+    let is_dff = netlist.gates.iter().any(|g| matches!(g, Gate::Dff { input, out } if *input == net || *out == net));
+    if is_dff {
+        return "a DFF".to_string();
+    }
+    match netlist.builtins.iter().find(|b| b.inputs.iter().chain(&b.outputs).flatten().any(|&n| n == net)) {
+        Some(builtin) => format!("a built-in {}", builtin.chip),
+        None => "an internal net".to_string()
+    }
+}
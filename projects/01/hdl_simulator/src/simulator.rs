@@ -0,0 +1,201 @@
+use std::path::Path;
+
+use cpu_emulator::test_script::{split_index, Simulator};
+
+use crate::builtins::{model, BuiltinChip};
+use crate::error::HdlError;
+use crate::library::ChipLibrary;
+use crate::netlist::{elaborate, Gate, Net, Netlist, PinKind, TRUE};
+
+// Gives up on a combinational loop that hasn't settled after this many passes
+const MAX_LOOP_PASSES: usize = 100;
+
+// Simulates an elaborated chip one net at a time
+pub struct HdlSimulator {
+    pub lib: ChipLibrary,
+    netlist: Option<Netlist>,
+    values: Vec<bool>,
+    latched: Vec<bool>,
+    models: Vec<Box<dyn BuiltinChip>>
+}
+
+impl HdlSimulator {
+    pub fn new(lib: ChipLibrary) -> HdlSimulator {
+        HdlSimulator { lib, netlist: None, values: Vec::new(), latched: Vec::new(), models: Vec::new() }
+    }
+
+    pub fn load_chip(&mut self, file: &Path) -> Result<&Netlist, HdlError> {
+        let chip = self.lib.load_file(file)?;
+        let netlist = elaborate(chip, &mut self.lib)?;
+
+        self.values = vec![false; netlist.net_count];
+        self.values[TRUE] = true;
+        self.latched = vec![false; netlist.gates.len()];
+        self.models = netlist.builtins.iter()
+                                      .filter_map(|part| model(&part.builtin))
+                                      .collect();
+        Ok(self.netlist.insert(netlist))
+    }
+
+    pub fn netlist(&self) -> Option<&Netlist> {
+        self.netlist.as_ref()
+    }
+
+    fn loaded(&self) -> Result<&Netlist, String> {
+        self.netlist.as_ref().ok_or_else(|| "no chip has been loaded".to_string())
+    }
+
+    pub fn evaluate(&mut self) {
+        let Some(netlist) = &self.netlist else {
+            return;
+        };
+
+        for &i in &netlist.order {
+            update(netlist, &netlist.gates[i], &mut self.values, &mut self.models);
+        }
+
+        // Gates in a loop (latches made of Nands) are re-evaluated until they settle,
+        // along with everything after them
+        for _ in 0..MAX_LOOP_PASSES {
+            let mut changed = false;
+            for &i in &netlist.looped {
+                changed |= update(netlist, &netlist.gates[i], &mut self.values, &mut self.models);
+            }
+            if !changed {
+                break;
+            }
+            for &i in &netlist.order {
+                update(netlist, &netlist.gates[i], &mut self.values, &mut self.models);
+            }
+        }
+    }
+
+    fn builtin_part(&mut self, var: &str) -> Result<(&mut Box<dyn BuiltinChip>, usize), String> {
+        // `RAM16K[5]` or `DRegister[]` name the memory of the first built-in part of that chip
+        let (name, index) = split_index(var);
+        let part = index.and_then(|_| self.loaded().ok()?.builtin(name))
+                        .ok_or_else(|| format!("unknown pin '{}'", var))?;
+        let index = match index {
+            Some("") | None => 0,
+            Some(index) => index.parse().map_err(|_| format!("'{}' is not a valid address", var))?
+        };
+        Ok((&mut self.models[part], index))
+    }
+}
+
+fn read(values: &[bool], nets: &[Net]) -> u16 {
+    nets.iter().rev().fold(0, |value, &net| value << 1 | values[net] as u16)
+}
+
+fn write(values: &mut [bool], nets: &[Net], value: u16) -> bool {
+    let mut changed = false;
+    for (bit, &net) in nets.iter().enumerate() {
+        let bit = value >> bit & 1 != 0;
+        changed |= values[net] != bit;
+        values[net] = bit;
+    }
+    changed
+}
+
+fn update(netlist: &Netlist, gate: &Gate, values: &mut [bool], models: &mut [Box<dyn BuiltinChip>]) -> bool {
+    // Evaluates one combinational gate, returning whether any of its outputs changed
+    match *gate {
+        Gate::Nand { a, b, out } => write(values, &[out], !(values[a] && values[b]) as u16),
+        Gate::Dff { .. } => false,
+        Gate::Builtin { part } => {
+            let builtin = &netlist.builtins[part];
+            let inputs: Vec<u16> = builtin.inputs.iter().map(|nets| read(values, nets)).collect();
+            let mut outputs = vec![0; builtin.outputs.len()];
+            models[part].eval(&inputs, &mut outputs);
+
+            let mut changed = false;
+            for (nets, value) in builtin.outputs.iter().zip(outputs) {
+                changed |= write(values, nets, value);
+            }
+            changed
+        }
+    }
+}
+
+impl Simulator for HdlSimulator {
+    fn load(&mut self, file: &Path) -> Result<(), String> {
+        self.load_chip(file).map(|_| ()).map_err(|e| e.to_string())
+    }
+
+    fn get(&mut self, var: &str) -> Result<u16, String> {
+        if let Some(pin) = self.loaded()?.pin(var) {
+            return Ok(read(&self.values, &pin.nets));
+        }
+
+        let (model, index) = self.builtin_part(var)?;
+        model.get(index).ok_or_else(|| format!("'{}' is not a valid address", var))
+    }
+
+    fn set(&mut self, var: &str, value: u16) -> Result<(), String> {
+        if let Some(pin) = self.loaded()?.pin(var) {
+            if pin.kind != PinKind::Input {
+                return Err(format!("'{}' isn't an input pin", var));
+            }
+            let nets = pin.nets.clone();
+            write(&mut self.values, &nets, value);
+            return Ok(());
+        }
+
+        let (model, index) = self.builtin_part(var)?;
+        if model.set(index, value) {
+            Ok(())
+        } else {
+            Err(format!("'{}' is not a valid address", var))
+        }
+    }
+
+    fn eval(&mut self) {
+        self.evaluate();
+    }
+
+    fn tick(&mut self) {
+        // DFFs and clocked built-in parts sample their inputs on the rising edge...
+        self.evaluate();
+        if let Some(netlist) = &self.netlist {
+            for (i, gate) in netlist.gates.iter().enumerate() {
+                match *gate {
+                    Gate::Dff { input, .. } => self.latched[i] = self.values[input],
+                    Gate::Builtin { part } => {
+                        let inputs: Vec<u16> = netlist.builtins[part].inputs.iter()
+                                                                      .map(|nets| read(&self.values, nets))
+                                                                      .collect();
+                        self.models[part].tick(&inputs);
+                    },
+                    Gate::Nand { .. } => ()
+                }
+            }
+        }
+    }
+
+    fn tock(&mut self) {
+        // ...and only show them on their outputs on the falling edge
+        if let Some(netlist) = &self.netlist {
+            for (i, gate) in netlist.gates.iter().enumerate() {
+                match *gate {
+                    Gate::Dff { out, .. } => self.values[out] = self.latched[i],
+                    Gate::Builtin { part } => self.models[part].tock(),
+                    Gate::Nand { .. } => ()
+                }
+            }
+        }
+        self.evaluate();
+    }
+
+    fn command(&mut self, words: &[String], dir: &Path) -> Result<(), String> {
+        // <Chip> load <file>, e.g. `ROM32K load Max.hack`
+        match words {
+            [chip, load, file] if load == "load" => {
+                let part = self.loaded()?.builtin(chip).ok_or_else(|| format!("there is no built-in '{}' part", chip))?;
+                self.models[part].load(&dir.join(file))?;
+                self.evaluate();
+                Ok(())
+            },
+            _ => Err(format!("unknown command '{}'", words.join(" ")))
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use std::fs;
+use std::path::Path;
+
+use assembler::disassembler::read_words;
+use assembler::AsmError;
+
+use crate::test_script::{split_index, Simulator};
+
+pub const ROM_SIZE: usize = 32768;
+pub const RAM_SIZE: usize = 32768;
+
+pub const SCREEN: u16 = 16384;
+pub const KBD: u16    = 24576;
+
+// The Hack CPU with its instruction and data memory
+pub struct HackCpu {
+    pub a: u16,
+    pub d: u16,
+    pub pc: u16,
+    rom: Vec<u16>,
+    ram: Vec<u16>,
+    cycles: u64
+}
+
+impl Default for HackCpu {
+    fn default() -> HackCpu {
+        HackCpu::new()
+    }
+}
+
+impl HackCpu {
+    pub fn new() -> HackCpu {
+        HackCpu {
+            a: 0,
+            d: 0,
+            pc: 0,
+            rom: vec![0; ROM_SIZE],
+            ram: vec![0; RAM_SIZE],
+            cycles: 0
+        }
+    }
+
+    pub fn with_program(words: &[u16]) -> HackCpu {
+        let mut cpu = HackCpu::new();
+        cpu.load_rom(words);
+        cpu
+    }
+
+    pub fn from_asm(source: &str) -> Result<HackCpu, Vec<AsmError>> {
+        let words = assembler::assemble_str(source)?;
+        Ok(HackCpu::with_program(&words))
+    }
+
+    pub fn from_file(program: &str) -> Result<HackCpu, Vec<AsmError>> {
+        let words = load_program(program)?;
+        Ok(HackCpu::with_program(&words))
+    }
+
+    pub fn load_rom(&mut self, words: &[u16]) {
+        // Anything past the end of the program is left as 0 (@0)
+        let len = words.len().min(ROM_SIZE);
+        self.rom.fill(0);
+        self.rom[..len].copy_from_slice(&words[..len]);
+    }
+
+    pub fn reset(&mut self) {
+        // Like the reset pin, only the program counter and cycle count are cleared
+        self.pc = 0;
+        self.cycles = 0;
+    }
+
+    pub fn step(&mut self) {
+        /*
+         *  Executes the instruction at PC.
+         *
+         *  A-instruction  0vvvvvvvvvvvvvvv  A = v
+         *  C-instruction  111accccccdddjjj
+         *      a       selects A (0) or M (1) as the ALU's y input
+         *      cccccc  are the ALU control bits zx nx zy ny f no, which is exactly
+         *              how the comp mnemonics are encoded in hack_core's COMP_CODES
+         *      ddd     store the result in A, D and/or M
+         *      jjj     jump if the result is < 0, = 0 and/or > 0
+         */
+        // Like ROM32K's address bus, only the low 15 bits of PC are used
+        let ins = self.rom[(self.pc & 0x7FFF) as usize];
+        self.cycles += 1;
+
+        if ins & 0x8000 == 0 {
+            self.a = ins;
+            self.pc = self.pc.wrapping_add(1);
+            return;
+        }
+
+        let address = self.a;
+        let y = if ins & 0x1000 != 0 { self.read_ram(address) } else { self.a };
+        let out = alu(self.d, y, (ins >> 6) & 0b111111);
+
+        // M is written with the address from before this instruction changes A
+        if ins & 0b001_000 != 0 {
+            self.write_ram(address, out);
+        }
+        if ins & 0b100_000 != 0 {
+            self.a = out;
+        }
+        if ins & 0b010_000 != 0 {
+            self.d = out;
+        }
+
+        let out = out as i16;
+        let jump = (ins & 0b100 != 0 && out < 0)
+                || (ins & 0b010 != 0 && out == 0)
+                || (ins & 0b001 != 0 && out > 0);
+
+        self.pc = if jump { address } else { self.pc.wrapping_add(1) };
+    }
+
+    pub fn run(&mut self, cycles: u64) {
+        // Runs for exactly `cycles` instructions
+        for _ in 0..cycles {
+            self.step();
+        }
+    }
+
+    pub fn run_until_halt(&mut self, max_cycles: u64) -> bool {
+        /*
+         *  Runs until the program reaches the usual end loop (@END, 0;JMP back to it)
+         *  or max_cycles instructions have been executed.
+         *  Returns whether the program halted.
+         */
+        for _ in 0..max_cycles {
+            if self.is_halted() {
+                return true;
+            }
+            self.step();
+        }
+        self.is_halted()
+    }
+
+    pub fn is_halted(&self) -> bool {
+        // @n followed by 0;JMP at address n
+        let pc = self.pc as usize;
+        pc + 1 < ROM_SIZE && self.rom[pc] == self.pc && self.rom[pc + 1] == 0b1110101010000111
+    }
+
+    pub fn read_ram(&self, address: u16) -> u16 {
+        self.ram[address as usize % RAM_SIZE]
+    }
+
+    pub fn write_ram(&mut self, address: u16, value: u16) {
+        self.ram[address as usize % RAM_SIZE] = value;
+    }
+
+    pub fn ram(&self) -> &[u16] {
+        &self.ram
+    }
+
+    pub fn ram_mut(&mut self) -> &mut [u16] {
+        &mut self.ram
+    }
+
+    pub fn rom(&self) -> &[u16] {
+        &self.rom
+    }
+
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+}
+
+pub fn alu(x: u16, y: u16, control: u16) -> u16 {
+    // zx nx zy ny f no, from the most significant bit down
+    let mut x = if control & 0b100000 != 0 { 0 } else { x };
+    if control & 0b010000 != 0 {
+        x = !x;
+    }
+    let mut y = if control & 0b001000 != 0 { 0 } else { y };
+    if control & 0b000100 != 0 {
+        y = !y;
+    }
+
+    let out = if control & 0b000010 != 0 { x.wrapping_add(y) } else { x & y };
+    if control & 0b000001 != 0 { !out } else { out }
+}
+
+pub fn load_program(program: &str) -> Result<Vec<u16>, Vec<AsmError>> {
+    // .asm files are assembled first, anything else is read as a .hack file
+    let source = fs::read_to_string(program)
+        .map_err(|error| vec![AsmError::Io { file: program.to_string(), error }])?;
+
+    if Path::new(program).extension().is_some_and(|ext| ext == "asm") {
+        assembler::assemble_source(program, &source).map(|p| p.words)
+    } else {
+        read_words(program, &source)
+    }
+}
+
+impl Simulator for HackCpu {
+    /*
+     *  The CPU emulator as seen from a test script: `load` takes a .hack or .asm
+     *  program, the variables are A, D, PC, RAM[n] and ROM[n], and each
+     *  ticktock executes one instruction.
+     */
+    fn load(&mut self, file: &Path) -> Result<(), String> {
+        let words = load_program(&file.to_string_lossy()).map_err(|errors| {
+            errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("\n")
+        })?;
+        self.load_rom(&words);
+        self.reset();
+        Ok(())
+    }
+
+    fn get(&mut self, var: &str) -> Result<u16, String> {
+        match split_index(var) {
+            ("A", None)  => Ok(self.a),
+            ("D", None)  => Ok(self.d),
+            ("PC", None) => Ok(self.pc),
+            ("RAM", Some(index)) => Ok(self.ram[memory_index(var, index, RAM_SIZE)?]),
+            ("ROM", Some(index)) => Ok(self.rom[memory_index(var, index, ROM_SIZE)?]),
+            _ => Err(format!("unknown variable '{}'", var))
+        }
+    }
+
+    fn set(&mut self, var: &str, value: u16) -> Result<(), String> {
+        match split_index(var) {
+            ("A", None)  => self.a = value,
+            ("D", None)  => self.d = value,
+            ("PC", None) => self.pc = value,
+            ("RAM", Some(index)) => self.ram[memory_index(var, index, RAM_SIZE)?] = value,
+            ("ROM", Some(index)) => self.rom[memory_index(var, index, ROM_SIZE)?] = value,
+            _ => return Err(format!("unknown variable '{}'", var))
+        }
+        Ok(())
+    }
+
+    fn tock(&mut self) {
+        self.step();
+    }
+}
+
+pub fn memory_index(var: &str, index: &str, size: usize) -> Result<usize, String> {
+    match index.parse::<usize>() {
+        Ok(index) if index < size => Ok(index),
+        _ => Err(format!("'{}' is not a valid address", var))
+    }
+}
@@ -0,0 +1,121 @@
+use std::path::Path;
+
+use crate::cpu::alu;
+use crate::test_script::{split_index, Simulator};
+
+// The CPU chip of project 05 on its own, pin for pin like CPU.hdl, so CPU.tst can
+// be run against the emulator's idea of a correct CPU.
+//
+// IN  inM[16], instruction[16], reset
+// OUT outM[16], writeM, addressM[15], pc[15]
+#[derive(Default)]
+pub struct CpuChip {
+    pub in_m: u16,
+    pub instruction: u16,
+    pub reset: bool,
+
+    // Register outputs, which only change on tock
+    a: u16,
+    d: u16,
+    pc: u16,
+
+    // What the registers latched on tick. These are the ARegister[], DRegister[]
+    // and PC[] values a test script sees.
+    next_a: u16,
+    next_d: u16,
+    next_pc: u16
+}
+
+impl CpuChip {
+    pub fn new() -> CpuChip {
+        CpuChip::default()
+    }
+
+    fn is_c_instruction(&self) -> bool {
+        self.instruction & 0x8000 != 0
+    }
+
+    pub fn out_m(&self) -> u16 {
+        let y = if self.instruction & 0x1000 != 0 { self.in_m } else { self.a };
+        alu(self.d, y, (self.instruction >> 6) & 0b111111)
+    }
+
+    pub fn write_m(&self) -> bool {
+        self.is_c_instruction() && self.instruction & 0b001_000 != 0
+    }
+
+    pub fn address_m(&self) -> u16 {
+        self.a & 0x7FFF
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc & 0x7FFF
+    }
+}
+
+impl Simulator for CpuChip {
+    fn load(&mut self, file: &Path) -> Result<(), String> {
+        // Until chips can be simulated from HDL, only the CPU itself can be loaded
+        match file.file_name() {
+            Some(name) if name == "CPU.hdl" => {
+                *self = CpuChip::new();
+                Ok(())
+            },
+            _ => Err(format!("'{}' isn't the CPU chip, only CPU.hdl can be simulated here", file.display()))
+        }
+    }
+
+    fn get(&mut self, var: &str) -> Result<u16, String> {
+        match split_index(var) {
+            ("inM", None)         => Ok(self.in_m),
+            ("instruction", None) => Ok(self.instruction),
+            ("reset", None)       => Ok(self.reset as u16),
+            ("outM", None)        => Ok(self.out_m()),
+            ("writeM", None)      => Ok(self.write_m() as u16),
+            ("addressM", None)    => Ok(self.address_m()),
+            ("pc", None)          => Ok(self.pc()),
+            ("ARegister", Some(_)) => Ok(self.next_a),
+            ("DRegister", Some(_)) => Ok(self.next_d),
+            ("PC", Some(_))        => Ok(self.next_pc),
+            _ => Err(format!("unknown pin '{}'", var))
+        }
+    }
+
+    fn set(&mut self, var: &str, value: u16) -> Result<(), String> {
+        match var {
+            "inM"         => self.in_m = value,
+            "instruction" => self.instruction = value,
+            "reset"       => self.reset = value & 1 != 0,
+            _ => return Err(format!("'{}' isn't an input pin", var))
+        }
+        Ok(())
+    }
+
+    fn tick(&mut self) {
+        // The registers latch their inputs, computed from the current outputs
+        let out = self.out_m();
+        let c = self.is_c_instruction();
+
+        self.next_a = if !c { self.instruction } else if self.instruction & 0b100_000 != 0 { out } else { self.a };
+        self.next_d = if c && self.instruction & 0b010_000 != 0 { out } else { self.d };
+
+        let out = out as i16;
+        let jump = c && ((self.instruction & 0b100 != 0 && out < 0)
+                      || (self.instruction & 0b010 != 0 && out == 0)
+                      || (self.instruction & 0b001 != 0 && out > 0));
+
+        self.next_pc = if self.reset {
+            0
+        } else if jump {
+            self.a
+        } else {
+            self.pc.wrapping_add(1)
+        };
+    }
+
+    fn tock(&mut self) {
+        self.a = self.next_a;
+        self.d = self.next_d;
+        self.pc = self.next_pc;
+    }
+}
@@ -0,0 +1,106 @@
+use std::fs;
+
+use crate::cpu::{HackCpu, KBD};
+
+// A keystroke timeline: from `cycle` onwards the keyboard reports `key`
+pub struct KeyboardScript {
+    events: Vec<(u64, u16)>
+}
+
+impl KeyboardScript {
+    pub fn from_file(path: &str) -> Result<KeyboardScript, String> {
+        let script = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        KeyboardScript::parse(&script).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn parse(script: &str) -> Result<KeyboardScript, String> {
+        /*
+         *  One event per line, "<cycle> <key>", e.g.
+         *
+         *      // Press and release 'a', then press enter
+         *      1000    a
+         *      50000   none
+         *      90000   newline
+         *
+         *  A key is a single character, a name from key_code or a numeric code.
+         *  Events are applied in cycle order regardless of the order they're written in.
+         */
+        let mut events = Vec::new();
+
+        for (i, line) in script.lines().enumerate() {
+            let line = line.split("//").next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut parts = line.split_whitespace();
+            let cycle = parts.next().and_then(|c| c.parse::<u64>().ok());
+            let key = parts.next().and_then(key_code);
+
+            match (cycle, key, parts.next()) {
+                (Some(cycle), Some(key), None) => events.push((cycle, key)),
+                _ => return Err(format!("line {}: expected '<cycle> <key>' but found '{}'", i + 1, line))
+            }
+        }
+
+        events.sort_by_key(|(cycle, _)| *cycle);
+        Ok(KeyboardScript { events })
+    }
+
+    pub fn key_at(&self, cycle: u64) -> u16 {
+        // The key held down at a given cycle, 0 before the first event
+        self.events.iter()
+                   .take_while(|(c, _)| *c <= cycle)
+                   .last()
+                   .map_or(0, |(_, key)| *key)
+    }
+
+    pub fn run(&self, cpu: &mut HackCpu, cycles: u64) {
+        // Runs the CPU for `cycles` instructions, updating KBD as the timeline says
+        let mut next = self.events.partition_point(|(c, _)| *c < cpu.cycles());
+        cpu.write_ram(KBD, self.key_at(cpu.cycles()));
+
+        for _ in 0..cycles {
+            while next < self.events.len() && self.events[next].0 <= cpu.cycles() {
+                cpu.write_ram(KBD, self.events[next].1);
+                next += 1;
+            }
+            cpu.step();
+        }
+    }
+}
+
+pub fn key_code(key: &str) -> Option<u16> {
+    // Codes from the Hack keyboard, printable characters are their ASCII code
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if (' '..='~').contains(&c) {
+            return Some(c as u16);
+        }
+    }
+
+    let code = match key.to_lowercase().as_str() {
+        "none" | "release" => 0,
+        "space"     => 32,
+        "newline" | "enter" => 128,
+        "backspace" => 129,
+        "left"      => 130,
+        "up"        => 131,
+        "right"     => 132,
+        "down"      => 133,
+        "home"      => 134,
+        "end"       => 135,
+        "pageup"    => 136,
+        "pagedown"  => 137,
+        "insert"    => 138,
+        "delete"    => 139,
+        "esc"       => 140,
+        f if f.starts_with('f') => match f[1..].parse::<u16>() {
+            Ok(n) if (1..=12).contains(&n) => 140 + n,
+            _ => return None
+        },
+        n => return n.parse().ok()
+    };
+
+    Some(code)
+}
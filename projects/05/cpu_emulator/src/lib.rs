@@ -0,0 +1,13 @@
+// Native emulator for the Hack computer, so .hack programs can be run (and tested)
+// without the Java CPUEmulator.
+
+pub mod cpu;
+pub mod cpu_chip;
+pub mod keyboard;
+pub mod screen;
+pub mod test_script;
+
+pub use crate::cpu::{load_program, HackCpu};
+pub use crate::cpu_chip::CpuChip;
+pub use crate::keyboard::KeyboardScript;
+pub use crate::test_script::{Script, ScriptError, Simulator};
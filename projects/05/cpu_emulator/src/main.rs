@@ -0,0 +1,123 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::{env, process};
+
+use cpu_emulator::{screen, CpuChip, HackCpu, KeyboardScript, Script, Simulator};
+
+const USAGE: &str = "Usage: cpu_emulator [--keys <script>] [--screen <file.png|file.pbm>] <path-to-hack-or-asm-file> [max-cycles]\n       cpu_emulator --test <path-to-tst-file>";
+
+// Enough for any of the course's test programs to reach their end loop
+const DEFAULT_MAX_CYCLES: u64 = 10_000_000;
+
+fn main() {
+    let mut keys: Option<String> = None;
+    let mut screen_file: Option<String> = None;
+    let mut positional: Vec<String> = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--keys"   => keys = args.next(),          // Keystroke timeline for KBD
+            "--screen" => screen_file = args.next(),   // Save the screen when the program stops
+            "--test"   => match args.next() {              // Run a .tst script instead of a program
+                Some(script) => run_test(&script),
+                None => usage_error("Expected a test script")
+            },
+            flag if flag.starts_with("--") => usage_error(&format!("Unknown option {}", flag)),
+            _ => positional.push(arg)
+        }
+    }
+
+    let (program, max_cycles) = match positional.len() {
+        1 => (&positional[0], DEFAULT_MAX_CYCLES),
+        2 => match positional[1].parse() {
+            Ok(cycles) => (&positional[0], cycles),
+            Err(_) => usage_error(&format!("Invalid cycle count {}", positional[1]))
+        },
+        _ => usage_error("Expected a program to run")
+    };
+
+    let mut cpu = HackCpu::from_file(program).unwrap_or_else(|errors| {
+        for e in &errors {
+            eprintln!("error: {}", e);
+        }
+        process::exit(-1);
+    });
+
+    // With a keyboard script the program is run for the full cycle count,
+    // since it's usually waiting on keys in a loop that never halts
+    let halted = match keys {
+        Some(keys) => {
+            let script = KeyboardScript::from_file(&keys).unwrap_or_else(|e| usage_error(&e));
+            script.run(&mut cpu, max_cycles);
+            cpu.is_halted()
+        },
+        None => cpu.run_until_halt(max_cycles)
+    };
+
+    println!("{} after {} cycles", if halted { "Halted" } else { "Stopped" }, cpu.cycles());
+    println!("A = {}, D = {}, PC = {}", cpu.a, cpu.d as i16, cpu.pc);
+    for (address, value) in cpu.ram()[..16].iter().enumerate() {
+        println!("RAM[{}] = {}", address, *value as i16);
+    }
+
+    if let Some(screen_file) = screen_file {
+        if let Err(e) = save_screen(&screen_file, &cpu) {
+            eprintln!("Couldn't save the screen to {}: {}", screen_file, e);
+            process::exit(-1);
+        }
+        println!("Screen -> {}", screen_file);
+    }
+}
+
+fn run_test(script_file: &str) -> ! {
+    let script = Script::from_file(script_file).unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        process::exit(-1);
+    });
+
+    // Scripts for chips load CPU.hdl, scripts for programs load the .hack file.
+    // Other chips, like Computer.hdl, need the HDL simulator from project 01.
+    let mut sim: Box<dyn Simulator> = match script.loaded_file() {
+        Some(file) if file.ends_with(".hdl") && Path::new(file).file_name() != Some("CPU.hdl".as_ref()) => {
+            eprintln!("error: {}: the CPU emulator only simulates CPU.hdl, run this script with \
+                       projects/01/hdl_simulator instead:\n    hdl_simulator {}", file, script_file);
+            process::exit(-1);
+        },
+        Some(file) if file.ends_with(".hdl") => Box::new(CpuChip::new()),
+        _ => Box::new(HackCpu::new())
+    };
+
+    match script.run(sim.as_mut()) {
+        Ok(report) => {
+            if let Some(output_file) = report.output_file {
+                println!("{} lines -> {}", report.lines, output_file.display());
+            }
+            if report.compare_file.is_some() {
+                println!("Comparison ended successfully");
+            }
+            process::exit(0);
+        },
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(-1);
+        }
+    }
+}
+
+fn save_screen(screen_file: &str, cpu: &HackCpu) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(screen_file)?);
+
+    if screen_file.ends_with(".pbm") {
+        screen::write_pbm(&mut writer, cpu.ram())?;
+    } else {
+        screen::write_png(&mut writer, cpu.ram())?;
+    }
+    writer.flush()
+}
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("{}\n{}", msg, USAGE);
+    process::exit(-1);
+}
@@ -0,0 +1,104 @@
+use std::io::{Error, Write};
+
+use crate::cpu::SCREEN;
+
+pub const SCREEN_WIDTH: usize  = 512;
+pub const SCREEN_HEIGHT: usize = 256;
+
+// Each row of the screen is 32 words, bit 0 of a word being its leftmost pixel
+const WORDS_PER_ROW: usize = SCREEN_WIDTH / 16;
+
+pub fn pixel(ram: &[u16], x: usize, y: usize) -> bool {
+    // true is black. ram is the whole of RAM, from the CPU or the VM emulator
+    let word = ram[SCREEN as usize + y * WORDS_PER_ROW + x / 16];
+    (word >> (x % 16)) & 1 == 1
+}
+
+pub fn write_pbm(writer: &mut impl Write, ram: &[u16]) -> Result<(), Error> {
+    /*
+     *  Binary PBM (P4). Rows are packed 8 pixels to a byte with the leftmost pixel
+     *  in the most significant bit and 1 meaning black, same as the Hack screen.
+     */
+    writeln!(writer, "P4\n{} {}", SCREEN_WIDTH, SCREEN_HEIGHT)?;
+
+    for y in 0..SCREEN_HEIGHT {
+        writer.write_all(&packed_row(ram, y, false))?;
+    }
+    Ok(())
+}
+
+pub fn write_png(writer: &mut impl Write, ram: &[u16]) -> Result<(), Error> {
+    /*
+     *  1-bit greyscale PNG. The image data is put in stored (uncompressed) deflate blocks,
+     *  which keeps this free of dependencies at the cost of a ~17KB file.
+     */
+    let mut raw = Vec::with_capacity(SCREEN_HEIGHT * (SCREEN_WIDTH / 8 + 1));
+    for y in 0..SCREEN_HEIGHT {
+        raw.push(0);  // Filter type: none
+        raw.extend(packed_row(ram, y, true));
+    }
+
+    let mut ihdr = Vec::new();
+    ihdr.extend((SCREEN_WIDTH as u32).to_be_bytes());
+    ihdr.extend((SCREEN_HEIGHT as u32).to_be_bytes());
+    ihdr.extend([1, 0, 0, 0, 0]);  // Bit depth 1, greyscale, deflate, no filter, no interlace
+
+    writer.write_all(b"\x89PNG\r\n\x1a\n")?;
+    write_png_chunk(writer, b"IHDR", &ihdr)?;
+    write_png_chunk(writer, b"IDAT", &zlib_stored(&raw))?;
+    write_png_chunk(writer, b"IEND", &[])
+}
+
+fn packed_row(ram: &[u16], y: usize, white_is_one: bool) -> Vec<u8> {
+    let mut row = vec![0u8; SCREEN_WIDTH / 8];
+    for x in 0..SCREEN_WIDTH {
+        if pixel(ram, x, y) != white_is_one {
+            row[x / 8] |= 0x80 >> (x % 8);
+        }
+    }
+    row
+}
+
+fn write_png_chunk(writer: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> Result<(), Error> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+
+    let crc = crc32(kind.iter().chain(data.iter()));
+    writer.write_all(&crc.to_be_bytes())
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+
+    let blocks: Vec<&[u8]> = data.chunks(65535).collect();
+    for (i, block) in blocks.iter().enumerate() {
+        let last = (i + 1 == blocks.len()) as u8;
+        let len = block.len() as u16;
+
+        out.push(last);
+        out.extend(len.to_le_bytes());
+        out.extend((!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+
+    // Adler-32 of the uncompressed data
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    out.extend(((b << 16) | a).to_be_bytes());
+    out
+}
+
+fn crc32<'a>(bytes: impl Iterator<Item = &'a u8>) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
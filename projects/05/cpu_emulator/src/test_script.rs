@@ -0,0 +1,544 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use assembler::Location;
+
+// Anything the test-script language can drive: the emulator running a program,
+// the CPU chip, or a chip being simulated from HDL
+pub trait Simulator {
+    // `load <file>`, with the path already made relative to the script
+    fn load(&mut self, file: &Path) -> Result<(), String>;
+
+    fn get(&mut self, var: &str) -> Result<u16, String>;
+    fn set(&mut self, var: &str, value: u16) -> Result<(), String>;
+
+    fn eval(&mut self) {}
+    fn tick(&mut self) {}
+    fn tock(&mut self) {}
+
+    // Commands the script language leaves to the simulator, e.g. `ROM32K load Add.hack`
+    fn command(&mut self, words: &[String], _dir: &Path) -> Result<(), String> {
+        Err(format!("unknown command '{}'", words.join(" ")))
+    }
+}
+
+#[derive(Debug)]
+pub enum ScriptError {
+    Io { file: String, error: io::Error },
+    Syntax { loc: Location, text: String },
+    MalformedFormat { loc: Location, text: String },
+    MalformedValue { loc: Location, text: String },
+    Simulation { loc: Location, text: String },
+    ComparisonFailure { file: String, line: usize, expected: String, found: String },
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScriptError::Io { file, error } => write!(f, "{}: {}", file, error),
+            ScriptError::Syntax { loc, text } => write!(f, "{}: {}", loc, text),
+            ScriptError::MalformedFormat { loc, text } => {
+                write!(f, "{}: malformed output format '{}'", loc, text)
+            }
+            ScriptError::MalformedValue { loc, text } => {
+                write!(f, "{}: malformed value '{}'", loc, text)
+            }
+            ScriptError::Simulation { loc, text } => write!(f, "{}: {}", loc, text),
+            ScriptError::ComparisonFailure { file, line, expected, found } => {
+                write!(f, "{}:{}: comparison failure\n  expected: {}\n  found:    {}", file, line, expected, found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+struct Token {
+    text: String,
+    loc: Location
+}
+
+struct Condition {
+    var: String,
+    op: String,
+    value: u16
+}
+
+enum Statement {
+    Command { words: Vec<String>, loc: Location },
+    Repeat { count: Option<u64>, body: Vec<Statement> },
+    While { condition: Condition, body: Vec<Statement>, loc: Location },
+}
+
+// One column of the output list, `name%<kind><left>.<len>.<right>`
+struct Column {
+    var: String,
+    kind: char,
+    left: usize,
+    len: usize,
+    right: usize
+}
+
+// What a successful run produced
+pub struct TestReport {
+    pub output_file: Option<PathBuf>,
+    pub compare_file: Option<PathBuf>,
+    pub lines: usize
+}
+
+// A parsed .tst file
+pub struct Script {
+    file: PathBuf,
+    statements: Vec<Statement>
+}
+
+impl Script {
+    pub fn from_file(file: &str) -> Result<Script, ScriptError> {
+        let source = fs::read_to_string(file)
+            .map_err(|error| ScriptError::Io { file: file.to_string(), error })?;
+        Script::parse(file, &source)
+    }
+
+    pub fn parse(file: &str, source: &str) -> Result<Script, ScriptError> {
+        let tokens = tokenize(file, source)?;
+        let mut pos = 0;
+        let statements = parse_block(&tokens, &mut pos, None)?;
+
+        Ok(Script { file: PathBuf::from(file), statements })
+    }
+
+    pub fn loaded_file(&self) -> Option<&str> {
+        // The file given to the first `load`, which decides what kind of simulator the script needs
+        fn find(statements: &[Statement]) -> Option<&str> {
+            statements.iter().find_map(|s| match s {
+                Statement::Command { words, .. } if words[0] == "load" => words.get(1).map(|w| w.as_str()),
+                Statement::Repeat { body, .. } | Statement::While { body, .. } => find(body),
+                _ => None
+            })
+        }
+        find(&self.statements)
+    }
+
+    pub fn dir(&self) -> &Path {
+        // "." for a script in the current directory, so a bare `load` has somewhere to load
+        self.file.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."))
+    }
+
+    pub fn run(&self, sim: &mut dyn Simulator) -> Result<TestReport, ScriptError> {
+        /*
+         *  Runs the script to the end. The .out file is written either way, and the
+         *  first line that doesn't match the .cmp file stops the run with a
+         *  ComparisonFailure.
+         */
+        let mut run = Run {
+            sim,
+            dir: self.dir().to_path_buf(),
+            time: 0,
+            ticked: false,
+            columns: Vec::new(),
+            output: Vec::new(),
+            output_file: None,
+            compare_file: None,
+            compare: Vec::new()
+        };
+
+        let result = run.block(&self.statements);
+        run.write_output()?;
+        result?;
+
+        Ok(TestReport {
+            output_file: run.output_file,
+            compare_file: run.compare_file,
+            lines: run.output.len()
+        })
+    }
+}
+
+struct Run<'a> {
+    sim: &'a mut dyn Simulator,
+    dir: PathBuf,
+    time: u64,
+    ticked: bool,
+    columns: Vec<Column>,
+    output: Vec<String>,
+    output_file: Option<PathBuf>,
+    compare_file: Option<PathBuf>,
+    compare: Vec<String>
+}
+
+impl Run<'_> {
+    fn block(&mut self, statements: &[Statement]) -> Result<(), ScriptError> {
+        for statement in statements {
+            match statement {
+                Statement::Command { words, loc } => self.command(words, loc)?,
+                Statement::Repeat { count: Some(count), body } => {
+                    for _ in 0..*count {
+                        self.block(body)?;
+                    }
+                },
+                Statement::Repeat { count: None, body } => loop {
+                    self.block(body)?;
+                },
+                Statement::While { condition, body, loc } => {
+                    while self.holds(condition, loc)? {
+                        self.block(body)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn command(&mut self, words: &[String], loc: &Location) -> Result<(), ScriptError> {
+        let simulation = |text: String| ScriptError::Simulation { loc: loc.clone(), text };
+        let arg = |i: usize| {
+            words.get(i).ok_or_else(|| ScriptError::Syntax {
+                loc: loc.clone(),
+                text: format!("'{}' is missing an argument", words[0])
+            })
+        };
+
+        match words[0].as_str() {
+            "load" => {
+                // A bare `load` (the VM emulator scripts) loads the script's own directory
+                let file = words.get(1).map_or_else(|| self.dir.clone(), |file| self.dir.join(file));
+                self.sim.load(&file).map_err(simulation)?;
+            },
+            "output-file" => self.output_file = Some(self.dir.join(arg(1)?)),
+            "compare-to" => {
+                let file = self.dir.join(arg(1)?);
+                let text = fs::read_to_string(&file)
+                    .map_err(|error| ScriptError::Io { file: file.display().to_string(), error })?;
+                self.compare = text.lines().map(|l| l.trim_end().to_string()).collect();
+                self.compare_file = Some(file);
+            },
+            "output-list" => {
+                self.columns = words[1..].iter()
+                                         .map(|w| parse_column(w, loc))
+                                         .collect::<Result<_, _>>()?;
+                let header = self.columns.iter().map(Column::header).collect::<Vec<_>>();
+                self.emit(format!("|{}|", header.join("|")))?;
+            },
+            "output" => {
+                let mut cells = Vec::new();
+                for i in 0..self.columns.len() {
+                    let var = self.columns[i].var.clone();
+                    let value = self.value_of(&var, loc)?;
+                    cells.push(self.columns[i].cell(value));
+                }
+                self.emit(format!("|{}|", cells.join("|")))?;
+            },
+            "set" => {
+                let var = arg(1)?;
+                let value = parse_value(arg(2)?)
+                    .ok_or_else(|| ScriptError::MalformedValue { loc: loc.clone(), text: words[2].clone() })?;
+                self.sim.set(var, value).map_err(simulation)?;
+            },
+            "eval" => self.sim.eval(),
+            "tick" => self.tick(),
+            "tock" => self.tock(),
+            "ticktock" => {
+                self.tick();
+                self.tock();
+            },
+            "echo" => println!("{}", words[1..].join(" ").trim_matches('"')),
+            "clear-echo" | "breakpoint" | "clear-breakpoints" => (),
+            _ => self.sim.command(words, &self.dir).map_err(simulation)?
+        }
+        Ok(())
+    }
+
+    fn tick(&mut self) {
+        self.sim.tick();
+        self.ticked = true;
+    }
+
+    fn tock(&mut self) {
+        self.sim.tock();
+        self.time += 1;
+        self.ticked = false;
+    }
+
+    fn value_of(&mut self, var: &str, loc: &Location) -> Result<Value, ScriptError> {
+        if var == "time" {
+            return Ok(Value::Text(format!("{}{}", self.time, if self.ticked { "+" } else { "" })));
+        }
+        self.sim.get(var)
+                .map(Value::Word)
+                .map_err(|text| ScriptError::Simulation { loc: loc.clone(), text })
+    }
+
+    fn holds(&mut self, condition: &Condition, loc: &Location) -> Result<bool, ScriptError> {
+        let value = match self.value_of(&condition.var, loc)? {
+            Value::Word(value) => value as i16,
+            Value::Text(_) => (self.time as u16) as i16
+        };
+        let other = condition.value as i16;
+
+        Ok(match condition.op.as_str() {
+            "="  => value == other,
+            "<>" => value != other,
+            "<"  => value < other,
+            ">"  => value > other,
+            "<=" => value <= other,
+            _    => value >= other
+        })
+    }
+
+    fn emit(&mut self, line: String) -> Result<(), ScriptError> {
+        // Output lines are checked against the .cmp file as they're produced,
+        // where '*' in the .cmp file matches anything
+        let index = self.output.len();
+        self.output.push(line);
+
+        let Some(compare_file) = &self.compare_file else {
+            return Ok(());
+        };
+
+        let found = &self.output[index];
+        let expected = self.compare.get(index).map_or("", |l| l.as_str());
+        let matches = expected.len() == found.len()
+            && expected.chars().zip(found.chars()).all(|(e, f)| e == '*' || e == f);
+
+        if matches {
+            Ok(())
+        } else {
+            Err(ScriptError::ComparisonFailure {
+                file: compare_file.display().to_string(),
+                line: index + 1,
+                expected: expected.to_string(),
+                found: found.clone()
+            })
+        }
+    }
+
+    fn write_output(&self) -> Result<(), ScriptError> {
+        let Some(output_file) = &self.output_file else {
+            return Ok(());
+        };
+
+        let mut text = self.output.join("\n");
+        text.push('\n');
+        fs::write(output_file, text)
+            .map_err(|error| ScriptError::Io { file: output_file.display().to_string(), error })
+    }
+}
+
+enum Value {
+    Word(u16),
+    Text(String)
+}
+
+impl Column {
+    fn width(&self) -> usize {
+        self.left + self.len + self.right
+    }
+
+    fn header(&self) -> String {
+        // The variable name centred in the column, cut short if it doesn't fit
+        let name: String = self.var.chars().take(self.width()).collect();
+        let left = (self.width() - name.len()) / 2;
+        format!("{}{:<width$}", " ".repeat(left), name, width = self.width() - left)
+    }
+
+    fn cell(&self, value: Value) -> String {
+        let len = self.len;
+        let text = match (value, self.kind) {
+            (Value::Text(text), _)   => format!("{:<len$}", text),
+            (Value::Word(w), 'S')    => format!("{:<len$}", w as i16),
+            (Value::Word(w), 'D')    => format!("{:>len$}", w as i16),
+            (Value::Word(w), 'X')    => format!("{:0>len$}", format!("{:X}", w)),
+            (Value::Word(w), _)      => {
+                // %B shows the low `len` bits
+                let bits = format!("{:016b}", w);
+                format!("{:0>len$}", &bits[16 - len.min(16)..])
+            }
+        };
+        format!("{}{}{}", " ".repeat(self.left), text, " ".repeat(self.right))
+    }
+}
+
+fn parse_column(word: &str, loc: &Location) -> Result<Column, ScriptError> {
+    // Without a format a column is %B1.1.1, which suits single-bit pins
+    let malformed = || ScriptError::MalformedFormat { loc: loc.clone(), text: word.to_string() };
+
+    let (var, format) = word.split_once('%').unwrap_or((word, "B1.1.1"));
+    let kind = format.chars().next().filter(|c| "BDSX".contains(*c)).ok_or_else(malformed)?;
+
+    let sizes: Vec<usize> = format[1..].split('.')
+                                       .map(|n| n.parse().map_err(|_| malformed()))
+                                       .collect::<Result<_, _>>()?;
+    if sizes.len() != 3 || var.is_empty() {
+        return Err(malformed());
+    }
+
+    Ok(Column { var: var.to_string(), kind, left: sizes[0], len: sizes[1], right: sizes[2] })
+}
+
+pub fn parse_value(text: &str) -> Option<u16> {
+    // 17, -1, %D-1, %B0101, %X1F. Values wrap to 16 bits, so -1 is all ones.
+    let value = if let Some(bits) = text.strip_prefix("%B") {
+        i64::from_str_radix(bits, 2).ok()
+    } else if let Some(hex) = text.strip_prefix("%X") {
+        i64::from_str_radix(hex, 16).ok()
+    } else {
+        text.strip_prefix("%D").unwrap_or(text).parse::<i64>().ok()
+    };
+    value.map(|v| v as u16)
+}
+
+pub fn split_index(var: &str) -> (&str, Option<&str>) {
+    // RAM[12] -> ("RAM", Some("12")), DRegister[] -> ("DRegister", Some("")), PC -> ("PC", None)
+    match var.strip_suffix(']').and_then(|v| v.split_once('[')) {
+        Some((name, index)) => (name, Some(index)),
+        None => (var, None)
+    }
+}
+
+fn tokenize(file: &str, source: &str) -> Result<Vec<Token>, ScriptError> {
+    /*
+     *  Words, "strings" and the punctuation , ; ! { }
+     *  Comments are // to the end of the line and /* ... */
+     */
+    let mut tokens = Vec::new();
+
+    let chars: Vec<char> = source.chars().collect();
+    let (mut i, mut line, mut line_start) = (0, 1, 0);
+
+    while i < chars.len() {
+        let c = chars[i];
+        let loc = Location::new(file, line, i - line_start + 1);
+
+        if c == '\n' {
+            line += 1;
+            line_start = i + 1;
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if chars[i..].starts_with(&['/', '/']) {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if chars[i..].starts_with(&['/', '*']) {
+            i += 2;
+            while i < chars.len() && !chars[i..].starts_with(&['*', '/']) {
+                if chars[i] == '\n' {
+                    line += 1;
+                    line_start = i + 1;
+                }
+                i += 1;
+            }
+            if i >= chars.len() {
+                return Err(ScriptError::Syntax { loc, text: "unterminated comment".to_string() });
+            }
+            i += 2;
+        } else if c == '"' {
+            let end = chars[i + 1..].iter().position(|&c| c == '"' || c == '\n');
+            match end {
+                Some(end) if chars[i + 1 + end] == '"' => {
+                    tokens.push(Token { text: chars[i..i + end + 2].iter().collect(), loc });
+                    i += end + 2;
+                },
+                _ => return Err(ScriptError::Syntax { loc, text: "unterminated string".to_string() })
+            }
+        } else if ",;!{}".contains(c) {
+            tokens.push(Token { text: c.to_string(), loc });
+            i += 1;
+        } else {
+            let start = i;
+            while i < chars.len() && !chars[i].is_whitespace() && !",;!{}\"".contains(chars[i]) {
+                i += 1;
+            }
+            tokens.push(Token { text: chars[start..i].iter().collect(), loc });
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn parse_block(tokens: &[Token], pos: &mut usize, open: Option<&Location>) -> Result<Vec<Statement>, ScriptError> {
+    /*
+     *  statement: command (',' | ';' | '!')
+     *           | 'repeat' [count] '{' statement* '}'
+     *           | 'while' var op value '{' statement* '}'
+     *
+     *  `open` is where the enclosing '{' is, for blocks inside a repeat or while.
+     */
+    let mut statements = Vec::new();
+
+    loop {
+        let Some(token) = tokens.get(*pos) else {
+            return match open {
+                Some(loc) => Err(ScriptError::Syntax { loc: loc.clone(), text: "'{' is never closed".to_string() }),
+                None => Ok(statements)
+            };
+        };
+        let loc = token.loc.clone();
+
+        match token.text.as_str() {
+            "}" if open.is_some() => {
+                *pos += 1;
+                return Ok(statements);
+            },
+            "}" | "{" => return Err(ScriptError::Syntax { loc, text: format!("unexpected '{}'", token.text) }),
+            "," | ";" | "!" => *pos += 1,
+            "repeat" => {
+                *pos += 1;
+                let count = match tokens.get(*pos) {
+                    Some(t) if t.text != "{" => {
+                        *pos += 1;
+                        let count = t.text.parse().map_err(|_| ScriptError::MalformedValue {
+                            loc: t.loc.clone(),
+                            text: t.text.clone()
+                        })?;
+                        Some(count)
+                    },
+                    _ => None
+                };
+                let brace = expect_brace(tokens, pos, &loc)?;
+                let body = parse_block(tokens, pos, Some(&brace))?;
+                statements.push(Statement::Repeat { count, body });
+            },
+            "while" => {
+                *pos += 1;
+                let words: Vec<&Token> = tokens[*pos..].iter().take_while(|t| t.text != "{").collect();
+                *pos += words.len();
+
+                let condition = match words.as_slice() {
+                    [var, op, value] if ["=", "<>", "<", ">", "<=", ">="].contains(&op.text.as_str()) => {
+                        let value = parse_value(&value.text).ok_or_else(|| ScriptError::MalformedValue {
+                            loc: value.loc.clone(),
+                            text: value.text.clone()
+                        })?;
+                        Condition { var: var.text.clone(), op: op.text.clone(), value }
+                    },
+                    _ => return Err(ScriptError::Syntax { loc, text: "expected 'while <var> <op> <value> {'".to_string() })
+                };
+                let brace = expect_brace(tokens, pos, &loc)?;
+                let body = parse_block(tokens, pos, Some(&brace))?;
+                statements.push(Statement::While { condition, body, loc });
+            },
+            _ => {
+                // A command runs up to the next terminator, or the end of the block
+                let words: Vec<String> = tokens[*pos..].iter()
+                                                       .map(|t| t.text.clone())
+                                                       .take_while(|t| ![",", ";", "!", "{", "}"].contains(&t.as_str()))
+                                                       .collect();
+                *pos += words.len();
+                statements.push(Statement::Command { words, loc });
+            }
+        }
+    }
+}
+
+fn expect_brace(tokens: &[Token], pos: &mut usize, loc: &Location) -> Result<Location, ScriptError> {
+    match tokens.get(*pos) {
+        Some(t) if t.text == "{" => {
+            *pos += 1;
+            Ok(t.loc.clone())
+        },
+        _ => Err(ScriptError::Syntax { loc: loc.clone(), text: "expected '{'".to_string() })
+    }
+}
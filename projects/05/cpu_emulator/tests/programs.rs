@@ -0,0 +1,36 @@
+// Assembled programs run on the emulated CPU
+use cpu_emulator::HackCpu;
+
+const MAX_ASM: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../06/max/Max.asm");
+
+fn max(r0: u16, r1: u16) -> u16 {
+    let mut cpu = HackCpu::from_file(MAX_ASM).expect("Max.asm assembles");
+    cpu.write_ram(0, r0);
+    cpu.write_ram(1, r1);
+    assert!(cpu.run_until_halt(100), "Max.asm should reach its END loop");
+    cpu.read_ram(2)
+}
+
+#[test]
+fn max_picks_the_larger_value() {
+    assert_eq!(max(3, 5), 5);
+    assert_eq!(max(23456, 12345), 23456);
+    assert_eq!(max(7, 7), 7);
+    assert_eq!(max((-2i16) as u16, 1), 1);
+}
+
+#[test]
+fn running_off_the_end_of_rom_wraps_around() {
+    // The rest of ROM is @0, so PC runs past 32767 and the address bus wraps back to 0
+    let mut cpu = HackCpu::from_asm("@5\nD=A\n").expect("program assembles");
+    cpu.run(40000);
+    assert_eq!(cpu.cycles(), 40000);
+    assert_eq!(cpu.d, 5);
+
+    // A jump to an address with the top bit set runs ROM[address & 0x7FFF]
+    let mut cpu = HackCpu::from_asm("@32767\nD=A\nD=D+1\nA=D\n0;JMP\n").expect("program assembles");
+    cpu.run(5);
+    assert_eq!(cpu.pc, 32768);
+    cpu.step();
+    assert_eq!((cpu.a, cpu.pc), (32767, 32769));
+}
@@ -1,59 +1,169 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader, Write, Error};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write, Error};
 
-use crate::parser::{parse_line, Instruction, is_label, is_instruction};
+use crate::error::{AsmError, AsmWarning};
+use crate::listing::{write_listing, write_symbols};
+use crate::include::expand_includes;
+use crate::macros::{expand_macros, SourceLine};
+use crate::output::OutputFormat;
+use crate::parser::{parse_line, parse_label, parse_equ, Instruction, is_label, is_directive, is_instruction};
 use crate::translator::translate_instruction;
-use crate::symbol_table::{init_sym_table, SymbolTable, add_symbol};
+use crate::symbol_table::{init_sym_table, find_address, is_predefined, SymbolTable, add_constant, add_label, KBD, SCREEN};
 
-pub fn assemble(source_file: &String) -> Result<(), Error> {
-    let output_file = if let Some(index) = source_file.rfind('.') {
-        source_file[..index].to_owned() + ".hack"
-    } else {
-        source_file.to_owned() + ".hack"
-    };
+// Format of the output file and which of the optional debugging files to write next to it
+#[derive(Default)]
+pub struct AssembleOptions {
+    pub format: OutputFormat,
+    pub listing: bool,  // <name>.lst
+    pub symbols: bool   // <name>.sym
+}
+
+// The result of assembling a program
+pub struct Program {
+    pub words: Vec<u16>,
+    pub listing: Vec<ListingLine>,
+    pub sym_table: SymbolTable,
+    pub warnings: Vec<AsmWarning>
+}
+
+// The Hack computer has 32K words of ROM
+const ROM_SIZE: u32 = 32768;
+
+// One source line and the word it assembled to, if it is an instruction
+pub struct ListingLine {
+    pub line: usize,
+    pub address: Option<u16>,
+    pub word: Option<u16>,
+    pub source: String
+}
+
+pub fn assemble(source_file: &str,
+                options: &AssembleOptions) -> Result<(String, Vec<AsmWarning>), Vec<AsmError>> {
+    /*
+     *  Assembles source_file into <name>.hack (or whatever extension the output format uses)
+     *  and returns the name of the output file along with any warnings.
+     *  Every error found in the source is collected and returned together,
+     *  and the output files are only written if there were none.
+     */
+    let writer = options.format.writer();
+    let output_file = with_extension(source_file, writer.extension());
+
+    let source = fs::read_to_string(source_file)
+        .map_err(|error| vec![AsmError::Io { file: source_file.to_string(), error }])?;
+
+    let program = assemble_source(source_file, &source)?;
 
+    write_file(&output_file, |w| writer.write_words(w, &program.words))?;
+
+    if options.listing {
+        write_file(&with_extension(source_file, "lst"), |w| write_listing(w, &program))?;
+    }
+
+    if options.symbols {
+        write_file(&with_extension(source_file, "sym"), |w| write_symbols(w, &program.sym_table))?;
+    }
+
+    Ok((output_file, program.warnings))
+}
+
+pub fn assemble_source(source_name: &str, source: &str) -> Result<Program, Vec<AsmError>> {
+    /*
+     *  Assembles an in-memory program into machine words.
+     *  source_name is only used to label the locations in any errors.
+     */
+    let mut errors = Vec::new();
+    let lines = expand_includes(source_name, source, &mut errors);
+    let file_order = include_order(&lines);
+    let lines = expand_macros(lines, &mut errors);
+
+    // Whatever did expand still goes through both passes, so every error is found in one run
     let mut sym_table = init_sym_table();
+    let mut warnings = Vec::new();
 
-    first_pass(source_file, &mut sym_table)?;
-    second_pass(source_file, &output_file, &mut sym_table)?;
+    first_pass(&lines, &mut sym_table, &mut errors, &mut warnings);
+    let listing = second_pass(&lines, &mut sym_table, &mut errors, &mut warnings);
 
-    println!("Finished assembling: {} -> {}", source_file, output_file);
-    Ok(())
+    if !errors.is_empty() {
+        return Err(in_source_order(errors, &file_order));
+    }
+
+    Ok(Program {
+        words: listing.iter().filter_map(|l| l.word).collect(),
+        listing,
+        sym_table,
+        warnings
+    })
+}
+
+fn include_order(lines: &[SourceLine]) -> Vec<String> {
+    // Every file in the program, in the order they were included
+    let mut files: Vec<String> = Vec::new();
+    for line in lines {
+        if !files.contains(&line.loc.file) {
+            files.push(line.loc.file.clone());
+        }
+    }
+    files
 }
 
-fn first_pass(source_file: &String, sym_table: &mut SymbolTable) -> Result<(), Error> {
+fn in_source_order(mut errors: Vec<AsmError>, file_order: &[String]) -> Vec<AsmError> {
+    // Report errors in source order rather than the order the passes found them, files in include order
+    let file_index = |file: &String| file_order.iter().position(|f| f == file).unwrap_or(file_order.len());
+    errors.sort_by_key(|e| e.location().map(|loc| (file_index(&loc.file), loc.line, loc.column)));
+    errors
+}
+
+fn first_pass(lines: &[SourceLine],
+              sym_table: &mut SymbolTable,
+              errors: &mut Vec<AsmError>,
+              warnings: &mut Vec<AsmWarning>) {
     /*
      *  Does the first pass and builds up the symbol table.
      *  Incrememnt current command whenever a C or A instruction is encountered.
      *  It is not incremented when a label, pseudocommand or a comment is encountered 
+     *  .equ constants are defined here too, so they can only use symbols defined above them.
+     *
+     *  A label may not be defined twice, but it may replace a predefined symbol (with a warning).
+     *  The program has to fit in ROM, which is checked here since this is where it's counted.
      */
-    let file = File::open(source_file)?;
-    let reader = BufReader::new(file);
-
     let mut current_command: u32 = 0;
     
-    for line in reader.lines() {
-        let line = line?;
-
-        if is_label(&line) { 
-            // This logic should probs be in parser since we're parsing the label...
-            let mut chars = line.chars();
-            chars.next();       // Remove first char '('
-            chars.next_back();  // Remove last char ')'
-
-            add_symbol(chars.as_str().to_string(), current_command.to_string(), sym_table)
-        } else if is_instruction(&line) {
+    for line in lines {
+        if is_label(&line.text) { 
+            match parse_label(&line.text, line.loc.clone()) {
+                Ok(label) if sym_table.labels.contains(&label) || sym_table.constants.contains(&label) => {
+                    errors.push(AsmError::DuplicateSymbol { loc: line.loc.clone(), text: label })
+                },
+                Ok(label) => {
+                    if is_predefined(&label) {
+                        warnings.push(AsmWarning::LabelShadowsPredefined { loc: line.loc.clone(), text: label.clone() });
+                    }
+                    add_label(label, current_command, sym_table)
+                },
+                Err(e)    => errors.push(e)
+            }
+        } else if is_directive(&line.text) {
+            match parse_equ(&line.text, line.loc.clone(), sym_table) {
+                Ok((name, _)) if find_address(&name, sym_table).is_some() => {
+                    errors.push(AsmError::DuplicateSymbol { loc: line.loc.clone(), text: name })
+                },
+                Ok((name, value)) => add_constant(name, value, sym_table),
+                Err(e)            => errors.push(e)
+            }
+        } else if is_instruction(&line.text) {
             // println!("Incrementing current command. {}", line);
+            if current_command == ROM_SIZE {
+                errors.push(AsmError::ProgramTooLarge { loc: line.loc.clone(), text: line.text.trim().to_string() });
+            }
             current_command += 1;
         }
     }
-    
-    Ok(())
 }
 
-fn second_pass(source_file: &String,
-               output_file: &String,
-               sym_table: &mut SymbolTable) -> Result<(), Error> {
+fn second_pass(lines: &[SourceLine],
+               sym_table: &mut SymbolTable,
+               errors: &mut Vec<AsmError>,
+               warnings: &mut Vec<AsmWarning>) -> Vec<ListingLine> {
     /* ----------------------------------------------------------------------- *
      *  Go through the entire program again, parse and translate the program.
      *  Each time a symbolic A-instruction is encountered (@xxx) where xxx is a symbol
@@ -62,29 +172,74 @@ fn second_pass(source_file: &String,
      *  If it's not found, it must represent a new variable. Add the pair (xxx, n) to the 
      *  symbol table, where n is the next available RAM address. The allocated RAM addresses
      *  are consecutive numbers, starting at 16.
+     *
+     *  Every source line is kept alongside the word it produced so a listing can be written.
+     *  Variables that spill into the screen or keyboard memory maps are warned about.
      * ----------------------------------------------------------------------- */
-    let file = File::open(source_file)?;
-    let reader = BufReader::new(file);
-    let mut file_writer = File::create(output_file)?;
+    let mut listing = Vec::new();
+    let mut address: u16 = 0;
 
-    for line in reader.lines() {
-        let line = line?;
-        let ins: Instruction = parse_line(line, sym_table);
+    for line in lines {
+        let mut entry = ListingLine { line: line.loc.line, address: None, word: None, source: line.text.clone() };
+
+        let variable_count = sym_table.variable_count;
+        let ins: Instruction = match parse_line(&line.text, line.loc.clone(), sym_table) {
+            Ok(ins) => ins,
+            Err(e)  => {
+                errors.push(e);
+                continue;
+            }
+        };
+
+        if sym_table.variable_count > variable_count {
+            let address = variable_count + 16;
+            if address == SCREEN || address == KBD {
+                warnings.push(AsmWarning::VariableInIoMemory {
+                    loc: ins.loc.clone(),
+                    text: sym_table.variables.last().cloned().unwrap_or_default(),
+                    address
+                });
+            }
+        }
 
         if ins.line_type.is_none() {
+            listing.push(entry);
             continue;
         }
 
-        let code: String = translate_instruction(ins);
-        write_instruction(&mut file_writer, &code)?;
+        let word = translate_instruction(&ins).and_then(|bits| {
+            u16::from_str_radix(&bits, 2).map_err(|_| AsmError::MalformedInstruction {
+                loc: ins.loc.clone(),
+                text: ins.text.clone()
+            })
+        });
+
+        match word {
+            Ok(word) => {
+                entry.address = Some(address);
+                entry.word = Some(word);
+                listing.push(entry);
+            },
+            Err(e)   => errors.push(e)
+        }
+        address = address.wrapping_add(1);
     }
 
-    Ok(())
+    listing
 }
 
-fn write_instruction(file_writer: &mut impl Write, // Must impl write
-                     code: &String) -> Result<(), std::io::Error> {
-    file_writer.write_all(code.as_bytes())?;
-    file_writer.write_all(b"\n")?;
-    Ok(())
-}
\ No newline at end of file
+fn with_extension(source_file: &str, extension: &str) -> String {
+    if let Some(index) = source_file.rfind('.') {
+        format!("{}.{}", &source_file[..index], extension)
+    } else {
+        format!("{}.{}", source_file, extension)
+    }
+}
+
+fn write_file(output_file: &str,
+              write: impl FnOnce(&mut BufWriter<File>) -> Result<(), Error>) -> Result<(), Vec<AsmError>> {
+    let io_error = |error| vec![AsmError::Io { file: output_file.to_string(), error }];
+
+    let mut writer = BufWriter::new(File::create(output_file).map_err(io_error)?);
+    write(&mut writer).and_then(|_| writer.flush()).map_err(io_error)
+}
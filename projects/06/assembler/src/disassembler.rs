@@ -0,0 +1,144 @@
+use std::collections::BTreeSet;
+use std::fs;
+
+use hack_core::instruction::{lookup_mnemonic, COMP_CODES, DEST_CODES, JUMP_CODES};
+
+use crate::error::{AsmError, Location};
+
+pub fn disassemble_file(hack_file: &str) -> Result<String, Vec<AsmError>> {
+    /*
+     *  Disassembles hack_file into <name>.dis.asm and returns the name of the output file.
+     *  The .dis.asm suffix means the original source (if there is one) is never overwritten.
+     */
+    let output_file = if let Some(index) = hack_file.rfind('.') {
+        hack_file[..index].to_owned() + ".dis.asm"
+    } else {
+        hack_file.to_owned() + ".dis.asm"
+    };
+
+    let binary = fs::read_to_string(hack_file)
+        .map_err(|error| vec![AsmError::Io { file: hack_file.to_string(), error }])?;
+
+    let words = read_words(hack_file, &binary)?;
+    let source = disassemble(hack_file, &words)?;
+
+    fs::write(&output_file, source)
+        .map_err(|error| vec![AsmError::Io { file: output_file.clone(), error }])?;
+
+    Ok(output_file)
+}
+
+pub fn read_words(source_name: &str, binary: &str) -> Result<Vec<u16>, Vec<AsmError>> {
+    // A .hack file has one 16 character binary word per line
+    let mut words = Vec::new();
+    let mut errors = Vec::new();
+
+    for (i, line) in binary.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        match u16::from_str_radix(line, 2) {
+            Ok(word) if line.len() == 16 => words.push(word),
+            _ => errors.push(AsmError::InvalidWord {
+                loc: Location::new(source_name, i + 1, 1),
+                text: line.to_string()
+            })
+        }
+    }
+
+    if errors.is_empty() { Ok(words) } else { Err(errors) }
+}
+
+pub fn disassemble(source_name: &str, words: &[u16]) -> Result<String, Vec<AsmError>> {
+    /*
+     *  Turns each word back into @value or dest=comp;jump.
+     *
+     *  An A-instruction directly followed by a jump is treated as loading a jump target,
+     *  so a label (L<address>) is made for that address and the A-instruction uses it.
+     *  Only addresses inside the program (or just past the end) can be labelled,
+     *  anything else is left as a number. Since the label resolves to the same address,
+     *  the output reassembles to exactly the same binary.
+     */
+    let mut targets = BTreeSet::new();
+    for (i, pair) in words.windows(2).enumerate() {
+        let (a, c) = (pair[0], pair[1]);
+        if is_a_instruction(a) && !is_a_instruction(c) && c & 0b111 != 0 && (a as usize) <= words.len() {
+            targets.insert(i);
+        }
+    }
+
+    let labels: BTreeSet<usize> = targets.iter().map(|&i| words[i] as usize).collect();
+
+    let mut errors = Vec::new();
+    let mut lines = vec![format!("// Disassembled from {}", source_name)];
+
+    for (address, &word) in words.iter().enumerate() {
+        if labels.contains(&address) {
+            lines.push(format!("(L{})", address));
+        }
+
+        if is_a_instruction(word) {
+            if targets.contains(&address) {
+                lines.push(format!("    @L{}", word));
+            } else {
+                lines.push(format!("    @{}", word));
+            }
+            continue;
+        }
+
+        match decode_c_instruction(word) {
+            Some(ins) => lines.push(format!("    {}", ins)),
+            None => errors.push(AsmError::InvalidWord {
+                loc: Location::new(source_name, address + 1, 1),
+                text: format!("{:016b}", word)
+            })
+        }
+    }
+
+    // A jump to just past the last instruction needs its label at the very end
+    if labels.contains(&words.len()) {
+        lines.push(format!("(L{})", words.len()));
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    lines.push(String::new());
+    Ok(lines.join("\n"))
+}
+
+fn is_a_instruction(word: u16) -> bool {
+    word & 0x8000 == 0
+}
+
+fn decode_c_instruction(word: u16) -> Option<String> {
+    /*
+     *  111accccccdddjjj
+     *  The assembler always sets the two unused bits, so a word without them
+     *  couldn't be reassembled to the same binary.
+     */
+    let bits = format!("{:016b}", word);
+    if !bits.starts_with("111") {
+        return None;
+    }
+
+    let comp = lookup_mnemonic(&COMP_CODES, &bits[3..10])?;
+    let dest = lookup_mnemonic(&DEST_CODES, &bits[10..13])?;
+    let jump = lookup_mnemonic(&JUMP_CODES, &bits[13..16])?;
+
+    let mut ins = String::new();
+    if !dest.is_empty() {
+        ins.push_str(dest);
+        ins.push('=');
+    }
+    ins.push_str(comp);
+    if !jump.is_empty() {
+        ins.push(';');
+        ins.push_str(jump);
+    }
+
+    Some(ins)
+}
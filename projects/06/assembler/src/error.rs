@@ -0,0 +1,212 @@
+use std::fmt;
+use std::io;
+
+// Position of something in a source file. Lines and columns both start at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug)]
+pub enum AsmError {
+    Io { file: String, error: io::Error },
+
+    InvalidDest { loc: Location, text: String },
+    InvalidComp { loc: Location, text: String },
+    InvalidJump { loc: Location, text: String },
+    InvalidConstant { loc: Location, text: String },
+    InvalidSymbol { loc: Location, text: String },
+    MalformedLabel { loc: Location, text: String },
+    MalformedInstruction { loc: Location, text: String },
+    MalformedExpression { loc: Location, text: String },
+    MalformedDirective { loc: Location, text: String },
+    UndefinedSymbol { loc: Location, text: String },
+    DuplicateSymbol { loc: Location, text: String },
+    ConstantOutOfRange { loc: Location, text: String, value: u32 },
+    ProgramTooLarge { loc: Location, text: String },
+
+    // Includes
+    IncludeFailed { loc: Location, text: String, error: io::Error },
+    RecursiveInclude { loc: Location, text: String },
+
+    // Macros
+    MalformedMacro { loc: Location, text: String },
+    UnterminatedMacro { loc: Location, text: String },
+    UnmatchedEndm { loc: Location, text: String },
+    DuplicateMacro { loc: Location, text: String },
+    RecursiveMacro { loc: Location, text: String },
+    WrongMacroArguments { loc: Location, text: String, expected: usize, found: usize },
+    UnknownDirective { loc: Location, text: String },
+
+    // Disassembler
+    InvalidWord { loc: Location, text: String },
+}
+
+impl Location {
+    pub fn new(file: &str, line: usize, column: usize) -> Location {
+        Location {
+            file: file.to_string(),
+            line,
+            column,
+        }
+    }
+
+    pub fn at_column(&self, column: usize) -> Location {
+        Location {
+            file: self.file.clone(),
+            line: self.line,
+            column,
+        }
+    }
+}
+
+impl AsmError {
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            AsmError::Io { .. } => None,
+            AsmError::InvalidDest { loc, .. }
+            | AsmError::InvalidComp { loc, .. }
+            | AsmError::InvalidJump { loc, .. }
+            | AsmError::InvalidConstant { loc, .. }
+            | AsmError::InvalidSymbol { loc, .. }
+            | AsmError::MalformedLabel { loc, .. }
+            | AsmError::MalformedInstruction { loc, .. }
+            | AsmError::MalformedExpression { loc, .. }
+            | AsmError::MalformedDirective { loc, .. }
+            | AsmError::UndefinedSymbol { loc, .. }
+            | AsmError::DuplicateSymbol { loc, .. }
+            | AsmError::ConstantOutOfRange { loc, .. }
+            | AsmError::ProgramTooLarge { loc, .. }
+            | AsmError::IncludeFailed { loc, .. }
+            | AsmError::RecursiveInclude { loc, .. }
+            | AsmError::MalformedMacro { loc, .. }
+            | AsmError::UnterminatedMacro { loc, .. }
+            | AsmError::UnmatchedEndm { loc, .. }
+            | AsmError::DuplicateMacro { loc, .. }
+            | AsmError::RecursiveMacro { loc, .. }
+            | AsmError::WrongMacroArguments { loc, .. }
+            | AsmError::UnknownDirective { loc, .. }
+            | AsmError::InvalidWord { loc, .. } => Some(loc),
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsmError::Io { file, error } => write!(f, "{}: {}", file, error),
+            AsmError::InvalidDest { loc, text } => {
+                write!(f, "{}: invalid dest mnemonic '{}'", loc, text)
+            }
+            AsmError::InvalidComp { loc, text } => {
+                write!(f, "{}: invalid comp mnemonic '{}'", loc, text)
+            }
+            AsmError::InvalidJump { loc, text } => {
+                write!(f, "{}: invalid jump mnemonic '{}'", loc, text)
+            }
+            AsmError::InvalidConstant { loc, text } => {
+                write!(f, "{}: invalid constant '{}'", loc, text)
+            }
+            AsmError::InvalidSymbol { loc, text } => {
+                write!(f, "{}: invalid symbol '{}'", loc, text)
+            }
+            AsmError::MalformedLabel { loc, text } => {
+                write!(f, "{}: malformed label declaration '{}'", loc, text)
+            }
+            AsmError::MalformedInstruction { loc, text } => {
+                write!(f, "{}: malformed instruction '{}'", loc, text)
+            }
+            AsmError::MalformedExpression { loc, text } => {
+                write!(f, "{}: malformed expression '{}'", loc, text)
+            }
+            AsmError::MalformedDirective { loc, text } => {
+                write!(f, "{}: malformed directive '{}'", loc, text)
+            }
+            AsmError::UndefinedSymbol { loc, text } => {
+                write!(f, "{}: undefined symbol '{}'", loc, text)
+            }
+            AsmError::DuplicateSymbol { loc, text } => {
+                write!(f, "{}: symbol '{}' is already defined", loc, text)
+            }
+            AsmError::ConstantOutOfRange { loc, text, value } if *text == value.to_string() => {
+                write!(f, "{}: constant {} is too large, A-instructions only take values up to 32767", loc, value)
+            }
+            AsmError::ConstantOutOfRange { loc, text, value } => {
+                write!(f, "{}: '{}' is {}, A-instructions only take values up to 32767", loc, text, value)
+            }
+            AsmError::ProgramTooLarge { loc, text } => {
+                write!(f, "{}: '{}' is past the end of the 32K ROM", loc, text)
+            }
+            AsmError::IncludeFailed { loc, text, error } => {
+                write!(f, "{}: cannot include '{}': {}", loc, text, error)
+            }
+            AsmError::RecursiveInclude { loc, text } => {
+                write!(f, "{}: '{}' includes itself", loc, text)
+            }
+            AsmError::MalformedMacro { loc, text } => {
+                write!(f, "{}: malformed macro definition '{}'", loc, text)
+            }
+            AsmError::UnterminatedMacro { loc, text } => {
+                write!(f, "{}: macro '{}' has no matching .endm", loc, text)
+            }
+            AsmError::UnmatchedEndm { loc, text } => {
+                write!(f, "{}: '{}' without a matching .macro", loc, text)
+            }
+            AsmError::DuplicateMacro { loc, text } => {
+                write!(f, "{}: macro '{}' is already defined", loc, text)
+            }
+            AsmError::RecursiveMacro { loc, text } => {
+                write!(f, "{}: macro '{}' expands too deeply, is it recursive?", loc, text)
+            }
+            AsmError::WrongMacroArguments { loc, text, expected, found } => {
+                write!(f, "{}: macro '{}' takes {} argument(s) but {} were given", loc, text, expected, found)
+            }
+            AsmError::UnknownDirective { loc, text } => {
+                write!(f, "{}: unknown directive '{}'", loc, text)
+            }
+            AsmError::InvalidWord { loc, text } => {
+                write!(f, "{}: '{}' is not a valid Hack instruction", loc, text)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+// Problems that still produce a program, but probably not the one that was meant
+#[derive(Debug)]
+pub enum AsmWarning {
+    LabelShadowsPredefined { loc: Location, text: String },
+    VariableInIoMemory { loc: Location, text: String, address: u32 },
+}
+
+impl AsmWarning {
+    pub fn location(&self) -> &Location {
+        match self {
+            AsmWarning::LabelShadowsPredefined { loc, .. }
+            | AsmWarning::VariableInIoMemory { loc, .. } => loc,
+        }
+    }
+}
+
+impl fmt::Display for AsmWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsmWarning::LabelShadowsPredefined { loc, text } => {
+                write!(f, "{}: label '{}' shadows the predefined symbol", loc, text)
+            }
+            AsmWarning::VariableInIoMemory { loc, text, address } => {
+                let region = if *address >= 24576 { "KBD" } else { "SCREEN" };
+                write!(f, "{}: variable '{}' is allocated at {}, in the {} memory map", loc, text, address, region)
+            }
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use crate::symbol_table::{find_address, SymbolTable};
+
+// Why an expression couldn't be evaluated, the caller adds the location
+pub enum ExprError {
+    Undefined(String),
+    Syntax(String)
+}
+
+pub fn is_expression(value: &str) -> bool {
+    value.contains(['+', '-', '*', '/', '(', ')'])
+}
+
+pub fn evaluate(expr: &str, sym_table: &SymbolTable) -> Result<i64, ExprError> {
+    /*
+     *  Constant expressions in A-instructions and .equ, e.g. @SCREEN+32 or @ROWS*32-1
+     *
+     *  expr   := term (('+' | '-') term)*
+     *  term   := factor (('*' | '/') factor)*
+     *  factor := number | symbol | '(' expr ')' | '-' factor
+     *
+     *  Every symbol has to be defined already, an expression never allocates a variable.
+     */
+    let tokens = tokenize(expr)?;
+    let mut parser = ExprParser { tokens: &tokens, pos: 0, sym_table, expr };
+
+    let value = parser.expr()?;
+    if parser.pos != tokens.len() {
+        return Err(ExprError::Syntax(expr.to_string()));
+    }
+
+    Ok(value)
+}
+
+enum Token {
+    Number(i64),
+    Symbol(String),
+    Op(char)
+}
+
+fn tokenize(expr: &str) -> Result<Vec<Token>, ExprError> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if "+-*/()".contains(c) {
+            tokens.push(Token::Op(c));
+            chars.next();
+        } else if c.is_ascii_alphanumeric() || "_.$:".contains(c) {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || "_.$:".contains(c)) {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+
+            if word.starts_with(|c: char| c.is_ascii_digit()) {
+                let n = word.parse().map_err(|_| ExprError::Syntax(expr.to_string()))?;
+                tokens.push(Token::Number(n));
+            } else {
+                tokens.push(Token::Symbol(word));
+            }
+        } else {
+            return Err(ExprError::Syntax(expr.to_string()));
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct ExprParser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    sym_table: &'a SymbolTable,
+    expr: &'a str
+}
+
+impl ExprParser<'_> {
+    fn expr(&mut self) -> Result<i64, ExprError> {
+        let mut value = self.term()?;
+        while let Some(op) = self.next_op(&['+', '-']) {
+            let rhs = self.term()?;
+            value = match op {
+                '+' => value.checked_add(rhs),
+                _   => value.checked_sub(rhs)
+            }.ok_or_else(|| self.syntax_error())?;
+        }
+        Ok(value)
+    }
+
+    fn term(&mut self) -> Result<i64, ExprError> {
+        let mut value = self.factor()?;
+        while let Some(op) = self.next_op(&['*', '/']) {
+            let rhs = self.factor()?;
+            value = match op {
+                '*' => value.checked_mul(rhs),
+                _   => value.checked_div(rhs)
+            }.ok_or_else(|| self.syntax_error())?;
+        }
+        Ok(value)
+    }
+
+    fn factor(&mut self) -> Result<i64, ExprError> {
+        let token = self.tokens.get(self.pos).ok_or_else(|| self.syntax_error())?;
+        self.pos += 1;
+
+        match token {
+            Token::Number(n) => Ok(*n),
+            Token::Symbol(name) => match find_address(name, self.sym_table) {
+                Some(value) => Ok(value as i64),
+                None => Err(ExprError::Undefined(name.clone()))
+            },
+            Token::Op('-') => self.factor()?.checked_neg().ok_or_else(|| self.syntax_error()),
+            Token::Op('(') => {
+                let value = self.expr()?;
+                match self.next_op(&[')']) {
+                    Some(_) => Ok(value),
+                    None => Err(self.syntax_error())
+                }
+            },
+            Token::Op(_) => Err(self.syntax_error())
+        }
+    }
+
+    fn next_op(&mut self, ops: &[char]) -> Option<char> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) if ops.contains(op) => {
+                self.pos += 1;
+                Some(*op)
+            },
+            _ => None
+        }
+    }
+
+    fn syntax_error(&self) -> ExprError {
+        ExprError::Syntax(self.expr.to_string())
+    }
+}
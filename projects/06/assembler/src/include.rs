@@ -0,0 +1,67 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::AsmError;
+use crate::macros::{to_source_lines, SourceLine};
+use crate::parser::strip_comment;
+
+pub fn expand_includes(source_name: &str, source: &str, errors: &mut Vec<AsmError>) -> Vec<SourceLine> {
+    /*
+     *  Replaces every `.include "file.asm"` with the lines of that file, recursively.
+     *  Paths are relative to the file doing the including. Included lines keep their
+     *  own file name and line number so errors point at the right place.
+     *  An include that fails is left out and the rest of the program is still expanded.
+     */
+    let mut lines = Vec::new();
+    let mut including = vec![canonical(Path::new(source_name))];
+
+    include_source(source_name, source, &mut including, &mut lines, errors);
+    lines
+}
+
+fn include_source(source_name: &str,
+                  source: &str,
+                  including: &mut Vec<PathBuf>,
+                  lines: &mut Vec<SourceLine>,
+                  errors: &mut Vec<AsmError>) {
+    for line in to_source_lines(source_name, source) {
+        let code = strip_comment(&line.text).trim();
+        if code.split_whitespace().next() != Some(".include") {
+            lines.push(line);
+            continue;
+        }
+
+        let file = code[".include".len()..].trim();
+        let file = match file.strip_prefix('"').and_then(|f| f.strip_suffix('"')) {
+            Some(file) if !file.is_empty() => file,
+            _ => {
+                errors.push(AsmError::MalformedDirective { loc: line.loc, text: code.to_string() });
+                continue;
+            }
+        };
+
+        let path = Path::new(source_name).parent().unwrap_or(Path::new("")).join(file);
+        let path_name = path.to_string_lossy().into_owned();
+
+        if including.contains(&canonical(&path)) {
+            errors.push(AsmError::RecursiveInclude { loc: line.loc, text: path_name });
+            continue;
+        }
+
+        match fs::read_to_string(&path) {
+            Ok(included) => {
+                lines.push(SourceLine { loc: line.loc, text: format!("// {}", code) });
+
+                including.push(canonical(&path));
+                include_source(&path_name, &included, including, lines, errors);
+                including.pop();
+            },
+            Err(error) => errors.push(AsmError::IncludeFailed { loc: line.loc, text: path_name, error })
+        }
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    // In-memory sources don't exist on disk, so fall back to the name as given
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
@@ -0,0 +1,25 @@
+// Hack assembler library.
+// The `assembler` binary is a thin wrapper around `assembler::assemble`, other tools
+// can use `assemble_str` to assemble a program held in memory.
+
+pub mod assembler;
+pub mod disassembler;
+pub mod error;
+pub mod expression;
+pub mod include;
+pub mod listing;
+pub mod macros;
+pub mod output;
+pub mod parser;
+pub mod symbol_table;
+pub mod translator;
+
+pub use crate::assembler::{assemble, assemble_source, AssembleOptions, Program};
+pub use crate::disassembler::{disassemble, disassemble_file};
+pub use crate::error::{AsmError, AsmWarning, Location};
+pub use crate::output::{HackWriter, OutputFormat};
+
+pub fn assemble_str(source: &str) -> Result<Vec<u16>, Vec<AsmError>> {
+    // Assembles a complete program and returns one 16-bit word per instruction
+    assemble_source("<source>", source).map(|program| program.words)
+}
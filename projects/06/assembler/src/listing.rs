@@ -0,0 +1,64 @@
+use std::io::{Error, Write};
+
+use crate::assembler::Program;
+use crate::symbol_table::{find_address, SymbolTable};
+
+pub fn write_listing(writer: &mut impl Write, program: &Program) -> Result<(), Error> {
+    /*
+     *  ROM address, binary word and the source line side by side.
+     *  Lines that don't produce a word (labels, comments, blanks) are kept
+     *  so the listing reads like the source file.
+     *
+     *     ROM  Word              Source
+     *       0  0000000000000000     @R0
+     *                            (ITSR0)
+     */
+    writeln!(writer, "{:>5}  {:<16}  Source", "ROM", "Word")?;
+
+    for entry in &program.listing {
+        let source = entry.source.trim_end();
+
+        match (entry.address, entry.word) {
+            (Some(address), Some(word)) => writeln!(writer, "{:>5}  {:016b}  {}", address, word, source)?,
+            _                           => writeln!(writer, "{}", format!("{:25}{}", "", source).trim_end())?
+        }
+    }
+
+    Ok(())
+}
+
+pub fn write_symbols(writer: &mut impl Write, sym_table: &SymbolTable) -> Result<(), Error> {
+    /*
+     *  Labels with the ROM address they resolved to, .equ constants with their
+     *  value, then variables with the RAM address they were allocated (from 16 upwards).
+     *  The predefined symbols are left out since they never change.
+     */
+    let width = sym_table.labels.iter()
+                                .chain(sym_table.variables.iter())
+                                .chain(sym_table.constants.iter())
+                                .map(|s| s.len())
+                                .max()
+                                .unwrap_or(0);
+
+    writeln!(writer, "// Labels (ROM address)")?;
+    for label in &sym_table.labels {
+        let address = find_address(label, sym_table).unwrap_or_default();
+        writeln!(writer, "{:<width$}  {}", label, address, width = width)?;
+    }
+
+    writeln!(writer)?;
+    writeln!(writer, "// Constants (.equ)")?;
+    for constant in &sym_table.constants {
+        let value = find_address(constant, sym_table).unwrap_or_default();
+        writeln!(writer, "{:<width$}  {}", constant, value, width = width)?;
+    }
+
+    writeln!(writer)?;
+    writeln!(writer, "// Variables (RAM address)")?;
+    for variable in &sym_table.variables {
+        let address = find_address(variable, sym_table).unwrap_or_default();
+        writeln!(writer, "{:<width$}  {}", variable, address, width = width)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,251 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use crate::error::{AsmError, Location};
+use crate::parser::strip_comment;
+
+// Built-in pseudo-instructions, written with the same .macro syntax users have
+const BUILTIN_MACROS: &str = "
+.macro PUSHD
+    @SP
+    A=M
+    M=D
+    @SP
+    M=M+1
+.endm
+
+.macro POPD
+    @SP
+    AM=M-1
+    D=M
+.endm
+
+.macro LOADD address
+    @%address
+    D=M
+.endm
+
+.macro STORED address
+    @%address
+    M=D
+.endm
+
+.macro GOTO label
+    @%label
+    0;JMP
+.endm
+
+.macro IF_D_JGT label
+    @%label
+    D;JGT
+.endm
+
+.macro IF_D_JEQ label
+    @%label
+    D;JEQ
+.endm
+
+.macro IF_D_JGE label
+    @%label
+    D;JGE
+.endm
+
+.macro IF_D_JLT label
+    @%label
+    D;JLT
+.endm
+
+.macro IF_D_JNE label
+    @%label
+    D;JNE
+.endm
+
+.macro IF_D_JLE label
+    @%label
+    D;JLE
+.endm
+";
+
+// Directives that are left for the assembler passes
+const DIRECTIVES: [&str; 1] = [".equ"];
+
+// Deep enough for any sensible nesting, shallow enough to catch a macro that expands itself
+const MAX_EXPANSION_DEPTH: usize = 32;
+
+// One line of the program after preprocessing, with where it came from
+pub struct SourceLine {
+    pub loc: Location,
+    pub text: String
+}
+
+struct Macro {
+    params: Vec<String>,
+    body: Vec<String>
+}
+
+pub fn expand_macros(lines: Vec<SourceLine>, errors: &mut Vec<AsmError>) -> Vec<SourceLine> {
+    /*
+     *  Runs before the first pass so label addresses are counted on the expanded program.
+     *
+     *  .macro NAME arg1 arg2     Starts a definition, ended by .endm
+     *      @%arg1                %arg is replaced by the argument given for arg
+     *  (LOOP%#)                  %# is replaced by a number unique to each expansion,
+     *  .endm                     so labels inside a macro don't clash
+     *
+     *  NAME x, y                 Expands the macro (arguments split on commas or spaces)
+     *
+     *  Expanded lines keep the location of the line that invoked the macro, and the
+     *  invocation itself is left behind as a comment so it shows up in the listing.
+     *  Lines that can't be expanded are left out, so the passes after this one
+     *  don't report them a second time.
+     */
+    let mut macros = HashMap::new();
+
+    let builtins = to_source_lines("<builtin>", BUILTIN_MACROS);
+    collect_definitions(builtins, &mut macros, errors);
+
+    let lines = collect_definitions(lines, &mut macros, errors);
+
+    let mut expanded = Vec::new();
+    let mut expansion_count = 0;
+    for line in lines {
+        expand_line(line, &macros, 0, &mut expansion_count, &mut expanded, errors);
+    }
+    expanded
+}
+
+pub fn to_source_lines(source_name: &str, source: &str) -> Vec<SourceLine> {
+    source.lines()
+          .enumerate()
+          .map(|(i, line)| SourceLine { loc: Location::new(source_name, i + 1, 1), text: line.to_string() })
+          .collect()
+}
+
+fn collect_definitions(lines: Vec<SourceLine>,
+                       macros: &mut HashMap<String, Macro>,
+                       errors: &mut Vec<AsmError>) -> Vec<SourceLine> {
+    // Removes every .macro ... .endm block from lines and adds it to macros
+    let mut remaining = Vec::new();
+    let mut current: Option<(SourceLine, String, Macro)> = None;
+
+    for line in lines {
+        let code = strip_comment(&line.text).trim();
+        let directive = code.split_whitespace().next().unwrap_or_default();
+
+        match (directive, current.is_some()) {
+            (".macro", false) => {
+                let mut words = code.split_whitespace().skip(1);
+                let name = words.next().unwrap_or_default().to_string();
+                let params: Vec<String> = words.map(|w| w.trim_end_matches(',').to_string()).collect();
+
+                if !is_macro_name(&name) || !params.iter().all(|p| is_macro_name(p)) {
+                    errors.push(AsmError::MalformedMacro { loc: line.loc.clone(), text: code.to_string() });
+                }
+                current = Some((line, name, Macro { params, body: Vec::new() }));
+            },
+            (".macro", true) => {
+                // Definitions can't be nested, but the body can invoke other macros
+                errors.push(AsmError::MalformedMacro { loc: line.loc, text: code.to_string() });
+            },
+            (".endm", true) => {
+                let (start, name, definition) = current.take().unwrap();
+                match macros.entry(name) {
+                    Entry::Occupied(entry) => {
+                        errors.push(AsmError::DuplicateMacro { loc: start.loc, text: entry.key().clone() })
+                    },
+                    Entry::Vacant(entry) => {
+                        entry.insert(definition);
+                    }
+                }
+            },
+            (".endm", false) => {
+                errors.push(AsmError::UnmatchedEndm { loc: line.loc, text: code.to_string() });
+            },
+            (_, true) => {
+                if let Some((_, _, definition)) = current.as_mut() {
+                    definition.body.push(line.text);
+                }
+            },
+            (_, false) => remaining.push(line)
+        }
+    }
+
+    if let Some((start, name, _)) = current {
+        errors.push(AsmError::UnterminatedMacro { loc: start.loc, text: name });
+    }
+
+    remaining
+}
+
+fn expand_line(line: SourceLine,
+               macros: &HashMap<String, Macro>,
+               depth: usize,
+               expansion_count: &mut usize,
+               expanded: &mut Vec<SourceLine>,
+               errors: &mut Vec<AsmError>) {
+    let code = strip_comment(&line.text).trim();
+    let mut words = code.splitn(2, char::is_whitespace);
+    let name = words.next().unwrap_or_default();
+
+    let definition = match macros.get(name) {
+        Some(definition) => definition,
+        None => {
+            if name.starts_with('.') && !DIRECTIVES.contains(&name) {
+                errors.push(AsmError::UnknownDirective { loc: line.loc, text: name.to_string() });
+                return;
+            }
+            expanded.push(line);
+            return;
+        }
+    };
+
+    let args: Vec<&str> = words.next()
+                               .unwrap_or_default()
+                               .split(|c: char| c == ',' || c.is_whitespace())
+                               .filter(|a| !a.is_empty())
+                               .collect();
+
+    if args.len() != definition.params.len() {
+        errors.push(AsmError::WrongMacroArguments {
+            loc: line.loc,
+            text: name.to_string(),
+            expected: definition.params.len(),
+            found: args.len()
+        });
+        return;
+    }
+
+    if depth >= MAX_EXPANSION_DEPTH {
+        errors.push(AsmError::RecursiveMacro { loc: line.loc, text: name.to_string() });
+        return;
+    }
+
+    // Substitute longer parameter names first so %ab isn't mangled by %a
+    let mut substitutions: Vec<(String, &str)> = definition.params.iter()
+                                                                  .map(|p| format!("%{}", p))
+                                                                  .zip(args)
+                                                                  .collect();
+    substitutions.sort_by_key(|(param, _)| std::cmp::Reverse(param.len()));
+
+    let unique = expansion_count.to_string();
+    *expansion_count += 1;
+
+    expanded.push(SourceLine { loc: line.loc.clone(), text: format!("// {}", code) });
+
+    for body_line in &definition.body {
+        let mut text = body_line.clone();
+        for (param, arg) in &substitutions {
+            text = text.replace(param.as_str(), arg);
+        }
+        text = text.replace("%#", &unique);
+
+        let body_line = SourceLine { loc: line.loc.clone(), text };
+        expand_line(body_line, macros, depth + 1, expansion_count, expanded, errors);
+    }
+}
+
+fn is_macro_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
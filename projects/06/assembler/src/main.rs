@@ -1,30 +1,78 @@
 use std:: {env, process};
 
-mod assembler;
-mod parser;
-mod symbol_table;
-mod translator;
+use assembler::{AssembleOptions, OutputFormat};
+
+const USAGE: &str = "Usage: assembler [--format hack|bin|ihex|memb|memh|logisim] [--listing] [--symbols] <path-to-source-file>
+       assembler --disassemble <path-to-hack-file>";
 
 fn main() {
-    let args: Vec<String> = env::args().collect();    
+    let mut options = AssembleOptions::default();
+    let mut files: Vec<String> = Vec::new();
+    let mut disassemble = false;
 
-    match args.len() {
-        1 => {
-            println!("Please specify a source file to be assembled");
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                let name = args.next().unwrap_or_default();
+                options.format = OutputFormat::from_name(&name).unwrap_or_else(|| {
+                    println!("Unknown output format '{}'. Expected one of: {}", name, OutputFormat::NAMES.join(", "));
+                    process::exit(-1);
+                });
+            }
+            "--listing" => options.listing = true,   // Also write <name>.lst
+            "--symbols" => options.symbols = true,   // Also write <name>.sym
+            "--disassemble" => disassemble = true,   // .hack -> <name>.dis.asm
+            flag if flag.starts_with("--") => {
+                println!("Unknown option {}\n{}", flag, USAGE);
+                process::exit(-1);
+            }
+            _ => files.push(arg)
+        }
+    }
+
+    match files.len() {
+        0 => {
+            println!("Please specify a source file to be assembled\n{}", USAGE);
             process::exit(-1);
         }
 
-        n if n > 2 => {
-            println!("Too many arguments provided. Expected only 1.");
+        n if n > 1 => {
+            println!("Too many arguments provided. Expected only 1.\n{}", USAGE);
             process::exit(-1);
         }
 
+        _ if disassemble => {
+            println!("Disassembling {}", &files[0]);
+            match assembler::disassemble_file(&files[0]) {
+                Ok(output_file) => println!("Finished disassembling: {} -> {}", files[0], output_file),
+                Err(errors) => {
+                    for e in &errors {
+                        eprintln!("error: {}", e);
+                    }
+                    eprintln!("Failed to disassemble {}: {} error(s)", files[0], errors.len());
+                    process::exit(-1);
+                }
+            }
+        }
+
         _ => {
-            println!("Assembling {}", &args[1]);
-            match assembler::assemble(&args[1]) {
-                Ok(()) => (),
-                Err(e) => println!("Error while assembling source file {}: {}", args[1], e)
+            println!("Assembling {}", &files[0]);
+            match assembler::assemble(&files[0], &options) {
+                Ok((output_file, warnings)) => {
+                    for w in &warnings {
+                        eprintln!("warning: {}", w);
+                    }
+                    println!("Finished assembling: {} -> {}", files[0], output_file)
+                },
+                Err(errors) => {
+                    for e in &errors {
+                        eprintln!("error: {}", e);
+                    }
+                    eprintln!("Failed to assemble {}: {} error(s)", files[0], errors.len());
+                    process::exit(-1);
+                }
             }
         }
     }
-}
\ No newline at end of file
+}
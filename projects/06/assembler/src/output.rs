@@ -0,0 +1,176 @@
+use std::io::{Error, Write};
+
+// Writes assembled words in some file format. New formats only need to implement this.
+pub trait HackWriter {
+    // Extension of the output file, without the dot
+    fn extension(&self) -> &'static str;
+
+    fn write_words(&self, writer: &mut dyn Write, words: &[u16]) -> Result<(), Error>;
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum OutputFormat {
+    #[default]
+    Text,       // .hack, one ASCII 0/1 line per word
+    Binary,     // .bin, packed big-endian words
+    IntelHex,   // .hex
+    ReadMemB,   // .mem, for Verilog $readmemb
+    ReadMemH,   // .memh, for Verilog $readmemh
+    Logisim     // .img, Logisim "v2.0 raw" ROM image
+}
+
+impl OutputFormat {
+    pub const NAMES: [&'static str; 6] = ["hack", "bin", "ihex", "memb", "memh", "logisim"];
+
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        match name {
+            "hack"    => Some(OutputFormat::Text),
+            "bin"     => Some(OutputFormat::Binary),
+            "ihex"    => Some(OutputFormat::IntelHex),
+            "memb"    => Some(OutputFormat::ReadMemB),
+            "memh"    => Some(OutputFormat::ReadMemH),
+            "logisim" => Some(OutputFormat::Logisim),
+            _         => None
+        }
+    }
+
+    pub fn writer(&self) -> &'static dyn HackWriter {
+        match self {
+            OutputFormat::Text     => &TextWriter,
+            OutputFormat::Binary   => &BinaryWriter,
+            OutputFormat::IntelHex => &IntelHexWriter,
+            OutputFormat::ReadMemB => &ReadMemWriter { hex: false },
+            OutputFormat::ReadMemH => &ReadMemWriter { hex: true },
+            OutputFormat::Logisim  => &LogisimWriter
+        }
+    }
+}
+
+pub struct TextWriter;
+
+impl HackWriter for TextWriter {
+    fn extension(&self) -> &'static str {
+        "hack"
+    }
+
+    fn write_words(&self, writer: &mut dyn Write, words: &[u16]) -> Result<(), Error> {
+        for word in words {
+            writeln!(writer, "{:016b}", word)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct BinaryWriter;
+
+impl HackWriter for BinaryWriter {
+    fn extension(&self) -> &'static str {
+        "bin"
+    }
+
+    fn write_words(&self, writer: &mut dyn Write, words: &[u16]) -> Result<(), Error> {
+        for word in words {
+            writer.write_all(&word.to_be_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+pub struct IntelHexWriter;
+
+impl HackWriter for IntelHexWriter {
+    fn extension(&self) -> &'static str {
+        "hex"
+    }
+
+    fn write_words(&self, writer: &mut dyn Write, words: &[u16]) -> Result<(), Error> {
+        /*
+         *  Data records of up to 16 bytes (8 words, big-endian) then an end of file record.
+         *  Addresses are byte addresses, the whole 32K word ROM is exactly 64K bytes so
+         *  extended address records are never needed.
+         *
+         *  :LLAAAATT<data>CC  (length, address, type, data, checksum)
+         */
+        let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_be_bytes()).collect();
+
+        for (i, chunk) in bytes.chunks(16).enumerate() {
+            let address = (i * 16) as u16;
+            let mut record = vec![chunk.len() as u8];
+            record.extend_from_slice(&address.to_be_bytes());
+            record.push(0x00);
+            record.extend_from_slice(chunk);
+
+            write_hex_record(writer, &record)?;
+        }
+
+        write_hex_record(writer, &[0x00, 0x00, 0x00, 0x01])
+    }
+}
+
+fn write_hex_record(writer: &mut dyn Write, record: &[u8]) -> Result<(), Error> {
+    // The checksum is the two's complement of the sum of every other byte
+    let checksum = record.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)).wrapping_neg();
+
+    write!(writer, ":")?;
+    for b in record {
+        write!(writer, "{:02X}", b)?;
+    }
+    writeln!(writer, "{:02X}", checksum)
+}
+
+pub struct ReadMemWriter {
+    pub hex: bool
+}
+
+impl HackWriter for ReadMemWriter {
+    fn extension(&self) -> &'static str {
+        if self.hex { "memh" } else { "mem" }
+    }
+
+    fn write_words(&self, writer: &mut dyn Write, words: &[u16]) -> Result<(), Error> {
+        // One word per line, starting at address 0
+        writeln!(writer, "// Hack ROM image, {} words", words.len())?;
+        writeln!(writer, "@0")?;
+        for word in words {
+            if self.hex {
+                writeln!(writer, "{:04x}", word)?;
+            } else {
+                writeln!(writer, "{:016b}", word)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct LogisimWriter;
+
+impl HackWriter for LogisimWriter {
+    fn extension(&self) -> &'static str {
+        "img"
+    }
+
+    fn write_words(&self, writer: &mut dyn Write, words: &[u16]) -> Result<(), Error> {
+        /*
+         *  "v2.0 raw" header then hex words, 8 to a line.
+         *  Runs of the same word are written as <count>*<word> like Logisim does itself.
+         */
+        writeln!(writer, "v2.0 raw")?;
+
+        let mut entries = Vec::new();
+        let mut i = 0;
+        while i < words.len() {
+            let run = words[i..].iter().take_while(|w| **w == words[i]).count();
+            if run >= 4 {
+                entries.push(format!("{}*{:x}", run, words[i]));
+            } else {
+                entries.extend(words[i..i + run].iter().map(|w| format!("{:x}", w)));
+            }
+            i += run;
+        }
+
+        for line in entries.chunks(8) {
+            writeln!(writer, "{}", line.join(" "))?;
+        }
+        Ok(())
+    }
+}
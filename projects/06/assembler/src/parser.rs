@@ -1,7 +1,8 @@
 use core::fmt;
-use std::process;
 
-use crate::symbol_table::{find_address, SymbolTable};
+use crate::error::{AsmError, Location};
+use crate::expression::{evaluate, is_expression, ExprError};
+use crate::symbol_table::{add_variable, find_address, SymbolTable};
 
 pub enum LineType {
     CInstruction,
@@ -17,7 +18,15 @@ pub struct Instruction {
     pub jump: Option<String>, 
 
     // a-Instruction
-    pub value: Option<u32>
+    pub value: Option<u32>,
+
+    // The instruction as written and where it and each of its fields start,
+    // used for error reporting
+    pub text: String,
+    pub loc: Location,
+    pub dest_col: usize,
+    pub comp_col: usize,
+    pub jump_col: usize
 }
 
 impl fmt::Display for Instruction {
@@ -49,113 +58,197 @@ impl fmt::Display for Instruction {
     }
 }
 
-fn build_instruction(line_type: Option<LineType>,
-                     dest: Option<String>,
-                     comp: Option<String>,
-                     jump: Option<String>,
-                     value: Option<u32>) -> Instruction {
+fn empty_instruction(loc: Location) -> Instruction {
     Instruction {
-        line_type,
-        dest,
-        comp,
-        jump,
-        value,
+        line_type: None,
+        dest: None,
+        comp: None,
+        jump: None,
+        value: None,
+        text: String::new(),
+        dest_col: loc.column,
+        comp_col: loc.column,
+        jump_col: loc.column,
+        loc
     }
 }
 
-fn get_a_instruction(line: String, sym_table: &mut SymbolTable) -> Instruction {
+fn get_a_instruction(line: &str,
+                     loc: Location,
+                     sym_table: &mut SymbolTable) -> Result<Instruction, AsmError> {
 
     let value = line[1..].trim();
+    let value_loc = loc.at_column(loc.column + 1);
 
     let value = match value.parse() {
         Ok(val) => val,
+        Err(_) if is_expression(value) => evaluate_value(value, value_loc, sym_table)?,
+        Err(_) if value.starts_with(|c: char| c.is_ascii_digit()) => {
+            return Err(AsmError::InvalidConstant { loc: value_loc, text: value.to_string() });
+        },
+        Err(_) if !is_symbol(value) => {
+            return Err(AsmError::InvalidSymbol { loc: value_loc, text: value.to_string() });
+        },
         Err(_)    => {
             // Value is not a number. Look for it in the symbol table
-            match find_address(value.to_string(), sym_table) {
-                Some(address) => {
-                    address.parse().expect("Error occurred while parsing address form symbol table.")
-                },
+            match find_address(value, sym_table) {
+                Some(address) => address,
                 None => {
                     // If the value is not found in the symbol table. We have a variable.
-                    let var_address = sym_table.variable_count + 16;  // Variables start from RAM address 16.
-                    sym_table.symbols.insert(value.to_string(), var_address.to_string());
-                    sym_table.variable_count += 1;
-
-                    var_address
+                    add_variable(value.to_string(), sym_table)
                 }
             }
         }
     };
 
-    Instruction {
+    Ok(Instruction {
         line_type: Some(LineType::AInstruction),
-        dest: None,
-        comp: None,
-        jump: None,
-        value: Some(value)
+        value: Some(value),
+        ..empty_instruction(loc)
+    })
+}
+
+
+fn evaluate_value(value: &str, loc: Location, sym_table: &SymbolTable) -> Result<u32, AsmError> {
+    // Constant expressions can't be negative, the A-register only takes unsigned constants
+    match evaluate(value, sym_table) {
+        Ok(result) => u32::try_from(result)
+                          .map_err(|_| AsmError::InvalidConstant { loc, text: value.to_string() }),
+        Err(ExprError::Undefined(name)) => Err(AsmError::UndefinedSymbol { loc, text: name }),
+        Err(ExprError::Syntax(text))    => Err(AsmError::MalformedExpression { loc, text })
     }
 }
 
 
-fn get_c_instruction(line: String) -> Instruction {
-    if line.contains('=') && line.contains(';') {
-        let parts: Vec<&str> = line.split('=').flat_map(|x| x.split(';')).collect();
-        build_instruction(Some(LineType::CInstruction),
-                          Some(parts[0].to_string()),
-                          Some(parts[1].to_string()),
-                          Some(parts[2].to_string()),
-                          None)
-    } else if line.contains('=') {
-        let parts: Vec<&str> = line.split('=').collect();
-        build_instruction(Some(LineType::CInstruction),
-                          Some(parts[0].to_string()),
-                          Some(parts[1].to_string()),
-                          None, None)
-    } else if line.contains(';') {
-        let parts: Vec<&str> = line.split(';').collect();
-        build_instruction(Some(LineType::CInstruction),
-                          None,
-                          Some(parts[0].to_string()),
-                          Some(parts[1].to_string()),
-                          None)
-    } else {
-        eprintln!("Error while parsing c instruction {}", line);
-        process::exit(-1);
+fn get_c_instruction(line: &str, loc: Location) -> Result<Instruction, AsmError> {
+    /*
+     *  dest=comp;jump, where either dest or jump may be missing.
+     *  Whitespace inside the instruction is ignored, so 'D; JEQ' is the same as 'D;JEQ'.
+     *  The column of each field is kept so the translator can point at a bad mnemonic.
+     */
+    let eq   = line.find('=');
+    let semi = line.find(';');
+
+    let comp_start = eq.map_or(0, |i| i + 1);
+    let comp_end   = semi.unwrap_or(line.len());
+
+    if comp_start > comp_end || line[comp_start..].contains('=') || line[comp_end..].matches(';').count() > 1 {
+        return Err(AsmError::MalformedInstruction { loc, text: line.to_string() });
     }
+
+    let field = |start: usize, end: usize| -> (String, usize) {
+        let text = &line[start..end];
+        let offset = text.len() - text.trim_start().len();
+        (text.split_whitespace().collect(), loc.column + start + offset)
+    };
+
+    let mut ins = empty_instruction(loc.clone());
+    ins.line_type = Some(LineType::CInstruction);
+
+    if let Some(eq) = eq {
+        let (dest, col) = field(0, eq);
+        ins.dest = Some(dest);
+        ins.dest_col = col;
+    }
+
+    let (comp, col) = field(comp_start, comp_end);
+    ins.comp = Some(comp);
+    ins.comp_col = col;
+
+    if let Some(semi) = semi {
+        let (jump, col) = field(semi + 1, line.len());
+        ins.jump = Some(jump);
+        ins.jump_col = col;
+    }
+
+    Ok(ins)
 }
 
 
-pub fn parse_line(line: String, sym_table: &mut SymbolTable) -> Instruction {
+pub fn parse_line(line: &str,
+                  loc: Location,
+                  sym_table: &mut SymbolTable) -> Result<Instruction, AsmError> {
     // We need to Separate 'line' depending on the type of instruction it is.
-    if !is_instruction(&line) {
-        return Instruction {
-            line_type: None,
-            dest: None,
-            comp: None,
-            jump: None,
-            value: None
-        }
+    if !is_instruction(line) {
+        return Ok(empty_instruction(loc));
     } 
 
-    // Remove inline comments
-    let line = if let Some(index) = line.find("//") {
-        line[..index].trim()
+    // Remove inline comments, keeping track of where the instruction starts
+    let code = strip_comment(line);
+    let start = code.len() - code.trim_start().len();
+    let code = code.trim();
+    let loc = loc.at_column(start + 1);
+
+    let ins = if code.starts_with('@') {
+        get_a_instruction(code, loc, sym_table)
     } else {
-        line[..].trim()
+        get_c_instruction(code, loc)
+    };
+
+    ins.map(|ins| Instruction { text: code.to_string(), ..ins })
+}
+
+pub fn parse_equ(line: &str,
+                 loc: Location,
+                 sym_table: &SymbolTable) -> Result<(String, u32), AsmError> {
+    // .equ NAME value, where value is a number, a defined symbol or an expression
+    let code = strip_comment(line).trim();
+    let mut words = code.splitn(3, char::is_whitespace).skip(1);
+
+    let name  = words.next().unwrap_or_default();
+    let value = words.next().unwrap_or_default().trim();
+
+    if !is_symbol(name) || value.is_empty() {
+        return Err(AsmError::MalformedDirective { loc, text: code.to_string() });
+    }
+
+    let value_loc = loc.at_column(line.find(value).map_or(1, |i| i + 1));
+    let value = match value.parse() {
+        Ok(value) => value,
+        Err(_)    => evaluate_value(value, value_loc, sym_table)?
     };
 
-    if line.contains('@') {
-        return get_a_instruction(line.to_string(), sym_table);
+    Ok((name.to_string(), value))
+}
+
+pub fn parse_label(line: &str, loc: Location) -> Result<String, AsmError> {
+    // (LABEL) -> LABEL
+    let code = strip_comment(line);
+    let start = code.len() - code.trim_start().len();
+    let code = code.trim();
+    let loc = loc.at_column(start + 1);
+
+    match code.strip_prefix('(').and_then(|l| l.strip_suffix(')')) {
+        Some(label) if is_symbol(label.trim()) => Ok(label.trim().to_string()),
+        _ => Err(AsmError::MalformedLabel { loc, text: code.to_string() })
+    }
+}
+
+pub fn strip_comment(line: &str) -> &str {
+    match line.find("//") {
+        Some(index) => &line[..index],
+        None        => line
     }
-    
-    get_c_instruction(line.to_string())
+}
+
+fn is_symbol(value: &str) -> bool {
+    // Symbols are letters, digits, '_', '.', '$' and ':' that don't begin with a digit
+    !value.is_empty()
+        && !value.starts_with(|c: char| c.is_ascii_digit())
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || "_.$:".contains(c))
 }
 
 pub fn is_label(line: &str) -> bool {
-    line.starts_with('(') 
+    line.trim().starts_with('(') 
+}
+
+pub fn is_directive(line: &str) -> bool {
+    // .equ etc. Includes and macros have already been expanded by this point
+    line.trim().starts_with('.')
 }
 
 pub fn is_instruction(line: &str) -> bool {
-    // If the line isn't a label, a comment or whitespace, it must be a command
-    !line.trim().starts_with('(') && !line.trim().starts_with("//") && !line.is_empty()
-}
\ No newline at end of file
+    // If the line isn't a label, a directive, a comment or whitespace, it must be a command
+    let line = line.trim();
+    !line.starts_with('(') && !line.starts_with('.') && !line.starts_with("//") && !line.is_empty()
+}
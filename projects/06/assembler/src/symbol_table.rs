@@ -1,24 +1,73 @@
 use std::collections::HashMap;
 
+pub const SCREEN: u32 = 16384;
+pub const KBD: u32    = 24576;
+
+const PREDEFINED_SYMBOLS: [(&str, u32); 23] = [
+    ("SP", 0), ("LCL", 1), ("ARG", 2), ("THIS", 3), ("THAT", 4),
+    ("R0", 0),   ("R1", 1),   ("R2", 2),   ("R3", 3),
+    ("R4", 4),   ("R5", 5),   ("R6", 6),   ("R7", 7),
+    ("R8", 8),   ("R9", 9),   ("R10", 10), ("R11", 11),
+    ("R12", 12), ("R13", 13), ("R14", 14), ("R15", 15),
+    ("SCREEN", SCREEN), ("KBD", KBD)
+];
+
 pub struct SymbolTable {
-    pub symbols: HashMap<String, String>,
+    pub symbols: HashMap<String, u32>,
     pub variable_count: u32,
+
+    // Labels, variables and constants in the order they were added, for the symbol map
+    pub labels: Vec<String>,
+    pub variables: Vec<String>,
+    pub constants: Vec<String>,
+}
+
+
+pub fn find_address(symbol: &str, sym_table: &SymbolTable) -> Option<u32> {
+    sym_table.symbols.get(symbol).copied()
 }
 
 
-pub fn find_address(symbol: String, sym_table: &SymbolTable) -> Option<String> {
-    sym_table.symbols.get(&symbol).cloned()
+pub fn is_predefined(symbol: &str) -> bool {
+    PREDEFINED_SYMBOLS.iter().any(|(s, _)| *s == symbol)
 }
 
 
 pub fn add_symbol(symbol: String,
-                  address: String,
+                  address: u32,
                   sym_table: &mut SymbolTable) {
       
     // println!("Adding [{}, {}] to symbol table", symbol, address);
     sym_table.symbols.insert(symbol, address);
 }
 
+pub fn add_label(label: String,
+                 address: u32,
+                 sym_table: &mut SymbolTable) {
+    sym_table.labels.push(label.clone());
+    add_symbol(label, address, sym_table);
+}
+
+
+pub fn add_constant(name: String,
+                    value: u32,
+                    sym_table: &mut SymbolTable) {
+    // Defined with .equ
+    sym_table.constants.push(name.clone());
+    add_symbol(name, value, sym_table);
+}
+
+
+pub fn add_variable(symbol: String, sym_table: &mut SymbolTable) -> u32 {
+    // Variables are allocated consecutive RAM addresses starting from 16
+    let var_address = sym_table.variable_count + 16;
+    sym_table.variables.push(symbol.clone());
+    add_symbol(symbol, var_address, sym_table);
+    sym_table.variable_count += 1;
+
+    var_address
+}
+
 pub fn init_sym_table() -> SymbolTable {
     /*
      *  Initialise the symbol table with the keywords.
@@ -26,24 +75,15 @@ pub fn init_sym_table() -> SymbolTable {
 
     let mut sym_table = SymbolTable {
         symbols: HashMap::new(),
-        variable_count: 0
+        variable_count: 0,
+        labels: Vec::new(),
+        variables: Vec::new(),
+        constants: Vec::new()
     };
 
-    sym_table.symbols.insert(String::from("SP"),   String::from("0"));
-    sym_table.symbols.insert(String::from("LCL"),  String::from("1"));
-    sym_table.symbols.insert(String::from("ARG"),  String::from("2"));
-    sym_table.symbols.insert(String::from("THIS"), String::from("3"));
-    sym_table.symbols.insert(String::from("THAT"), String::from("4"));
-    
-    // R0 -> R15
-    for i in 0..16 {
-        let reg  = format!("R{}", i);
-        let addr = i.to_string();
-        sym_table.symbols.insert(reg, addr);
+    for (symbol, address) in PREDEFINED_SYMBOLS {
+        sym_table.symbols.insert(symbol.to_string(), address);
     }
 
-    sym_table.symbols.insert(String::from("SCREEN"), String::from("16384"));
-    sym_table.symbols.insert(String::from("KBD"),    String::from("24576"));
-
     sym_table
 }
@@ -1,77 +1,58 @@
+use hack_core::instruction::{lookup_code, COMP_CODES, DEST_CODES, JUMP_CODES};
+
+use crate::error::AsmError;
 use crate::parser::{Instruction, LineType};
 
-fn translate_a_instruction(ins: Instruction) -> String {
+fn translate_a_instruction(ins: &Instruction) -> Result<String, AsmError> {
+    // The top bit marks a C-instruction, so only 15 bits are left for the value
     match ins.value {
-        Some(uint) => format!("0{:015b}", uint),
-        None       => panic!("Error while translating A instruction")
+        Some(uint) if uint > 0x7FFF => Err(AsmError::ConstantOutOfRange {
+            loc: ins.loc.at_column(ins.loc.column + 1),
+            text: ins.text.trim_start_matches('@').trim().to_string(),
+            value: uint
+        }),
+        Some(uint) => Ok(format!("0{:015b}", uint)),
+        None       => Err(AsmError::MalformedInstruction { loc: ins.loc.clone(), text: String::from("@") })
     }
 }
 
 
-fn translate_c_instruction(ins: Instruction) -> String {
-    let dest_code = match ins.dest.as_deref() {
-        None        => "000",
-        Some("M")   => "001",
-        Some("D")   => "010",
-        Some("MD")  => "011",
-        Some("A")   => "100",
-        Some("AM")  => "101",
-        Some("AD")  => "110",
-        Some("AMD") => "111",
-        _ => panic!("Invalid dest mnemonic")
+fn translate_c_instruction(ins: &Instruction) -> Result<String, AsmError> {
+    // A missing dest or jump is looked up as "" which encodes to 000
+    let dest = ins.dest.as_deref().unwrap_or_default();
+    let dest_code = match lookup_code(&DEST_CODES, dest) {
+        Some(code) if ins.dest.is_none() || !dest.is_empty() => code,
+        _ => return Err(AsmError::InvalidDest {
+            loc: ins.loc.at_column(ins.dest_col),
+            text: dest.to_string()
+        })
     };
 
-    let comp_code = match ins.comp.as_deref() {
-        Some("0")   => "0101010", // a = 0
-        Some("1")   => "0111111",
-        Some("-1")  => "0111010",
-        Some("D")   => "0001100",
-        Some("A")   => "0110000",
-        Some("!D")  => "0001101",
-        Some("!A")  => "0110001",
-        Some("-D")  => "0001111",
-        Some("-A")  => "0110011",
-        Some("D+1") => "0011111",
-        Some("A+1") => "0110111",
-        Some("D-1") => "0001110",
-        Some("A-1") => "0110010",
-        Some("D+A") => "0000010",
-        Some("D-A") => "0010011",
-        Some("A-D") => "0000111",
-        Some("D&A") => "0000000",
-        Some("D|A") => "0010101",
-        Some("M")   => "1110000", // a = 1
-        Some("!M")  => "1110001",
-        Some("-M")  => "1110011",
-        Some("M+1") => "1110111",
-        Some("M-1") => "1110010",
-        Some("D+M") => "1000010",
-        Some("D-M") => "1010011",
-        Some("M-D") => "1000111",
-        Some("D&M") => "1000000",
-        Some("D|M") => "1010101",
-        _           => panic!("Invalid comp mnemonic")
+    let comp = ins.comp.as_deref().unwrap_or_default();
+    let comp_code = match lookup_code(&COMP_CODES, comp) {
+        Some(code) => code,
+        None => return Err(AsmError::InvalidComp {
+            loc: ins.loc.at_column(ins.comp_col),
+            text: comp.to_string()
+        })
     };
 
-    let jump_code = match ins.jump.as_deref() {
-        None        => "000",
-        Some("JGT") => "001",
-        Some("JEQ") => "010",
-        Some("JGE") => "011",
-        Some("JLT") => "100",
-        Some("JNE") => "101",
-        Some("JLE") => "110",
-        Some("JMP") => "111",
-        _ => panic!("Invalid jump mnemonic")
+    let jump = ins.jump.as_deref().unwrap_or_default();
+    let jump_code = match lookup_code(&JUMP_CODES, jump) {
+        Some(code) if ins.jump.is_none() || !jump.is_empty() => code,
+        _ => return Err(AsmError::InvalidJump {
+            loc: ins.loc.at_column(ins.jump_col),
+            text: jump.to_string()
+        })
     };
 
-    String::from("111") + comp_code + dest_code + jump_code
+    Ok(String::from("111") + comp_code + dest_code + jump_code)
 }
 
-pub fn translate_instruction(ins: Instruction) -> String {
+pub fn translate_instruction(ins: &Instruction) -> Result<String, AsmError> {
     match ins.line_type {
         Some(LineType::AInstruction) => translate_a_instruction(ins),
         Some(LineType::CInstruction) => translate_c_instruction(ins),
-        None => panic!("Instruction is neither type A or C.")
+        None => Err(AsmError::MalformedInstruction { loc: ins.loc.clone(), text: String::new() })
     }
-}
\ No newline at end of file
+}
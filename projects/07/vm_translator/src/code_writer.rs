@@ -12,8 +12,7 @@ use std::io::{BufWriter, Write};
 use std::fs::File;
 use std::path::Path;
 
-use crate::parser::{Command, CommandType};
-use crate::vm_translator::translation_error;
+use hack_core::vm::{Command, CommandType, VmError};
 
 
 pub struct CodeWriter {
@@ -23,36 +22,38 @@ pub struct CodeWriter {
 }
 
 impl CodeWriter {
-    pub fn new(output_file: &String) -> CodeWriter {
+    pub fn new(output_file: &String) -> Result<CodeWriter, VmError> {
         let file = File::create(output_file)
-                               .expect("Couldn't open output file");
+                        .map_err(|error| VmError::Io { file: output_file.clone(), error })?;
 
         let path = Path::new(output_file);
         let file_name = path.file_stem()
                             .and_then(|f| f.to_str())
                             .unwrap_or_default();
 
-        CodeWriter {
+        Ok(CodeWriter {
             writer: BufWriter::new(file),
             comp_count: 0,
             file_name: file_name.to_string()
-        }
-
+        })
     }
 
 
-    pub fn translate_command(&mut self, command: &Command) {
-        match command.get_command_type() {
+    pub fn translate_command(&mut self, command: &Command) -> Result<(), VmError> {
+        let translated = match command.get_command_type() {
             CommandType::Arithmetic => self.translate_arithmetic(command),
             CommandType::Push       => self.translate_push(command),
             CommandType::Pop        => self.translate_pop(command),
-            _                       => println!("Soz, not implemented yet...")
-        }
-    
+            _                       => {
+                println!("Soz, not implemented yet...");
+                Ok(())
+            }
+        };
+        translated.map_err(|message| command.error(&message))
     }
 
 
-    fn translate_arithmetic(&mut self, command: &Command) {
+    fn translate_arithmetic(&mut self, command: &Command) -> Result<(), String> {
         match command.get_arg1().as_str() {
             "add" => self.two_var_arithmetic("M=M+D"),
             "sub" => self.two_var_arithmetic("M=M-D"),
@@ -63,16 +64,17 @@ impl CodeWriter {
             "and" => self.two_var_arithmetic("M=D&M"),
             "or"  => self.two_var_arithmetic("M=D|M"),
             "not" => self.write_strings(&["@SP", "A=M-1", "M=!M"]),
-            _     => translation_error(&format!("Bad arithmetic command {}", command.get_arg1()))
+            _     => return Err(format!("Bad arithmetic command {}", command.get_arg1()))
         };
+        Ok(())
     }
 
 
-    fn translate_push(&mut self, command: &Command) {
+    fn translate_push(&mut self, command: &Command) -> Result<(), String> {
         let index = if let Some(i) = command.get_arg2() {
             i
         } else {
-            translation_error(&format!("No index was given: push {}", command.get_arg1()))
+            return Err(format!("No index was given: push {}", command.get_arg1()));
         };
 
         match command.get_arg1().as_str() {
@@ -81,21 +83,20 @@ impl CodeWriter {
             "this"     => self.generic_mem_push("@THIS", index),
             "that"     => self.generic_mem_push("@THAT", index),
             "static"   => self.push_static(index),
-            "constant" => self.push_constant(command),
+            "constant" => self.push_constant(command)?,
             "pointer"  => self.push_base_index(index, "@3"),
             "temp"     => self.push_base_index(index, "@5"),
-            _          => translation_error(&format!("Invalid memory location: {}", command.get_arg1()))
+            _          => return Err(format!("Invalid memory location: {}", command.get_arg1()))
         };
+        Ok(())
     }
 
 
-    fn translate_pop(&mut self, command: &Command) {
+    fn translate_pop(&mut self, command: &Command) -> Result<(), String> {
         let index = if let Some(i) = command.get_arg2() {
             i
         } else {
-            translation_error(
-                &format!("No index was given: pop {}", command.get_arg1())
-            )
+            return Err(format!("No index was given: pop {}", command.get_arg1()));
         };
 
         match command.get_arg1().as_str() {
@@ -104,11 +105,12 @@ impl CodeWriter {
             "this"     => self.generic_mem_pop("@THIS", index),
             "that"     => self.generic_mem_pop("@THAT", index),
             "static"   => self.pop_static(index),
-            "constant" => translation_error("Can't pop to 'constant' memory segment."),
+            "constant" => return Err("Can't pop to 'constant' memory segment.".to_string()),
             "pointer"  => self.pop_base_index(index, "@3"),
             "temp"     => self.pop_base_index(index, "@5"),
-            _          => translation_error(&format!("Invalid memory location: {}", command.get_arg1()))
+            _          => return Err(format!("Invalid memory location: {}", command.get_arg1()))
         };
+        Ok(())
     }
 
 
@@ -180,7 +182,7 @@ impl CodeWriter {
     }
 
 
-    fn push_constant(&mut self, command: &Command) {
+    fn push_constant(&mut self, command: &Command) -> Result<(), String> {
         /*
          *   @<constant>
          *   D=A
@@ -194,7 +196,7 @@ impl CodeWriter {
         let constant = if let Some(c) = command.get_arg2() {
             c   // Assume that c is an unsigned integer... within the correct range
         } else {
-            translation_error("Push/Pop command requires a second argument.\n'push constant <constant>")
+            return Err("Push/Pop command requires a second argument.\n'push constant <constant>".to_string());
         };
 
         // Store the constant in D
@@ -207,6 +209,7 @@ impl CodeWriter {
             "M=D"
         ]);
         self.modify_SP(true);
+        Ok(())
     }
 
 
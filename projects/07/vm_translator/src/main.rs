@@ -1,15 +1,22 @@
-use std::env;
+use std::{env, process};
 
 #[allow(non_snake_case)]
 mod vm_translator;
-mod parser;
 mod code_writer;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
     match args.len() {
-        2 => vm_translator::vm_translate(args[1].clone()),
+        2 => {
+            if let Err(errors) = vm_translator::vm_translate(args[1].clone()) {
+                for e in &errors {
+                    eprintln!("error: {}", e);
+                }
+                eprintln!("Failed to translate {}: {} error(s)", args[1], errors.len());
+                process::exit(-1);
+            }
+        }
         _ => println!("Usage: `cargo run -- <path-to-source-file>")
     }
 }
@@ -1,10 +1,10 @@
+use std::io;
 
-use std::process;
+use hack_core::vm::{Parser, VmError};
 
-use crate::parser;
 use crate::code_writer;
 
-pub fn vm_translate(input_file: String) {
+pub fn vm_translate(input_file: String) -> Result<(), Vec<VmError>> {
     /* ==================================================================
      *  input  -> fileName.vm
      *  output -> fileName.asm
@@ -14,36 +14,47 @@ pub fn vm_translate(input_file: String) {
      *  - Constructs codeWriter to handle the output file
      *  - Marches through the file, parsing each line and generating code.
      * 
-     *  Translator is extremely temperamental and will panic at when 
-     *  encountering any bug.
-     *  Probs better to handle these with Result<> but idk :shrug:
+     *  Every bad line is reported, not just the first one.
      * 
      * ================================================================== */
 
     let output_file = if let Some(index) = input_file.rfind(".vm") {
         input_file[..index].to_owned() + ".asm"
     } else {
-        panic!("Input file requires .vm extension")
+        return Err(vec![VmError::Io {
+            file: input_file,
+            error: io::Error::new(io::ErrorKind::InvalidInput, "input file requires .vm extension")
+        }]);
     };
 
     
-    let mut parser      = parser::Parser::new(input_file);
-    let mut code_writer = code_writer::CodeWriter::new(&output_file);
+    let mut parser      = Parser::new(&input_file).map_err(|e| vec![e])?;
+    let mut code_writer = code_writer::CodeWriter::new(&output_file).map_err(|e| vec![e])?;
+    let mut errors: Vec<VmError> = Vec::new();
     
     while parser.has_more_commands() {
-        parser.advance();  // Update parser.currentCommand
-        
-        if let Some(command) = parser.get_current_command() {
-            
-            code_writer.translate_command(command)
+        // Update parser.currentCommand, a bad line is noted and skipped
+        let translated = parser.advance().and_then(|()| match parser.get_current_command() {
+            Some(command) => code_writer.translate_command(command),
+            None => Ok(())
+        });
+        match translated {
+            Ok(()) => (),
+            Err(e @ VmError::Io { .. }) => {
+                errors.push(e);
+                break;
+            },
+            Err(VmError::Translation { line, text, message, .. }) => {
+                // Commands only know the name of their file, give the path like the parser does
+                errors.push(VmError::Translation { file: input_file.clone(), line, text, message });
+            },
+            Err(e) => errors.push(e)
         }
-        
     }
 
+    if !errors.is_empty() {
+        return Err(errors);
+    }
     println!("Successfully translated source VM file\nOutput -> {}", output_file);
+    Ok(())
 }
-
-pub fn translation_error(msg: &str) -> ! {
-    eprintln!("Error: {}", msg);
-    process::exit(-1);
-}
\ No newline at end of file
@@ -0,0 +1,505 @@
+use std::path::Path;
+
+use cpu_emulator::cpu::{memory_index, KBD, RAM_SIZE};
+use cpu_emulator::test_script::{split_index, Simulator};
+use cpu_emulator::KeyboardScript;
+
+use crate::error::EmulatorError;
+use crate::os::{self, Native, NativeResult, Os};
+use crate::program::{read_sources, Op, Operand, Program, ARG, LCL, SP, TEMP, THAT, THIS};
+
+// Where the stack starts, as set up by the translator's bootstrap code
+pub const STACK_START: u16 = 256;
+
+// How long a VM function called from a native one may run before it's given up on,
+// e.g. a Sys.halt from project 12 that loops forever
+const MAX_INVOKE_STEPS: u64 = 1_000_000;
+
+// A function being executed. The outermost frame, the function the program
+// started in, has nowhere to return to.
+#[derive(Clone, Copy, Debug)]
+pub struct Frame {
+    pub function: usize,
+    pub return_address: Option<usize>,
+}
+
+// The VM, running a program's commands against the same 32K RAM the Hack computer has
+pub struct VmEmulator {
+    pub pc: usize,
+    program: Program,
+    ram: Vec<u16>,
+    frames: Vec<Frame>,
+    function_steps: Vec<u64>,
+    steps: u64,
+    halted: bool,
+    natives: Vec<Option<&'static Native>>,   // Indexed by function id
+    pub os: Os,
+    keys: Option<KeyboardScript>,
+    step_limit: u64,
+    native: Option<usize>                    // The native function being run, for the profile
+}
+
+impl Default for VmEmulator {
+    fn default() -> VmEmulator {
+        VmEmulator::new(Program::default())
+    }
+}
+
+impl VmEmulator {
+    pub fn new(program: Program) -> VmEmulator {
+        let mut vm = VmEmulator {
+            pc: 0,
+            function_steps: vec![0; program.functions.len()],
+            natives: vec![None; program.functions.len()],
+            program,
+            ram: vec![0; RAM_SIZE],
+            frames: Vec::new(),
+            steps: 0,
+            halted: false,
+            os: Os::default(),
+            keys: None,
+            step_limit: 0,
+            native: None
+        };
+        vm.reset();
+        vm
+    }
+
+    pub fn load(path: &Path) -> Result<VmEmulator, EmulatorError> {
+        VmEmulator::with_os(&read_sources(path)?)
+    }
+
+    pub fn with_os(sources: &[(String, String)]) -> Result<VmEmulator, EmulatorError> {
+        /*
+         *  Loads a program with the OS classes it doesn't define itself made native.
+         *  If Sys is native the program gets a Sys.init that calls Main.main, see
+         *  os::sys_init. Which classes are native is decided before it's added.
+         */
+        let program = Program::from_sources(sources)?;
+        let natives: Vec<&'static Native> = os::CLASSES.iter()
+                                                       .filter(|(class, _)| !program.defines_class(class))
+                                                       .flat_map(|(_, functions)| functions.iter())
+                                                       .collect();
+
+        let mut program = match os::sys_init(&program) {
+            Some(sys) => Program::from_sources(&[sources, &[sys]].concat())?,
+            None => program
+        };
+        let ids: Vec<usize> = natives.iter().map(|native| program.function_id(native.name)).collect();
+
+        let mut vm = VmEmulator::new(program);
+        for (id, native) in ids.into_iter().zip(natives) {
+            vm.natives[id] = Some(native);
+        }
+        Ok(vm)
+    }
+
+    pub fn set_keys(&mut self, keys: KeyboardScript) {
+        // A keystroke timeline for KBD, by step instead of by cycle
+        self.keys = Some(keys);
+    }
+
+    pub fn reset(&mut self) {
+        // Back to the start of the entry function, RAM is left alone
+        self.frames.clear();
+        self.function_steps.fill(0);
+        self.steps = 0;
+        self.step_limit = 0;
+        self.os = Os::default();
+
+        match self.program.entry {
+            Some(function) => {
+                self.pc = self.program.functions[function].entry.unwrap_or_default();
+                self.frames.push(Frame { function, return_address: None });
+                self.halted = false;
+                self.skip_labels();
+            },
+            None => self.halted = true
+        }
+    }
+
+    pub fn bootstrap(&mut self) {
+        /*
+         *  Does what the translator's bootstrap code does: SP = 256, then
+         *  `call Sys.init 0`, which leaves a frame of 5 words on the stack.
+         *  Programs without a Sys.init just get the stack.
+         */
+        self.reset();
+        self.ram[SP as usize] = STACK_START;
+
+        if matches!(self.program.ops.get(self.pc), Some(Op::Function(_))) {
+            // Returning from Sys.init halts, so the return address is never used
+            self.push(0);
+            for register in [LCL, ARG, THIS, THAT] {
+                self.push(self.ram[register as usize]);
+            }
+            self.ram[ARG as usize] = STACK_START;
+            self.ram[LCL as usize] = self.ram[SP as usize];
+        }
+    }
+
+    pub fn step(&mut self) -> Result<(), EmulatorError> {
+        // Executes the command at pc
+        if self.is_halted() {
+            self.halted = true;
+            return Ok(());
+        }
+
+        let op = self.program.ops[self.pc];
+        let current = self.frames.last().map_or(0, |frame| frame.function);
+        self.function_steps[current] += 1;
+        self.steps += 1;
+        self.step_limit = self.step_limit.max(self.steps);
+        self.pc += 1;
+        self.update_keyboard();
+
+        match op {
+            Op::Push(operand) => {
+                let value = match operand {
+                    Operand::Constant(value) => value,
+                    _ => self.read_ram(self.address(operand))
+                };
+                self.push(value);
+            },
+            Op::Pop(operand) => {
+                let address = self.address(operand);
+                let value = self.pop();
+                self.write_ram(address, value);
+            },
+            Op::Add => self.binary(|x, y| x.wrapping_add(y)),
+            Op::Sub => self.binary(|x, y| x.wrapping_sub(y)),
+            Op::And => self.binary(|x, y| x & y),
+            Op::Or  => self.binary(|x, y| x | y),
+            Op::Eq  => self.binary(|x, y| truth(x == y)),
+            Op::Gt  => self.binary(|x, y| truth((x as i16) > (y as i16))),
+            Op::Lt  => self.binary(|x, y| truth((x as i16) < (y as i16))),
+            Op::Neg => {
+                let x = self.pop();
+                self.push(x.wrapping_neg());
+            },
+            Op::Not => {
+                let x = self.pop();
+                self.push(!x);
+            },
+            Op::Label => (),
+            Op::Goto(target) => self.pc = target,
+            Op::IfGoto(target) => {
+                if self.pop() != 0 {
+                    self.pc = target;
+                }
+            },
+            Op::Function(locals) => {
+                for _ in 0..locals {
+                    self.push(0);
+                }
+            },
+            Op::Call { function, args } => self.call(current, function, args)?,
+            Op::Return => self.ret()
+        }
+        self.skip_labels();
+        Ok(())
+    }
+
+    fn skip_labels(&mut self) {
+        // Labels only mark a place, like the Java VMEmulator they don't take a step
+        while let Some(Op::Label) = self.program.ops.get(self.pc) {
+            self.pc += 1;
+        }
+    }
+
+    pub fn run_until_halt(&mut self, max_steps: u64) -> Result<bool, EmulatorError> {
+        /*
+         *  Runs until the program returns from its entry function, runs off the
+         *  end of its commands or reaches a `goto` back to itself, or until
+         *  max_steps commands have been executed.
+         *  Returns whether the program halted.
+         */
+        self.step_limit = self.steps + max_steps;
+        while self.steps < self.step_limit {
+            if self.is_halted() {
+                return Ok(true);
+            }
+            self.step()?;
+        }
+        Ok(self.is_halted())
+    }
+
+    pub fn tick(&mut self) -> bool {
+        /*
+         *  A step spent waiting in a native function, on a key or Sys.wait.
+         *  Returns false once the step limit is reached, then the native function
+         *  gives up and its call is made again on the next step.
+         */
+        self.steps += 1;
+        if let Some(native) = self.native {
+            self.function_steps[native] += 1;
+        }
+        self.update_keyboard();
+        self.steps < self.step_limit
+    }
+
+    pub fn halt(&mut self) {
+        self.halted = true;
+    }
+
+    fn update_keyboard(&mut self) {
+        if let Some(keys) = &self.keys {
+            self.ram[KBD as usize] = keys.key_at(self.steps);
+        }
+    }
+
+    pub fn is_halted(&self) -> bool {
+        // The usual end loop is `label END`, `goto END`
+        if self.halted || self.pc >= self.program.ops.len() {
+            return true;
+        }
+        match self.program.ops[self.pc] {
+            Op::Goto(target) if target <= self.pc => {
+                self.program.ops[target..self.pc].iter().all(|op| matches!(op, Op::Label))
+            },
+            _ => false
+        }
+    }
+
+    fn call(&mut self, caller: usize, function: usize, args: u16) -> Result<(), EmulatorError> {
+        if let Some(native) = self.natives[function] {
+            return self.call_native(native, function, args);
+        }
+
+        let entry = self.program.functions[function].entry.ok_or_else(|| EmulatorError::UndefinedFunction {
+            caller: self.program.functions[caller].name.clone(),
+            name: self.program.functions[function].name.clone()
+        })?;
+
+        // The return address saved on the stack is the index of the next command
+        self.push(self.pc as u16);
+        for register in [LCL, ARG, THIS, THAT] {
+            self.push(self.ram[register as usize]);
+        }
+        let sp = self.ram[SP as usize];
+        self.ram[ARG as usize] = sp.wrapping_sub(args.wrapping_add(5));
+        self.ram[LCL as usize] = sp;
+
+        self.frames.push(Frame { function, return_address: Some(self.pc) });
+        self.pc = entry;
+        Ok(())
+    }
+
+    fn call_native(&mut self, native: &Native, function: usize, args: u16) -> Result<(), EmulatorError> {
+        // The arguments stay on the stack until the function has finished
+        if args != native.args {
+            return Err(EmulatorError::OsCall {
+                function: native.name.to_string(),
+                text: format!("takes {} arguments but is called with {}", native.args, args)
+            });
+        }
+        let sp = self.ram[SP as usize];
+        let values: Vec<u16> = (0..args).map(|i| self.read_ram(sp.wrapping_sub(args - i))).collect();
+
+        match self.run_native(native, function, &values)? {
+            Some(value) => {
+                self.ram[SP as usize] = sp.wrapping_sub(args);
+                self.push(value);
+            },
+            None => self.pc -= 1
+        }
+        Ok(())
+    }
+
+    fn run_native(&mut self, native: &Native, function: usize, args: &[u16]) -> NativeResult {
+        // A native call counts as one step of the function in the profile, plus any it spends waiting
+        self.function_steps[function] += 1;
+        let outer = self.native.replace(function);
+        let result = (native.run)(self, args);
+        self.native = outer;
+        result
+    }
+
+    pub fn invoke(&mut self, name: &str, args: &[u16]) -> NativeResult {
+        /*
+         *  Calls a function from a native one, so the OS classes call each other
+         *  through whichever version of a class the program uses. A VM function
+         *  is run until it returns. None if the program halted before it did.
+         */
+        let caller = self.native.unwrap_or_default();
+        let function = self.program.find_function(name).ok_or_else(|| EmulatorError::UndefinedFunction {
+            caller: self.program.functions[caller].name.clone(),
+            name: name.to_string()
+        })?;
+        if let Some(native) = self.natives[function] {
+            return self.run_native(native, function, args);
+        }
+
+        let depth = self.frames.len();
+        for &arg in args {
+            self.push(arg);
+        }
+        self.call(caller, function, args.len() as u16)?;
+
+        let limit = self.steps + MAX_INVOKE_STEPS;
+        while self.frames.len() > depth {
+            if self.is_halted() {
+                return Ok(None);
+            }
+            if self.steps >= limit {
+                return Err(EmulatorError::OsCall {
+                    function: name.to_string(),
+                    text: format!("called from native code and still running after {} steps", MAX_INVOKE_STEPS)
+                });
+            }
+            self.step()?;
+        }
+        Ok(Some(self.pop()))
+    }
+
+    fn ret(&mut self) {
+        // The caller's segments come back from the saved frame, even for the entry function
+        let frame = self.ram[LCL as usize];
+        let value = self.pop();
+        let arg = self.ram[ARG as usize];
+        self.write_ram(arg, value);
+        self.ram[SP as usize] = arg.wrapping_add(1);
+
+        for (offset, register) in [THAT, THIS, ARG, LCL].into_iter().enumerate() {
+            self.ram[register as usize] = self.read_ram(frame.wrapping_sub(offset as u16 + 1));
+        }
+
+        match self.frames.pop().and_then(|frame| frame.return_address) {
+            Some(address) => self.pc = address,
+            None => self.halted = true
+        }
+    }
+
+    fn address(&self, operand: Operand) -> u16 {
+        match operand {
+            Operand::Based(register, index) => self.ram[register as usize].wrapping_add(index),
+            Operand::Address(address) | Operand::Constant(address) => address
+        }
+    }
+
+    fn binary(&mut self, f: impl Fn(u16, u16) -> u16) {
+        let y = self.pop();
+        let x = self.pop();
+        self.push(f(x, y));
+    }
+
+    pub fn push(&mut self, value: u16) {
+        let sp = self.ram[SP as usize];
+        self.write_ram(sp, value);
+        self.ram[SP as usize] = sp.wrapping_add(1);
+    }
+
+    pub fn pop(&mut self) -> u16 {
+        let sp = self.ram[SP as usize].wrapping_sub(1);
+        self.ram[SP as usize] = sp;
+        self.read_ram(sp)
+    }
+
+    pub fn read_ram(&self, address: u16) -> u16 {
+        self.ram[address as usize % RAM_SIZE]
+    }
+
+    pub fn write_ram(&mut self, address: u16, value: u16) {
+        self.ram[address as usize % RAM_SIZE] = value;
+    }
+
+    pub fn ram(&self) -> &[u16] {
+        &self.ram
+    }
+
+    pub fn ram_mut(&mut self) -> &mut [u16] {
+        &mut self.ram
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    pub fn call_stack(&self) -> &[Frame] {
+        // Outermost first, the function being executed last
+        &self.frames
+    }
+
+    pub fn function_name(&self, function: usize) -> &str {
+        &self.program.functions[function].name
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn step_counts(&self) -> Vec<(&str, u64)> {
+        // Commands executed in each function, busiest first
+        let mut counts: Vec<(&str, u64)> = self.program.functions.iter()
+                                                                 .zip(&self.function_steps)
+                                                                 .filter(|(_, &steps)| steps > 0)
+                                                                 .map(|(f, &steps)| (f.name.as_str(), steps))
+                                                                 .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        counts
+    }
+}
+
+fn truth(b: bool) -> u16 {
+    // true is -1, all bits set
+    if b { 0xFFFF } else { 0 }
+}
+
+impl Simulator for VmEmulator {
+    /*
+     *  The VM emulator as seen from a test script: `load` takes a .vm file or a
+     *  directory, each `vmstep` executes one command, and the variables are
+     *  sp, local, argument, this, that, RAM[n], temp[n], pointer[n] and
+     *  local[n], argument[n], this[n], that[n] for the segments.
+     */
+    fn load(&mut self, file: &Path) -> Result<(), String> {
+        // Bootstrapped, so scripts for OS programs (project 12) can just step. Those for
+        // projects 07 and 08 set the registers themselves.
+        *self = VmEmulator::load(file).map_err(|e| e.to_string())?;
+        self.bootstrap();
+        Ok(())
+    }
+
+    fn get(&mut self, var: &str) -> Result<u16, String> {
+        Ok(self.ram[self.variable(var)?])
+    }
+
+    fn set(&mut self, var: &str, value: u16) -> Result<(), String> {
+        let address = self.variable(var)?;
+        self.ram[address] = value;
+        Ok(())
+    }
+
+    fn command(&mut self, words: &[String], _dir: &Path) -> Result<(), String> {
+        match words[0].as_str() {
+            "vmstep" => self.step().map_err(|e| e.to_string()),
+            _ => Err(format!("unknown command '{}'", words.join(" ")))
+        }
+    }
+}
+
+impl VmEmulator {
+    fn variable(&self, var: &str) -> Result<usize, String> {
+        // The RAM address a test script variable stands for
+        let segment = |register: u16, index: &str| -> Result<usize, String> {
+            let index = memory_index(var, index, RAM_SIZE)? as u16;
+            Ok(self.ram[register as usize].wrapping_add(index) as usize % RAM_SIZE)
+        };
+
+        match split_index(var) {
+            ("sp", None)       => Ok(SP as usize),
+            ("local", None)    => Ok(LCL as usize),
+            ("argument", None) => Ok(ARG as usize),
+            ("this", None)     => Ok(THIS as usize),
+            ("that", None)     => Ok(THAT as usize),
+            ("RAM", Some(index))      => memory_index(var, index, RAM_SIZE),
+            ("temp", Some(index))     => Ok(TEMP as usize + memory_index(var, index, 8)?),
+            ("pointer", Some(index))  => Ok(THIS as usize + memory_index(var, index, 2)?),
+            ("local", Some(index))    => segment(LCL, index),
+            ("argument", Some(index)) => segment(ARG, index),
+            ("this", Some(index))     => segment(THIS, index),
+            ("that", Some(index))     => segment(THAT, index),
+            _ => Err(format!("unknown variable '{}'", var))
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use std::fmt;
+use std::io;
+
+use assembler::Location;
+
+#[derive(Debug)]
+pub enum EmulatorError {
+    Io { file: String, error: io::Error },
+    Syntax { loc: Location, text: String },
+    UnknownLabel { loc: Location, label: String },
+    DuplicateFunction { loc: Location, name: String },
+    UndefinedFunction { caller: String, name: String },
+    OsCall { function: String, text: String },
+}
+
+impl fmt::Display for EmulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EmulatorError::Io { file, error } => write!(f, "{}: {}", file, error),
+            EmulatorError::Syntax { loc, text } => write!(f, "{}: {}", loc, text),
+            EmulatorError::UnknownLabel { loc, label } => {
+                write!(f, "{}: label '{}' isn't defined in this function", loc, label)
+            }
+            EmulatorError::DuplicateFunction { loc, name } => {
+                write!(f, "{}: function '{}' is already defined", loc, name)
+            }
+            EmulatorError::UndefinedFunction { caller, name } => {
+                write!(f, "{} calls '{}', which isn't defined anywhere", caller, name)
+            }
+            EmulatorError::OsCall { function, text } => write!(f, "{} {}", function, text),
+        }
+    }
+}
+
+impl std::error::Error for EmulatorError {}
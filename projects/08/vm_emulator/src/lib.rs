@@ -0,0 +1,11 @@
+// Emulator for the VM language of projects 07 and 08, so .vm files (the Jack
+// compiler's output in particular) can be run without translating and assembling them.
+
+pub mod emulator;
+pub mod error;
+pub mod os;
+pub mod program;
+
+pub use crate::emulator::{Frame, VmEmulator};
+pub use crate::error::EmulatorError;
+pub use crate::program::Program;
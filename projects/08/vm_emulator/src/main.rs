@@ -0,0 +1,131 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::{env, process};
+
+use cpu_emulator::{screen, KeyboardScript, Script};
+use vm_emulator::VmEmulator;
+
+const USAGE: &str = "Usage: vm_emulator [--profile] [--keys <script>] [--screen <file.png|file.pbm>] <path-to-vm-file-or-dir> [max-steps]\n       vm_emulator --test <path-to-tst-file>";
+
+// Plenty for the course's test programs, Jack programs may need more
+const DEFAULT_MAX_STEPS: u64 = 10_000_000;
+
+fn main() {
+    let mut profile = false;
+    let mut keys: Option<String> = None;
+    let mut screen_file: Option<String> = None;
+    let mut positional: Vec<String> = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--profile" => profile = true,                // Print the commands executed per function
+            "--keys"    => keys = args.next(),            // Keystroke timeline for KBD, by step
+            "--screen"  => screen_file = args.next(),     // Save the screen when the program stops
+            "--test"    => match args.next() {            // Run a VME .tst script instead of a program
+                Some(script) => run_test(&script),
+                None => usage_error("Expected a test script")
+            },
+            flag if flag.starts_with("--") => usage_error(&format!("Unknown option {}", flag)),
+            _ => positional.push(arg)
+        }
+    }
+
+    let (program, max_steps) = match positional.len() {
+        1 => (&positional[0], DEFAULT_MAX_STEPS),
+        2 => match positional[1].parse() {
+            Ok(steps) => (&positional[0], steps),
+            Err(_) => usage_error(&format!("Invalid step count {}", positional[1]))
+        },
+        _ => usage_error("Expected a .vm file or a directory of them")
+    };
+
+    let mut vm = VmEmulator::load(Path::new(program)).unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        process::exit(-1);
+    });
+    if let Some(keys) = keys {
+        vm.set_keys(KeyboardScript::from_file(&keys).unwrap_or_else(|e| usage_error(&e)));
+    }
+    vm.bootstrap();
+
+    let result = vm.run_until_halt(max_steps);
+    match &result {
+        Ok(halted) => println!("{} after {} steps", if *halted { "Halted" } else { "Stopped" }, vm.steps()),
+        Err(e) => eprintln!("error: {}", e)
+    }
+
+    if let Some(code) = vm.os.error {
+        println!("Sys.error({})", code);
+    }
+
+    let registers = &vm.ram()[..5];
+    println!("SP = {}, LCL = {}, ARG = {}, THIS = {}, THAT = {}", registers[0], registers[1], registers[2], registers[3], registers[4]);
+    if !vm.is_halted() {
+        let names: Vec<&str> = vm.call_stack().iter().map(|frame| vm.function_name(frame.function)).collect();
+        println!("Call stack: {}", names.join(" > "));
+    }
+
+    // What was printed with the native Output, since there's no screen to look at
+    if !vm.os.text.is_empty() {
+        println!("Output:\n{}", vm.os.text.trim_end());
+    }
+
+    if let Some(screen_file) = screen_file {
+        if let Err(e) = save_screen(&screen_file, &vm) {
+            eprintln!("Couldn't save the screen to {}: {}", screen_file, e);
+            process::exit(-1);
+        }
+        println!("Screen -> {}", screen_file);
+    }
+
+    if profile {
+        for (function, steps) in vm.step_counts() {
+            println!("{:>12}  {}", steps, function);
+        }
+    }
+
+    if result.is_err() {
+        process::exit(-1);
+    }
+}
+
+fn run_test(script_file: &str) -> ! {
+    let script = Script::from_file(script_file).unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        process::exit(-1);
+    });
+
+    match script.run(&mut VmEmulator::default()) {
+        Ok(report) => {
+            if let Some(output_file) = report.output_file {
+                println!("{} lines -> {}", report.lines, output_file.display());
+            }
+            if report.compare_file.is_some() {
+                println!("Comparison ended successfully");
+            }
+            process::exit(0);
+        },
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(-1);
+        }
+    }
+}
+
+fn save_screen(screen_file: &str, vm: &VmEmulator) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(screen_file)?);
+
+    if screen_file.ends_with(".pbm") {
+        screen::write_pbm(&mut writer, vm.ram())?;
+    } else {
+        screen::write_png(&mut writer, vm.ram())?;
+    }
+    writer.flush()
+}
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("{}\n{}", msg, USAGE);
+    process::exit(-1);
+}
@@ -0,0 +1,20 @@
+// Array: blocks from Memory.alloc, whichever Memory is in use
+use super::{error, Native, NativeResult};
+use crate::emulator::VmEmulator;
+
+pub const FUNCTIONS: &[Native] = &[
+    Native { name: "Array.new",     args: 1, run: new },
+    Native { name: "Array.dispose", args: 1, run: dispose },
+];
+
+fn new(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    // Error 2 is a size that isn't positive
+    if args[0] as i16 <= 0 {
+        return error(vm, 2);
+    }
+    vm.invoke("Memory.alloc", &[args[0]])
+}
+
+fn dispose(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    vm.invoke("Memory.deAlloc", &[args[0]])
+}
@@ -0,0 +1,106 @@
+// Keyboard: reads RAM[24576], which the emulator fills in from a keystroke script
+use cpu_emulator::cpu::KBD;
+
+use super::string::{parse_int, BACKSPACE, NEWLINE};
+use super::{Native, NativeResult};
+use crate::emulator::VmEmulator;
+use crate::error::EmulatorError;
+
+pub const FUNCTIONS: &[Native] = &[
+    Native { name: "Keyboard.init",       args: 0, run: |_, _| Ok(Some(0)) },
+    Native { name: "Keyboard.keyPressed", args: 0, run: key_pressed },
+    Native { name: "Keyboard.readChar",   args: 0, run: read_char },
+    Native { name: "Keyboard.readLine",   args: 1, run: read_line },
+    Native { name: "Keyboard.readInt",    args: 1, run: read_int },
+];
+
+// A read in progress. Reading waits on the keyboard, so it can run past the step
+// limit and be carried on by the next run.
+#[derive(Default)]
+pub struct Input {
+    pressed: Option<u16>,       // The key held down, reported once it's released
+    line: Option<Vec<u16>>      // What readLine/readInt have read so far
+}
+
+fn key_pressed(vm: &mut VmEmulator, _: &[u16]) -> NativeResult {
+    Ok(Some(vm.read_ram(KBD)))
+}
+
+fn read_char(vm: &mut VmEmulator, _: &[u16]) -> NativeResult {
+    let Some(c) = read_key(vm) else {
+        return Ok(None);
+    };
+    vm.invoke("Output.printChar", &[c])?;
+    Ok(Some(c))
+}
+
+fn read_line(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    // The line as a new String
+    let Some(line) = read_chars(vm, args[0])? else {
+        return Ok(None);
+    };
+    let Some(s) = vm.invoke("String.new", &[line.len() as u16])? else {
+        return Ok(None);
+    };
+    for c in line {
+        vm.invoke("String.appendChar", &[s, c])?;
+    }
+    Ok(Some(s))
+}
+
+fn read_int(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    // Like String.intValue, a line that doesn't start with a number is 0
+    Ok(read_chars(vm, args[0])?.map(|line| parse_int(&line)))
+}
+
+fn read_chars(vm: &mut VmEmulator, message: u16) -> Result<Option<Vec<u16>>, EmulatorError> {
+    /*
+     *  Prints the message, then echoes keys until newline is pressed. Backspace
+     *  takes back the last character. The message is only printed the first time
+     *  round, when a read that ran into the step limit is carried on it isn't.
+     */
+    if vm.os.input.line.is_none() {
+        vm.invoke("Output.printString", &[message])?;
+        vm.os.input.line = Some(Vec::new());
+    }
+
+    loop {
+        let Some(c) = read_key(vm) else {
+            return Ok(None);
+        };
+        let line = vm.os.input.line.as_mut().expect("a line is being read");
+        match c {
+            NEWLINE => {
+                vm.invoke("Output.println", &[])?;
+                return Ok(vm.os.input.line.take());
+            },
+            BACKSPACE => {
+                if line.pop().is_some() {
+                    vm.invoke("Output.backSpace", &[])?;
+                }
+            },
+            c => {
+                line.push(c);
+                vm.invoke("Output.printChar", &[c])?;
+            }
+        }
+    }
+}
+
+fn read_key(vm: &mut VmEmulator) -> Option<u16> {
+    // Waits for a key to be pressed and released. None if the step limit comes first.
+    loop {
+        match (vm.os.input.pressed, vm.read_ram(KBD)) {
+            (None, 0) => (),
+            (None, key) => vm.os.input.pressed = Some(key),
+            (Some(key), 0) => {
+                vm.os.input.pressed = None;
+                return Some(key);
+            },
+            (Some(_), _) => ()
+        }
+        if !vm.tick() {
+            return None;
+        }
+    }
+}
@@ -0,0 +1,62 @@
+// Math: the functions the compiler calls for * and /, and a few more
+use super::{error, Native, NativeResult};
+use crate::emulator::VmEmulator;
+
+pub const FUNCTIONS: &[Native] = &[
+    Native { name: "Math.init",     args: 0, run: init },
+    Native { name: "Math.abs",      args: 1, run: abs },
+    Native { name: "Math.multiply", args: 2, run: multiply },
+    Native { name: "Math.divide",   args: 2, run: divide },
+    Native { name: "Math.min",      args: 2, run: min },
+    Native { name: "Math.max",      args: 2, run: max },
+    Native { name: "Math.sqrt",     args: 1, run: sqrt },
+];
+
+fn init(_: &mut VmEmulator, _: &[u16]) -> NativeResult {
+    Ok(Some(0))
+}
+
+fn abs(_: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    Ok(Some((args[0] as i16).wrapping_abs() as u16))
+}
+
+fn multiply(_: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    // 16-bit two's complement, overflow wraps like it does in the Hack ALU
+    Ok(Some((args[0] as i16).wrapping_mul(args[1] as i16) as u16))
+}
+
+fn divide(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    // Rounds towards zero. Error 3 is dividing by zero.
+    if args[1] == 0 {
+        return error(vm, 3);
+    }
+    Ok(Some((args[0] as i16).wrapping_div(args[1] as i16) as u16))
+}
+
+fn min(_: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    Ok(Some((args[0] as i16).min(args[1] as i16) as u16))
+}
+
+fn max(_: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    Ok(Some((args[0] as i16).max(args[1] as i16) as u16))
+}
+
+fn sqrt(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    // Error 4 is a negative x
+    let x = args[0] as i16;
+    if x < 0 {
+        return error(vm, 4);
+    }
+    Ok(Some(isqrt(x as u32) as u16))
+}
+
+pub fn isqrt(x: u32) -> u32 {
+    let mut root = (x as f64).sqrt() as u32;
+    while root * root > x {
+        root -= 1;
+    }
+    while (root + 1) * (root + 1) <= x {
+        root += 1;
+    }
+    root
+}
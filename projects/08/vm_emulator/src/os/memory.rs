@@ -0,0 +1,100 @@
+// Memory: direct access to RAM, and a heap in RAM[2048..16383]
+use std::collections::HashMap;
+
+use super::{error, Native, NativeResult};
+use crate::emulator::VmEmulator;
+
+pub const HEAP_START: u16 = 2048;
+pub const HEAP_END: u16   = 16384;
+
+pub const FUNCTIONS: &[Native] = &[
+    Native { name: "Memory.init",    args: 0, run: init },
+    Native { name: "Memory.peek",    args: 1, run: peek },
+    Native { name: "Memory.poke",    args: 2, run: poke },
+    Native { name: "Memory.alloc",   args: 1, run: alloc },
+    Native { name: "Memory.deAlloc", args: 1, run: de_alloc },
+];
+
+// First-fit allocator. The free blocks are kept in address order, and the size of
+// each allocated block on the side, so the whole heap can be handed out.
+pub struct Heap {
+    free: Vec<(u16, u16)>,
+    allocated: HashMap<u16, u16>
+}
+
+impl Default for Heap {
+    fn default() -> Heap {
+        Heap {
+            free: vec![(HEAP_START, HEAP_END - HEAP_START)],
+            allocated: HashMap::new()
+        }
+    }
+}
+
+impl Heap {
+    pub fn alloc(&mut self, size: u16) -> Option<u16> {
+        let i = self.free.iter().position(|&(_, len)| len >= size)?;
+        let (start, len) = self.free[i];
+        if len == size {
+            self.free.remove(i);
+        } else {
+            self.free[i] = (start + size, len - size);
+        }
+        self.allocated.insert(start, size);
+        Some(start)
+    }
+
+    pub fn free(&mut self, block: u16) {
+        // The block is merged with free neighbours. Anything that wasn't allocated is ignored.
+        let Some(size) = self.allocated.remove(&block) else {
+            return;
+        };
+
+        let i = self.free.partition_point(|&(start, _)| start < block);
+        self.free.insert(i, (block, size));
+        if i + 1 < self.free.len() && block + size == self.free[i + 1].0 {
+            self.free[i].1 += self.free[i + 1].1;
+            self.free.remove(i + 1);
+        }
+        if i > 0 && self.free[i - 1].0 + self.free[i - 1].1 == block {
+            self.free[i - 1].1 += self.free[i].1;
+            self.free.remove(i);
+        }
+    }
+
+    pub fn in_use(&self) -> usize {
+        // Words allocated and not freed yet
+        self.allocated.values().map(|&size| size as usize).sum()
+    }
+}
+
+fn init(vm: &mut VmEmulator, _: &[u16]) -> NativeResult {
+    vm.os.heap = Heap::default();
+    Ok(Some(0))
+}
+
+fn peek(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    Ok(Some(vm.read_ram(args[0])))
+}
+
+fn poke(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    vm.write_ram(args[0], args[1]);
+    Ok(Some(0))
+}
+
+fn alloc(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    // Error 5 is a size that isn't positive, 6 is running out of heap
+    let size = args[0] as i16;
+    if size <= 0 {
+        return error(vm, 5);
+    }
+    match vm.os.heap.alloc(size as u16) {
+        Some(block) => Ok(Some(block)),
+        None => error(vm, 6)
+    }
+}
+
+fn de_alloc(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    vm.os.heap.free(args[0]);
+    Ok(Some(0))
+}
@@ -0,0 +1,96 @@
+// Native versions of the Jack OS classes, like the Java VMEmulator's built-ins.
+// A class is only native if the program doesn't define any of its functions, so a
+// class from project 12 can be tested on its own with the rest of the OS native.
+
+mod array;
+mod keyboard;
+mod math;
+mod memory;
+mod output;
+mod screen;
+mod string;
+mod sys;
+
+use crate::emulator::VmEmulator;
+use crate::error::EmulatorError;
+use crate::program::Program;
+
+pub use self::keyboard::Input;
+pub use self::memory::{Heap, HEAP_END, HEAP_START};
+pub use self::output::Cursor;
+
+// What a native function returns. None means it didn't finish: it's waiting on the
+// keyboard or Sys.wait and the run's step limit was reached (the call is made again
+// on the next step), or the program halted.
+pub type NativeResult = Result<Option<u16>, EmulatorError>;
+
+pub struct Native {
+    pub name: &'static str,
+    pub args: u16,
+    pub run: fn(&mut VmEmulator, &[u16]) -> NativeResult,
+}
+
+pub const CLASSES: &[(&str, &[Native])] = &[
+    ("Array",    array::FUNCTIONS),
+    ("Keyboard", keyboard::FUNCTIONS),
+    ("Math",     math::FUNCTIONS),
+    ("Memory",   memory::FUNCTIONS),
+    ("Output",   output::FUNCTIONS),
+    ("Screen",   screen::FUNCTIONS),
+    ("String",   string::FUNCTIONS),
+    ("Sys",      sys::FUNCTIONS),
+];
+
+// The state the native classes keep between calls
+pub struct Os {
+    pub heap: Heap,
+    pub cursor: Cursor,
+    pub black: bool,
+    pub input: Input,
+    pub wait_until: Option<u64>,
+    pub error: Option<u16>,
+    pub text: String,     // Everything printed through Output, for headless runs
+}
+
+impl Default for Os {
+    fn default() -> Os {
+        Os {
+            heap: Heap::default(),
+            cursor: Cursor::default(),
+            black: true,
+            input: Input::default(),
+            wait_until: None,
+            error: None,
+            text: String::new()
+        }
+    }
+}
+
+pub fn sys_init(program: &Program) -> Option<(String, String)> {
+    /*
+     *  With a native Sys, Sys.init is VM code that does what the stock one does:
+     *  initialises the other classes, calls Main.main and halts. Classes from the
+     *  program only get their init called if they have one.
+     *  Programs without a Main.main (the project 07 and 08 tests) don't get one.
+     */
+    let defined = |name: &str| program.find_function(name).is_some_and(|id| program.functions[id].entry.is_some());
+    if program.defines_class("Sys") || !defined("Main.main") {
+        return None;
+    }
+
+    let mut source = String::from("function Sys.init 0\n");
+    for class in ["Memory", "Math", "Screen", "Output", "Keyboard"] {
+        let init = format!("{}.init", class);
+        if !program.defines_class(class) || defined(&init) {
+            source += &format!("call {} 0\npop temp 0\n", init);
+        }
+    }
+    source += "call Main.main 0\npop temp 0\ncall Sys.halt 0\npop temp 0\n";
+
+    Some(("Sys.vm".to_string(), source))
+}
+
+fn error(vm: &mut VmEmulator, code: u16) -> NativeResult {
+    // Sys.error, native or not. It normally halts, if it doesn't the function returns 0
+    Ok(vm.invoke("Sys.error", &[code])?.map(|_| 0))
+}
@@ -0,0 +1,126 @@
+// Screen: drawing in black or white on the 512x256 screen at RAM[16384..24575]
+use cpu_emulator::cpu::{KBD, SCREEN};
+
+use super::math::isqrt;
+use super::{error, Native, NativeResult};
+use crate::emulator::VmEmulator;
+
+const WIDTH: i32  = 512;
+const HEIGHT: i32 = 256;
+
+pub const FUNCTIONS: &[Native] = &[
+    Native { name: "Screen.init",          args: 0, run: init },
+    Native { name: "Screen.clearScreen",   args: 0, run: clear_screen },
+    Native { name: "Screen.setColor",      args: 1, run: set_color },
+    Native { name: "Screen.drawPixel",     args: 2, run: draw_pixel },
+    Native { name: "Screen.drawLine",      args: 4, run: draw_line },
+    Native { name: "Screen.drawRectangle", args: 4, run: draw_rectangle },
+    Native { name: "Screen.drawCircle",    args: 3, run: draw_circle },
+];
+
+fn init(vm: &mut VmEmulator, _: &[u16]) -> NativeResult {
+    vm.os.black = true;
+    Ok(Some(0))
+}
+
+fn clear_screen(vm: &mut VmEmulator, _: &[u16]) -> NativeResult {
+    vm.ram_mut()[SCREEN as usize..KBD as usize].fill(0);
+    Ok(Some(0))
+}
+
+fn set_color(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    // Any non-zero value is true, i.e. black
+    vm.os.black = args[0] != 0;
+    Ok(Some(0))
+}
+
+fn draw_pixel(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    // Error 7 is a pixel off the screen
+    let (x, y) = (args[0] as i16 as i32, args[1] as i16 as i32);
+    if !on_screen(x, y) {
+        return error(vm, 7);
+    }
+    set_pixel(vm, x, y);
+    Ok(Some(0))
+}
+
+fn draw_line(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    // Bresenham's algorithm. Error 8 is an end off the screen.
+    let [x1, y1, x2, y2] = [args[0], args[1], args[2], args[3]].map(|a| a as i16 as i32);
+    if !on_screen(x1, y1) || !on_screen(x2, y2) {
+        return error(vm, 8);
+    }
+
+    let (dx, dy) = ((x2 - x1).abs(), -(y2 - y1).abs());
+    let (sx, sy) = ((x2 - x1).signum(), (y2 - y1).signum());
+    let (mut x, mut y, mut diff) = (x1, y1, dx + dy);
+    loop {
+        set_pixel(vm, x, y);
+        if x == x2 && y == y2 {
+            break;
+        }
+        let twice = 2 * diff;
+        if twice >= dy {
+            diff += dy;
+            x += sx;
+        }
+        if twice <= dx {
+            diff += dx;
+            y += sy;
+        }
+    }
+    Ok(Some(0))
+}
+
+fn draw_rectangle(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    // Filled, corners included. Error 9 is a corner off the screen or the wrong way round.
+    let [x1, y1, x2, y2] = [args[0], args[1], args[2], args[3]].map(|a| a as i16 as i32);
+    if !on_screen(x1, y1) || !on_screen(x2, y2) || x1 > x2 || y1 > y2 {
+        return error(vm, 9);
+    }
+    for y in y1..=y2 {
+        fill_row(vm, x1, x2, y);
+    }
+    Ok(Some(0))
+}
+
+fn draw_circle(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    /*
+     *  Filled, one row at a time. Error 12 is a centre off the screen and 13 a
+     *  radius that's negative or over 181. Like the Java OS, the parts of the
+     *  circle that are off the screen are left out.
+     */
+    let [x, y, r] = [args[0], args[1], args[2]].map(|a| a as i16 as i32);
+    if !on_screen(x, y) {
+        return error(vm, 12);
+    }
+    if !(0..=181).contains(&r) {
+        return error(vm, 13);
+    }
+
+    for dy in -r..=r {
+        if !(0..HEIGHT).contains(&(y + dy)) {
+            continue;
+        }
+        let half = isqrt((r * r - dy * dy) as u32) as i32;
+        fill_row(vm, (x - half).max(0), (x + half).min(WIDTH - 1), y + dy);
+    }
+    Ok(Some(0))
+}
+
+fn on_screen(x: i32, y: i32) -> bool {
+    (0..WIDTH).contains(&x) && (0..HEIGHT).contains(&y)
+}
+
+fn fill_row(vm: &mut VmEmulator, x1: i32, x2: i32, y: i32) {
+    for x in x1..=x2 {
+        set_pixel(vm, x, y);
+    }
+}
+
+fn set_pixel(vm: &mut VmEmulator, x: i32, y: i32) {
+    let address = SCREEN + (y * 32 + x / 16) as u16;
+    let bit = 1 << (x % 16);
+    let word = vm.read_ram(address);
+    vm.write_ram(address, if vm.os.black { word | bit } else { word & !bit });
+}
@@ -0,0 +1,120 @@
+// String: objects of [maxLength, length, characters...] on the heap
+use super::{error, Native, NativeResult};
+use crate::emulator::VmEmulator;
+
+pub const NEWLINE: u16      = 128;
+pub const BACKSPACE: u16    = 129;
+pub const DOUBLE_QUOTE: u16 = 34;
+
+pub const FUNCTIONS: &[Native] = &[
+    Native { name: "String.new",           args: 1, run: new },
+    Native { name: "String.dispose",       args: 1, run: dispose },
+    Native { name: "String.length",        args: 1, run: length },
+    Native { name: "String.charAt",        args: 2, run: char_at },
+    Native { name: "String.setCharAt",     args: 3, run: set_char_at },
+    Native { name: "String.appendChar",    args: 2, run: append_char },
+    Native { name: "String.eraseLastChar", args: 1, run: erase_last_char },
+    Native { name: "String.intValue",      args: 1, run: int_value },
+    Native { name: "String.setInt",        args: 2, run: set_int },
+    Native { name: "String.backSpace",     args: 0, run: |_, _| Ok(Some(BACKSPACE)) },
+    Native { name: "String.doubleQuote",   args: 0, run: |_, _| Ok(Some(DOUBLE_QUOTE)) },
+    Native { name: "String.newLine",       args: 0, run: |_, _| Ok(Some(NEWLINE)) },
+];
+
+/*
+ *  The error codes are the stock OS's:
+ *  14 negative maxLength, 15 charAt index, 16 setCharAt index,
+ *  17 appending to a full string, 18 erasing from an empty one,
+ *  19 setInt with too little room
+ */
+
+fn new(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    let max_length = args[0] as i16;
+    if max_length < 0 {
+        return error(vm, 14);
+    }
+    let Some(this) = vm.invoke("Memory.alloc", &[(max_length as u16).wrapping_add(2)])? else {
+        return Ok(None);
+    };
+    vm.write_ram(this, max_length as u16);
+    vm.write_ram(this.wrapping_add(1), 0);
+    Ok(Some(this))
+}
+
+fn dispose(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    vm.invoke("Memory.deAlloc", &[args[0]])
+}
+
+fn length(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    Ok(Some(vm.read_ram(args[0].wrapping_add(1))))
+}
+
+fn char_at(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    let (this, j) = (args[0], args[1]);
+    if j >= vm.read_ram(this.wrapping_add(1)) {
+        return error(vm, 15);
+    }
+    Ok(Some(vm.read_ram(this.wrapping_add(2).wrapping_add(j))))
+}
+
+fn set_char_at(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    let (this, j, c) = (args[0], args[1], args[2]);
+    if j >= vm.read_ram(this.wrapping_add(1)) {
+        return error(vm, 16);
+    }
+    vm.write_ram(this.wrapping_add(2).wrapping_add(j), c);
+    Ok(Some(0))
+}
+
+fn append_char(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    let (this, c) = (args[0], args[1]);
+    let length = vm.read_ram(this.wrapping_add(1));
+    if length >= vm.read_ram(this) {
+        return error(vm, 17);
+    }
+    vm.write_ram(this.wrapping_add(2).wrapping_add(length), c);
+    vm.write_ram(this.wrapping_add(1), length.wrapping_add(1));
+    Ok(Some(this))
+}
+
+fn erase_last_char(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    let this = args[0];
+    let length = vm.read_ram(this.wrapping_add(1));
+    if length == 0 {
+        return error(vm, 18);
+    }
+    vm.write_ram(this.wrapping_add(1), length.wrapping_sub(1));
+    Ok(Some(0))
+}
+
+fn int_value(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    let this = args[0];
+    let chars: Vec<u16> = (0..vm.read_ram(this.wrapping_add(1))).map(|j| vm.read_ram(this.wrapping_add(2).wrapping_add(j))).collect();
+    Ok(Some(parse_int(&chars)))
+}
+
+fn set_int(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    let (this, value) = (args[0], args[1]);
+    let digits = (value as i16).to_string();
+    if digits.len() > vm.read_ram(this) as usize {
+        return error(vm, 19);
+    }
+    for (j, c) in digits.bytes().enumerate() {
+        vm.write_ram(this.wrapping_add(2).wrapping_add(j as u16), c as u16);
+    }
+    vm.write_ram(this.wrapping_add(1), digits.len() as u16);
+    Ok(Some(0))
+}
+
+pub fn parse_int(chars: &[u16]) -> u16 {
+    // An optional '-' then digits, up to the first character that isn't one
+    let (negative, digits) = match chars.first() {
+        Some(&c) if c == '-' as u16 => (true, &chars[1..]),
+        _ => (false, chars)
+    };
+
+    let value = digits.iter()
+                      .take_while(|&&c| (b'0' as u16..=b'9' as u16).contains(&c))
+                      .fold(0i16, |value, &c| value.wrapping_mul(10).wrapping_add((c - b'0' as u16) as i16));
+    if negative { value.wrapping_neg() as u16 } else { value as u16 }
+}
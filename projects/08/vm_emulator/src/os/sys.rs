@@ -0,0 +1,44 @@
+// Sys: halting, errors and waiting. Sys.init is VM code, see sys_init in mod.rs.
+use super::{error, Native, NativeResult};
+use crate::emulator::VmEmulator;
+
+// How many steps a millisecond of Sys.wait takes
+pub const STEPS_PER_MS: u64 = 1000;
+
+pub const FUNCTIONS: &[Native] = &[
+    Native { name: "Sys.halt",  args: 0, run: halt },
+    Native { name: "Sys.error", args: 1, run: sys_error },
+    Native { name: "Sys.wait",  args: 1, run: wait },
+];
+
+fn halt(vm: &mut VmEmulator, _: &[u16]) -> NativeResult {
+    vm.halt();
+    Ok(Some(0))
+}
+
+fn sys_error(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    // Prints ERR<code> and halts, the code is kept for the caller to report
+    for c in "ERR".bytes() {
+        vm.invoke("Output.printChar", &[c as u16])?;
+    }
+    vm.invoke("Output.printInt", &[args[0]])?;
+    vm.os.error = Some(args[0]);
+    halt(vm, &[])
+}
+
+fn wait(vm: &mut VmEmulator, args: &[u16]) -> NativeResult {
+    // Error 1 is a negative duration
+    let duration = args[0] as i16;
+    if duration < 0 {
+        return error(vm, 1);
+    }
+
+    let until = *vm.os.wait_until.get_or_insert(vm.steps() + duration as u64 * STEPS_PER_MS);
+    while vm.steps() < until {
+        if !vm.tick() {
+            return Ok(None);
+        }
+    }
+    vm.os.wait_until = None;
+    Ok(Some(0))
+}
@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use assembler::Location;
+use hack_core::vm::{parse_command, Command, CommandType};
+
+use crate::error::EmulatorError;
+
+// The registers at the bottom of RAM
+pub const SP: u16   = 0;
+pub const LCL: u16  = 1;
+pub const ARG: u16  = 2;
+pub const THIS: u16 = 3;
+pub const THAT: u16 = 4;
+pub const TEMP: u16 = 5;
+
+// Static variables of every file share RAM[16..255]
+pub const STATIC_START: u16 = 16;
+pub const STATIC_END: u16   = 256;
+
+// What a push reads or a pop writes
+#[derive(Clone, Copy, Debug)]
+pub enum Operand {
+    Constant(u16),
+    Address(u16),         // static, pointer and temp, whose address is known when loading
+    Based(u16, u16),      // local, argument, this and that: RAM[register] + index
+}
+
+// A command with its labels and function names resolved, ready to execute
+#[derive(Clone, Copy, Debug)]
+pub enum Op {
+    Push(Operand),
+    Pop(Operand),
+    Add, Sub, Neg,
+    Eq,  Gt,  Lt,
+    And, Or,  Not,
+    Label,
+    Goto(usize),
+    IfGoto(usize),
+    Function(u16),
+    Call { function: usize, args: u16 },
+    Return,
+}
+
+// A function that is called somewhere, with the index of its `function` command
+// if it's defined. Code before the first `function` in a file belongs to a
+// function named after the file.
+pub struct Function {
+    pub name: String,
+    pub entry: Option<usize>,
+}
+
+// Every .vm file of a program, loaded into one list of commands
+#[derive(Default)]
+pub struct Program {
+    pub commands: Vec<Command>,
+    pub locations: Vec<Location>,
+    pub ops: Vec<Op>,
+    pub functions: Vec<Function>,
+    pub entry: Option<usize>,
+    function_ids: HashMap<String, usize>,
+}
+
+// A parsed line, waiting for its labels and calls to be resolved
+struct Line {
+    command: Command,
+    loc: Location,
+    function: usize,
+    static_base: u16,
+}
+
+impl Program {
+    pub fn load(path: &Path) -> Result<Program, EmulatorError> {
+        Program::from_sources(&read_sources(path)?)
+    }
+
+    pub fn from_sources(sources: &[(String, String)]) -> Result<Program, EmulatorError> {
+        /*
+         *  Loads (file name, source) pairs in order.
+         *  The first pass parses every line, defines the functions and finds the
+         *  labels of each one, the second pass turns the commands into ops.
+         *  The program starts at Sys.init if there is one, otherwise at the first command.
+         */
+        let mut program = Program::default();
+        let mut lines: Vec<Line> = Vec::new();
+        let mut labels: HashMap<(usize, String), usize> = HashMap::new();
+        let mut static_base = STATIC_START;
+
+        for (file, source) in sources {
+            let path = Path::new(file);
+            let file_name = path.file_name().and_then(|f| f.to_str()).unwrap_or(file);
+            let class = path.file_stem().and_then(|f| f.to_str()).unwrap_or(file);
+
+            let mut function = None;
+            let mut statics = 0;
+
+            for (number, text) in source.lines().enumerate() {
+                let text = text.split("//").next().unwrap_or_default();
+                let command = text.trim();
+                if command.is_empty() {
+                    continue;
+                }
+
+                let loc = Location {
+                    file: file.clone(),
+                    line: number + 1,
+                    column: text.len() - text.trim_start().len() + 1
+                };
+                let command = parse_command(command, file_name)
+                    .map_err(|text| EmulatorError::Syntax { loc: loc.clone(), text })?;
+
+                let index = lines.len();
+                let current = match (command.get_command_type(), function) {
+                    (CommandType::Function, _) => {
+                        let id = program.function_id(command.get_arg1());
+                        if program.functions[id].entry.is_some() {
+                            return Err(EmulatorError::DuplicateFunction { loc, name: command.get_arg1().clone() });
+                        }
+                        program.functions[id].entry = Some(index);
+                        id
+                    },
+                    (_, Some(id)) => id,
+                    (_, None) => {
+                        let id = program.function_id(class);
+                        program.functions[id].entry = Some(index);
+                        id
+                    }
+                };
+                function = Some(current);
+
+                match command.get_command_type() {
+                    CommandType::Label => {
+                        labels.insert((current, command.get_arg1().clone()), index);
+                    },
+                    CommandType::Push | CommandType::Pop if command.get_arg1() == "static" => {
+                        let index = parse_index(&command, &loc)?;
+                        if static_base as usize + index as usize >= STATIC_END as usize {
+                            return Err(EmulatorError::Syntax {
+                                loc,
+                                text: "static variables don't fit in RAM[16..255]".to_string()
+                            });
+                        }
+                        statics = statics.max(index + 1);
+                    },
+                    _ => ()
+                }
+
+                lines.push(Line { command, loc, function: current, static_base });
+            }
+
+            static_base += statics;
+        }
+
+        for line in lines {
+            let op = program.decode(&line, &labels)?;
+            program.ops.push(op);
+            program.commands.push(line.command);
+            program.locations.push(line.loc);
+        }
+
+        program.entry = program.function_ids.get("Sys.init")
+                                             .filter(|&&id| program.functions[id].entry.is_some())
+                                             .copied()
+                                             .or_else(|| program.functions.iter().position(|f| f.entry == Some(0)));
+        Ok(program)
+    }
+
+    pub fn function_id(&mut self, name: &str) -> usize {
+        // Functions that are called before (or without) being defined get an id too
+        if let Some(&id) = self.function_ids.get(name) {
+            return id;
+        }
+        self.functions.push(Function { name: name.to_string(), entry: None });
+        self.function_ids.insert(name.to_string(), self.functions.len() - 1);
+        self.functions.len() - 1
+    }
+
+    pub fn find_function(&self, name: &str) -> Option<usize> {
+        self.function_ids.get(name).copied()
+    }
+
+    pub fn defines_class(&self, class: &str) -> bool {
+        // Whether any function of the class has a `function` command in the program
+        self.functions.iter().any(|f| {
+            f.entry.is_some() && f.name.split_once('.').is_some_and(|(name, _)| name == class)
+        })
+    }
+
+    fn decode(&mut self, line: &Line, labels: &HashMap<(usize, String), usize>) -> Result<Op, EmulatorError> {
+        let command = &line.command;
+        let syntax = |text: String| EmulatorError::Syntax { loc: line.loc.clone(), text };
+        let label = |name: &String| {
+            labels.get(&(line.function, name.clone())).copied().ok_or_else(|| EmulatorError::UnknownLabel {
+                loc: line.loc.clone(),
+                label: name.clone()
+            })
+        };
+
+        let op = match command.get_command_type() {
+            CommandType::Arithmetic => match command.get_arg1().as_str() {
+                "add" => Op::Add,
+                "sub" => Op::Sub,
+                "neg" => Op::Neg,
+                "eq"  => Op::Eq,
+                "gt"  => Op::Gt,
+                "lt"  => Op::Lt,
+                "and" => Op::And,
+                "or"  => Op::Or,
+                _     => Op::Not
+            },
+            CommandType::Push => Op::Push(operand(line)?),
+            CommandType::Pop => match operand(line)? {
+                Operand::Constant(_) => return Err(syntax("can't pop to the constant segment".to_string())),
+                operand => Op::Pop(operand)
+            },
+            CommandType::Label    => Op::Label,
+            CommandType::Goto     => Op::Goto(label(command.get_arg1())?),
+            CommandType::If       => Op::IfGoto(label(command.get_arg1())?),
+            CommandType::Function => Op::Function(parse_index(command, &line.loc)?),
+            CommandType::Call     => match parse_index(command, &line.loc)? {
+                // Like a constant, the count has to fit in a Hack word
+                args if args > 32767 => {
+                    return Err(syntax(format!("call with {} arguments is out of range, a call takes up to 32767", args)))
+                },
+                args => Op::Call { function: self.function_id(command.get_arg1()), args }
+            },
+            CommandType::Return   => Op::Return
+        };
+        Ok(op)
+    }
+}
+
+pub fn read_sources(path: &Path) -> Result<Vec<(String, String)>, EmulatorError> {
+    // A single .vm file, or every .vm file in a directory, as (file name, source) pairs
+    let io_error = |error| EmulatorError::Io { file: path.display().to_string(), error };
+
+    let files = hack_core::handle_input(path, "vm").map_err(io_error)?;
+    if files.is_empty() {
+        return Err(io_error(io::Error::new(io::ErrorKind::InvalidInput, "no .vm files to load")));
+    }
+
+    let mut sources = Vec::new();
+    for file in files {
+        let source = fs::read_to_string(&file)
+            .map_err(|error| EmulatorError::Io { file: file.display().to_string(), error })?;
+        sources.push((file.display().to_string(), source));
+    }
+    Ok(sources)
+}
+
+fn operand(line: &Line) -> Result<Operand, EmulatorError> {
+    let index = parse_index(&line.command, &line.loc)?;
+    let out_of_range = |max: u16| EmulatorError::Syntax {
+        loc: line.loc.clone(),
+        text: format!("{} {} is out of range, the index goes up to {}", line.command.get_arg1(), index, max)
+    };
+
+    let operand = match line.command.get_arg1().as_str() {
+        "constant" if index > 32767 => return Err(out_of_range(32767)),
+        "constant" => Operand::Constant(index),
+        "local"    => Operand::Based(LCL, index),
+        "argument" => Operand::Based(ARG, index),
+        "this"     => Operand::Based(THIS, index),
+        "that"     => Operand::Based(THAT, index),
+        "pointer" if index > 1 => return Err(out_of_range(1)),
+        "pointer"  => Operand::Address(THIS + index),
+        "temp" if index > 7 => return Err(out_of_range(7)),
+        "temp"     => Operand::Address(TEMP + index),
+        _          => Operand::Address(line.static_base + index)
+    };
+    Ok(operand)
+}
+
+fn parse_index(command: &Command, loc: &Location) -> Result<u16, EmulatorError> {
+    // The index of a push/pop, or the local/argument count of a function/call
+    let arg = command.get_arg2().map(String::as_str).unwrap_or_default();
+    arg.parse().map_err(|_| EmulatorError::Syntax { loc: loc.clone(), text: format!("'{}' isn't a valid number", arg) })
+}
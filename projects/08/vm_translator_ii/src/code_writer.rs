@@ -4,41 +4,52 @@
 
 #![allow(non_snake_case)]
 
-use std::io::{BufWriter, Write};
+use std::io::{self, BufWriter, Write};
 use std::fs::File;
 use std::path::Path;
 
-use crate::parser::{Command, CommandType};
-use crate::vm_translator::translation_error;
+use hack_core::vm::{Command, CommandType, VmError};
+
+use crate::peephole::{self, Fused};
+use crate::vm_translator::TranslateOptions;
 
 
 pub struct CodeWriter {
     writer: BufWriter<File>,
+    output_file: String,
+    write_error: Option<io::Error>,   // The first write that failed, finish() reports it
     store_true_count: u32,       // No. of store true labels.
     return_count: u32,           // No. of return address labels.
-    func_count: u32,             // No. of function init loop labels.
-    file_name: String            // Name of the file without the extension
+    file_name: String,           // Name of the file without the extension
+    current_function: String,    // Labels are only visible inside the function they're in
+    pending: Vec<Command>,       // Held back while they could be the start of a peephole pattern
+    shared_routines: bool,       // Calls, returns and comparisons jump to one copy of their code
+    routines: Vec<&'static str>  // The shared routines used so far, finish() writes them out
 }
 
 
 impl CodeWriter {
-    pub fn new(output_file: &String) -> CodeWriter {
+    pub fn new(output_file: &String, options: &TranslateOptions) -> Result<CodeWriter, VmError> {
         let file = File::create(output_file)
-                               .expect("Couldn't open output file");
+                        .map_err(|error| VmError::Io { file: output_file.clone(), error })?;
 
         let path = Path::new(output_file);
         let file_name = path.file_stem()
                             .and_then(|f| f.to_str())
                             .unwrap_or_default();
 
-        CodeWriter {
+        Ok(CodeWriter {
             writer: BufWriter::new(file),
+            output_file: output_file.clone(),
+            write_error: None,
             store_true_count: 0,
             return_count: 0,
-            func_count: 0,
-            file_name: file_name.to_string()
-        }
-
+            file_name: file_name.to_string(),
+            current_function: String::new(),
+            pending: Vec::new(),
+            shared_routines: options.shared_routines,
+            routines: Vec::new()
+        })
     }
 
 
@@ -99,7 +110,96 @@ impl CodeWriter {
     }
 
 
-    pub fn translate_command(&mut self, command: &Command) {
+    pub fn translate_command(&mut self, command: &Command) -> Result<(), VmError> {
+        // Commands go through the peephole window, call finish() after the last one
+        check(command).map_err(|message| command.error(&message))?;
+
+        self.pending.push(command.clone());
+        self.flush_pending(false);
+        Ok(())
+    }
+
+
+    pub fn finish(&mut self) -> Result<(), VmError> {
+        // Translates whatever is still held back, then the shared routines, and flushes the output file
+        self.flush_pending(true);
+
+        let routines = self.routines.clone();
+        for routine in routines {
+            self.write_routine(routine);
+        }
+
+        let flushed = self.writer.flush();
+        match self.write_error.take().map_or(flushed, Err) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(VmError::Io { file: self.output_file.clone(), error })
+        }
+    }
+
+
+    fn flush_pending(&mut self, all: bool) {
+        /*
+         *  Translates the pending commands, a whole pattern at a time where one
+         *  matches. Stops when what's left could still grow into a pattern,
+         *  unless this is the end of the input.
+         */
+        while !self.pending.is_empty() {
+            let window: Vec<Command> = self.pending.iter().take(peephole::WINDOW).cloned().collect();
+
+            if let Some((fused, length)) = peephole::fuse(&window) {
+                self.translate_fused(fused);
+                self.pending.drain(..length);
+            } else if !all && peephole::could_fuse(&self.pending) {
+                break;
+            } else {
+                let command = self.pending.remove(0);
+                self.translate_single(&command);
+            }
+        }
+    }
+
+
+    fn translate_fused(&mut self, fused: Fused) {
+        match fused {
+            Fused::Move { from, to } => {
+                // push X, pop Y -> D = X, Y = D without touching the stack
+                self.store_D(to, |writer| writer.load_D(from));
+            },
+            Fused::ConstantArithmetic { constant, op } => {
+                // push constant N, add -> x + N in place
+                if constant == "1" && (op == "add" || op == "sub") {
+                    let comp = if op == "add" { "M=M+1" } else { "M=M-1" };
+                    self.write_strings(&["@SP", "A=M-1", comp]);
+                } else {
+                    self.write_strings(&[&format!("@{}", constant), "D=A", "@SP", "A=M-1", arithmetic_comp(op)]);
+                }
+            },
+            Fused::Constant(value) => self.push_value(value),
+            Fused::CompareJump { jump, label } => {
+                // eq, if-goto -> jump on x - y straight away, nothing is pushed
+                let label = self.function_label(label);
+                self.write_strings(&[
+                    "@SP",
+                    "AM=M-1",
+                    "D=M",      // D = y
+                    "@SP",
+                    "AM=M-1",
+                    "D=M-D",    // D = x - y
+                    &format!("@{}", label),
+                    &format!("D;{}", jump)
+                ]);
+            },
+            Fused::NotJump { label } => {
+                // not is bitwise, so !x is non-zero, and the jump taken, unless x is -1
+                let label = self.function_label(label);
+                self.pop_D();
+                self.write_strings(&["D=D+1", &format!("@{}", label), "D;JNE"]);
+            }
+        }
+    }
+
+
+    fn translate_single(&mut self, command: &Command) {
         match command.get_command_type() {
             CommandType::Arithmetic => self.translate_arithmetic(command),
             CommandType::Push       => self.translate_push(command),
@@ -117,26 +217,26 @@ impl CodeWriter {
 
     fn translate_arithmetic(&mut self, command: &Command) {
         match command.get_arg1().as_str() {
-            "add" => self.two_var_arithmetic("M=M+D"),
-            "sub" => self.two_var_arithmetic("M=M-D"),
+            "add" | "sub" | "and" | "or" => self.two_var_arithmetic(arithmetic_comp(command.get_arg1())),
             "neg" => self.write_strings(&["@SP", "A=M-1", "M=-M"]),
             "eq"  => self.compare_arithmetic("D;JEQ"),
-            "gt"  => self.compare_arithmetic("D;JLT"),
-            "lt"  => self.compare_arithmetic("D;JGT"),
-            "and" => self.two_var_arithmetic("M=D&M"),
-            "or"  => self.two_var_arithmetic("M=D|M"),
+            "gt"  => self.compare_arithmetic("D;JGT"),
+            "lt"  => self.compare_arithmetic("D;JLT"),
             "not" => self.write_strings(&["@SP", "A=M-1", "M=!M"]),
-            _     => translation_error(&format!("Bad arithmetic command {}", command.get_arg1()))
+            _     => unreachable!("the parser only makes known arithmetic commands")
         };
     }
 
 
     fn translate_call(&mut self, command: &Command) {
         // call <function_name> nArgs
+        if self.shared_routines {
+            return self.call_routine(command);
+        }
 
         // 1. push return address
         // <vm_file>.ret<ret_count>
-        let ret_addr = format!("{}.ret{}", self.file_name, self.return_count.to_string());
+        let ret_addr = format!("{}.ret{}", self.file_name, self.return_count);
         
         self.write_strings(&[
             &format!("@{}", ret_addr),
@@ -158,30 +258,22 @@ impl CodeWriter {
         self.write_strings(&[ "@THAT", "D=M"]);
         self.push_reg("D");
 
-        // 3. Reposition ARG for the callee
-        //      -> ARG = SP - 5 - nArgs 
-        let nArg_str = if let Some(nArg) = command.get_arg2() {
-            nArg
-        } else {
-            translation_error("Invalid call command. Usage 'call <function_name> <nArgs>'.");
-        };
-
+        // 3. Set LCL = SP ready for the function to initialise its local variables
         self.write_strings(&[
             "@SP",
             "D=M",
-            "@5",
-            "D=D-A",    // D = SP - 5
-            &format!("@{}", nArg_str),
-            "D=D-A",    // D = (SP-5) - nArgs
-            "@ARG",
+            "@LCL",
             "M=D"
         ]);
 
-        // 4. Set LCL = SP ready for the function to initialise its local variables
+        // 4. Reposition ARG for the callee, D is still SP
+        //      -> ARG = SP - (5 + nArgs)
+        let nArgs = count(command);
+
         self.write_strings(&[
-            "@SP",
-            "D=M",
-            "@LCL",
+            &format!("@{}", 5 + nArgs as u32),
+            "D=D-A",    // D = SP - 5 - nArgs
+            "@ARG",
             "M=D"
         ]);
 
@@ -199,12 +291,22 @@ impl CodeWriter {
 
 
     fn push_reg(&mut self, reg: &str) {
+        // reg can also be 0, 1 or -1
         self.write_strings(&[
             "@SP",
-            "A=M",
+            "AM=M+1",   // SP++
+            "A=A-1",
             &format!("M={}", reg)
         ]);
-        self.modify_SP(true);
+    }
+
+
+    fn pop_D(&mut self) {
+        self.write_strings(&[
+            "@SP",
+            "AM=M-1",   // SP--
+            "D=M"
+        ]);
     }
 
 
@@ -214,57 +316,38 @@ impl CodeWriter {
         // 1. Make the function label
         //      -> (<function_name>)
         self.write_string(&format!("({})", command.get_arg1()));
+        self.current_function = command.get_arg1().clone();
 
 
-        // 2. Initialise the local vars to zero
-        //  (One optimisation would be skip this if nVars == 0)
+        // 2. Initialise the local vars to zero, unrolled since it's two instructions a var
         //      -> for nVars: push 0
+        let nVars = count(command);
+        if nVars == 0 {
+            return;
+        }
 
-        let loop_start_label = format!("func_init.loop_start.{}", self.func_count);
-        let loop_end_label   = format!("func_init.loop_end.{}", self.func_count);
-
-        let nVars = if let Some(s) = command.get_arg2() {
-            s
-        } else {
-            translation_error("Invalid function command. Usage: function <function_name> <nVars>")
-        };
-
+        self.write_strings(&["@SP", "A=M"]);
+        for _ in 0..nVars {
+            self.write_strings(&["M=0", "A=A+1"]);
+        }
         self.write_strings(&[
-            &format!("@{}", nVars), // Init nVars variable and the iterator i
             "D=A",
-            "@nVars",
-            "M=D",
-            "@i",
-            "M=0",
-
-            &format!("({})", loop_start_label),
-            "@nVars",
-            "D=M",
-            "@i",
-            "D=D-M",    // D = nVars - i
-            &format!("@{}", loop_end_label),
-            "D;JEQ",    // If nVars - i == 0 finish loop
-
-            "@SP",      // else push 0
-            "A=M",
-            "M=0",      
             "@SP",
-            "M=M+1",
-
-            "@i",
-            "M=M+1",    // i++
-
-            &format!("@{}", loop_start_label),
-            "0;JMP",
-
-            &format!("({})", loop_end_label)
+            "M=D"       // SP += nVars
         ]);
-
-        self.func_count += 1;
     }
 
     
     fn translate_return(&mut self) {
+        if self.shared_routines {
+            self.use_routine("$$RETURN");
+            return self.write_strings(&["@$$RETURN", "0;JMP"]);
+        }
+        self.write_return();
+    }
+
+
+    fn write_return(&mut self) {
         // We assume that the return value is at the top of the stack
 
         self.write_strings(&[
@@ -289,10 +372,8 @@ impl CodeWriter {
             // 3. Reposition the return value for the caller. Put the return value where ARG is.
             //      -> *ARG = pop()
             "@SP",
-            "A=M-1",
+            "AM=M-1",
             "D=M",      // D = pop()
-            "@SP",
-            "M=M-1",
 
             "@ARG",
             "A=M",      // M=RAM[ARG]
@@ -345,16 +426,27 @@ impl CodeWriter {
 
     fn translate_label(&mut self, command: &Command) {
         // label <label_name>
-        self.write_string(
-            &format!("({})", command.get_arg1())
-        );
+        //      -> (<function_name>$<label_name>)
+        let label = self.function_label(command.get_arg1());
+        self.write_string(&format!("({})", label));
+    }
+
+
+    fn function_label(&self, label: &str) -> String {
+        // Two functions can use the same label names, e.g. the compiler's WHILE_EXP0
+        if self.current_function.is_empty() {
+            label.to_string()
+        } else {
+            format!("{}${}", self.current_function, label)
+        }
     }
 
 
     fn translate_goto(&mut self, command: &Command) {
         // goto <label_name>
+        let label = self.function_label(command.get_arg1());
         self.write_strings(&[
-            &format!("@{}", command.get_arg1()),
+            &format!("@{}", label),
             "0;JMP"
         ]);
     }
@@ -362,13 +454,10 @@ impl CodeWriter {
 
     fn translate_if(&mut self, command: &Command) {
         // if-goto <label_name>
+        let label = self.function_label(command.get_arg1());
+        self.pop_D();
         self.write_strings(&[
-            "@SP",
-            "A=M-1",
-            "D=M",
-            "@SP",
-            "M=M-1",
-            &format!("@{}", command.get_arg1()),
+            &format!("@{}", label),
             "D;JNE"
         ])
 
@@ -376,313 +465,374 @@ impl CodeWriter {
 
 
     fn translate_push(&mut self, command: &Command) {
-        let index = if let Some(i) = command.get_arg2() {
-            i
-        } else {
-            translation_error(&format!("No index was given: push {}", command.get_arg1()))
-        };
-
-        match command.get_arg1().as_str() {
-            "argument" => self.generic_mem_push("@ARG", index),
-            "local"    => self.generic_mem_push("@LCL", index),
-            "this"     => self.generic_mem_push("@THIS", index),
-            "that"     => self.generic_mem_push("@THAT", index),
-            "static"   => self.push_static(index, command.get_file_name()),
-            "constant" => self.push_constant(command),
-            "pointer"  => self.push_base_index(index, "@3"),
-            "temp"     => self.push_base_index(index, "@5"),
-            _          => translation_error(&format!("Invalid memory location: {}", command.get_arg1()))
-        };
+        if command.get_arg1() == "constant" {
+            match command.get_arg2().map(String::as_str) {
+                Some("0") => return self.push_reg("0"),
+                Some("1") => return self.push_reg("1"),
+                _ => ()
+            }
+        }
+        self.load_D(command);
+        self.push_reg("D");
     }
 
 
     fn translate_pop(&mut self, command: &Command) {
-        let index = if let Some(i) = command.get_arg2() {
-            i
-        } else {
-            translation_error(
-                &format!("No index was given: pop {}", command.get_arg1())
-            )
-        };
+        self.store_D(command, |writer| writer.pop_D());
+    }
 
-        match command.get_arg1().as_str() {
-            "argument" => self.generic_mem_pop("@ARG", index),
-            "local"    => self.generic_mem_pop("@LCL", index),
-            "this"     => self.generic_mem_pop("@THIS", index),
-            "that"     => self.generic_mem_pop("@THAT", index),
-            "static"   => self.pop_static(index, command.get_file_name()),
-            "constant" => translation_error("Can't pop to 'constant' memory segment."),
-            "pointer"  => self.pop_base_index(index, "@3"),
-            "temp"     => self.pop_base_index(index, "@5"),
-            _          => translation_error(&format!("Invalid memory location: {}", command.get_arg1()))
-        };
+
+    fn push_value(&mut self, value: u16) {
+        // A constant that doesn't fit in an A-instruction is loaded as !(!value)
+        match value {
+            0      => self.push_reg("0"),
+            1      => self.push_reg("1"),
+            0xFFFF => self.push_reg("-1"),
+            2..=32767 => {
+                self.write_strings(&[&format!("@{}", value), "D=A"]);
+                self.push_reg("D");
+            },
+            _ => {
+                self.write_strings(&[&format!("@{}", !value), "D=!A"]);
+                self.push_reg("D");
+            }
+        }
     }
 
 
-    fn compare_arithmetic(&mut self, comp: &str) {
+    fn load_D(&mut self, command: &Command) {
+        /*
+         *  D = the value `push <segment> <index>` would push
+         *      push constant 7  ->  @7, D=A
+         *      push static 3    ->  @<file_name>.3, D=M
+         *      push temp 2      ->  @7, D=M
+         *      push local 2     ->  @LCL, D=M, @2, A=D+A, D=M
+         */
+        let index = segment_index(command);
+
+        if command.get_arg1() == "constant" {
+            return self.write_strings(&[&format!("@{}", index), "D=A"]);
+        }
+        if let Some(address) = direct_address(command) {
+            return self.write_strings(&[&address, "D=M"]);
+        }
+
+        let base = base_register(command);
+        match index {
+            "0" => self.write_strings(&[base, "A=M", "D=M"]),
+            "1" => self.write_strings(&[base, "A=M+1", "D=M"]),
+            _   => self.write_strings(&[
+                base,
+                "D=M",
+                &format!("@{}", index),
+                "A=D+A",        // M=RAM[*base+<index>]
+                "D=M"
+            ])
+        }
+    }
+
+
+    fn store_D(&mut self, command: &Command, load: impl FnOnce(&mut CodeWriter)) {
+        /*
+         *  `pop <segment> <index>` of whatever load() puts in D.
+         *  It's not where ya binnnnn,
+         *  but where you gonna goooooooooo...
+         *
+         *  static, temp and pointer have a fixed address, as do local etc. with
+         *  index 0 or 1. Otherwise the address is worked out first and kept in R13,
+         *  since D is needed for the value.
+         */
+        let index = segment_index(command);
+
+        if let Some(address) = direct_address(command) {
+            load(self);
+            return self.write_strings(&[&address, "M=D"]);
+        }
+
+        let base = base_register(command);
+        match index {
+            "0" | "1" => {
+                load(self);
+                let address = if index == "0" { "A=M" } else { "A=M+1" };
+                self.write_strings(&[base, address, "M=D"]);
+            },
+            _ => {
+                self.write_strings(&[
+                    &format!("@{}", index),
+                    "D=A",
+                    base,
+                    "D=D+M",            // D=RAM[<base>]+index
+                    "@R13",
+                    "M=D"               // store the address in R13
+                ]);
+                load(self);
+                self.write_strings(&["@R13", "A=M", "M=D"]);
+            }
+        }
+    }
+
+
+    fn compare_arithmetic(&mut self, jump: &str) {
+        if self.shared_routines {
+            let routine = match jump {
+                "D;JEQ" => "$$EQ",
+                "D;JGT" => "$$GT",
+                _       => "$$LT"
+            };
+            return self.jump_to_routine(routine, &[]);
+        }
+
         /*
          *  Handles gt, lt and eq. 
          *  We have an IF statement (if x == y store TRUE, else FALSE),
-         *  Therefore we need a label and a jump... 
-         *  We can use store_true_count to write out different labels
-         *  this is not the most efficient way...
+         *  so true is stored first and replaced with false if the jump isn't taken.
+         *  store_true_count gives each one a different label.
          *  EXAMPLE (eq)
          *      @SP
-         *      A=M-1 
-         *      D=M
-         *      SP--
-         *      @SP
-         *      A=M-1
-         *      D=D-M      
-         *      @true_<store_true_count>
-         *      D;JEQ   // JEQ will change depending on jmp_type
-         *      @SP     
-         *      A=M-1
-         *      M=0     // Store false
+         *      AM=M-1
+         *      D=M        // D = y, SP--
+         *      A=A-1
+         *      D=M-D      // D = x - y
+         *      M=-1       // Store true
          *      @comp_end_<store_true_count>
-         *      ;JMP
-         *  (true_<store_true_count>)
+         *      D;JEQ      // JEQ will change depending on the comparison
          *      @SP
          *      A=M-1
-         *      M=-1
+         *      M=0        // Store false
          *  (comp_end_<store_true_count>)
          * 
          */
 
-        let true_label     = format!("true_{}", self.store_true_count);
         let comp_end_label = format!("comp_end_{}", self.store_true_count);
 
-        self.write_strings(&["@SP", "A=M-1", "D=M"]);
-        self.modify_SP(false);
-        self.write_strings(&["@SP", "A=M-1", "D=D-M"]);
-        
-        self.write_string(&format!("@{}", true_label));
-        self.write_string(comp);
-        // Store false
-        self.write_strings(&["@SP", "A=M-1", "M=0"]);
-        self.write_string(&format!("@{}", comp_end_label));
-        self.write_string("0;JMP");
-
-        // Store true
-        self.write_string(&format!("({})", true_label));
-        self.write_strings(&["@SP", "A=M-1", "M=-1"]);
-
-        self.write_string(&format!("({})", comp_end_label));
-        self.store_true_count += 1;
-    }
-
-
-    fn two_var_arithmetic(&mut self, arith_command: &str) {
+        self.pop_D();
         self.write_strings(&[
+            "A=A-1",
+            "D=M-D",
+            "M=-1",
+            &format!("@{}", comp_end_label),
+            jump,
             "@SP",
             "A=M-1",
-            "D=M"       // Store y in D
-        ]);
-        self.modify_SP(false);
-        self.write_strings(&[
-            "@SP",
-            "A=M-1",
-            arith_command
+            "M=0",
+            &format!("({})", comp_end_label)
         ]);
+        self.store_true_count += 1;
     }
 
 
-    fn push_constant(&mut self, command: &Command) {
+    fn call_routine(&mut self, command: &Command) {
         /*
-         *   @<constant>
-         *   D=A
-         *   @SP
-         *   A=M
-         *   M=D
-         *   SP++
+         *  call <function_name> nArgs, through $$CALL
+         *      @<function_name>
+         *      D=A
+         *      @R13
+         *      M=D         // R13 = the function
+         *      ...R14 = the return address, D = nArgs...
+         *      @$$CALL
+         *      0;JMP
+         *  (<ret_addr>)
          */
-        
-        // Parse constant
-        let constant = if let Some(c) = command.get_arg2() {
-            c   // Assume that c is an unsigned integer... within the correct range
-        } else {
-            translation_error("Push/Pop command requires a second argument.\n'push constant <constant>")
+        let nArgs = count(command);
+        let load_nArgs = match nArgs {
+            0 | 1 => vec![format!("D={}", nArgs)],
+            _     => vec![format!("@{}", nArgs), "D=A".to_string()]
         };
 
-        // Store the constant in D
-        let label = &format!("@{}", constant);
-        self.write_strings(&[
-            label,
-            "D=A",
-            "@SP",
-            "A=M",
-            "M=D"
-        ]);
-        self.modify_SP(true);
+        self.write_strings(&[&format!("@{}", command.get_arg1()), "D=A", "@R13", "M=D"]);
+        let before_jump: Vec<&str> = ["@R14", "M=D"].into_iter()
+            .chain(load_nArgs.iter().map(String::as_str))
+            .collect();
+        self.jump_to_routine("$$CALL", &before_jump);
     }
 
 
-    fn push_static(&mut self, index: &str, vm_file_name: &str) {
-        /*
-         *  push static <index>
-         *  output:
-         *      @<file_name>.<index>
-         *      D=M
-         *      ...Push D to stack...
-         *  
-         */
-        let var_label = &format!("@{}.{}", vm_file_name, index);
+    fn jump_to_routine(&mut self, routine: &'static str, before_jump: &[&str]) {
+        // D = the return address, then before_jump, then off to the routine
+        let ret_addr = format!("{}.ret{}", self.file_name, self.return_count);
+        self.return_count += 1;
+        self.use_routine(routine);
+
+        self.write_strings(&[&format!("@{}", ret_addr), "D=A"]);
+        self.write_strings(before_jump);
         self.write_strings(&[
-            var_label,
-            "D=M",
-            "@SP",
-            "A=M",
-            "M=D"
+            &format!("@{}", routine),
+            "0;JMP",
+            &format!("({})", ret_addr)
         ]);
-        self.modify_SP(true);
     }
 
 
-    fn generic_mem_push(&mut self, mem_seg: &str, index: &str) {
-        let index_label = &format!("@{}", index);
-        self.write_strings(&[
-            mem_seg,        // @LCL for example
-            "D=M",
-            index_label,
-            "A=D+A",        // M=RAM[*LCL+<index>]
-            "D=M",
-            "@SP",
-            "A=M",
-            "M=D"
-        ]);
-        self.modify_SP(true);
+    fn use_routine(&mut self, routine: &'static str) {
+        if !self.routines.contains(&routine) {
+            self.routines.push(routine);
+        }
     }
 
 
-    fn push_base_index(&mut self, index: &str, base: &str) {
+    fn write_routine(&mut self, routine: &'static str) {
         /*
-         *  Temp = RAM[5-12], pointer=RAM[3-4]
-         *  Assume that index is in the right range...
-         * 
+         *  The shared routines, written once at the end of the output.
+         *  ($$CALL)   R13 = the function, R14 = the return address, D = nArgs
+         *  ($$RETURN) the same as an inline return
+         *  ($$EQ)     D = the return address, which is kept in R15. Same for $$GT and $$LT.
          */
+        self.write_string(&format!("({})", routine));
+
+        match routine {
+            "$$CALL" => {
+                self.write_strings(&[
+                    "@SP",
+                    "D=M-D",
+                    "@R15",
+                    "M=D"       // R15 = SP - nArgs, the callee's ARG
+                ]);
+                for register in ["@R14", "@LCL", "@ARG", "@THIS", "@THAT"] {
+                    self.write_strings(&[register, "D=M"]);
+                    self.push_reg("D");
+                }
+                self.write_strings(&[
+                    "@R15",
+                    "D=M",
+                    "@ARG",
+                    "M=D",      // ARG = SP - 5 - nArgs
+                    "@SP",
+                    "D=M",
+                    "@LCL",
+                    "M=D",      // LCL = SP
+                    "@R13",
+                    "A=M",
+                    "0;JMP"     // goto <function_name>
+                ]);
+            },
+            "$$RETURN" => self.write_return(),
+            _ => {
+                let jump = match routine {
+                    "$$EQ" => "D;JEQ",
+                    "$$GT" => "D;JGT",
+                    _      => "D;JLT"
+                };
+                let end_label = format!("{}_END", routine);
+
+                self.write_strings(&["@R15", "M=D"]);
+                self.pop_D();
+                self.write_strings(&[
+                    "A=A-1",
+                    "D=M-D",    // D = x - y
+                    "M=-1",     // Store true
+                    &format!("@{}", end_label),
+                    jump,
+                    "@SP",
+                    "A=M-1",
+                    "M=0",      // Store false
+                    &format!("({})", end_label),
+                    "@R15",
+                    "A=M",
+                    "0;JMP"
+                ]);
+            }
+        }
+    }
 
-        let index_label = &format!("@{}", index);
 
+    fn two_var_arithmetic(&mut self, arith_command: &str) {
+        self.pop_D();           // Store y in D
         self.write_strings(&[
-            index_label,
-            "D=A",
-            base,       // Base of temp/ptr (5/3)
-            "A=D+A",    // M = RAM[index+base]
-            "D=M",
-            "@SP",
-            "A=M",
-            "M=D"
+            "A=A-1",
+            arith_command
         ]);
-        self.modify_SP(true)
     }
 
 
-    fn pop_static(&mut self, index: &str, vm_file_name: &str) {
-        /*
-         *  pop static index
-         *  Output:
-         *      ...Store the top of the stack in D...
-         *      @<file_name>.<index>
-         *      M=D
-         */
-        let var_label = &format!("@{}.{}", vm_file_name, index);
-
-        self.write_strings(&[
-            "@SP",
-            "A=M-1",
-            "D=M"
-        ]);
-        self.modify_SP(false);
-        self.write_strings(&[
-            var_label,
-            "M=D"
-        ]);
+    fn write_string(&mut self, string: &str) {
+        // Take a string and write that shit to the output file
+        let string = format!("{}\n", string);
+        if let Err(error) = self.writer.write_all(string.as_bytes()) {
+            self.write_error.get_or_insert(error);
+        }
     }
 
 
-    fn generic_mem_pop(&mut self, mem_seg: &str, index: &str) {
-        /*
-         *  It's not where ya binnnnn,
-         *  but where you gonna goooooooooo...
-         *  
-         *  - Store the value of mem_seg + index in R13
-         *  - Grab the pop top of the stack to D
-         *  - Store D in Ram[R13]
-         * 
-         */
-
-        let index_label   = &format!("@{}", index);
-        
-        self.write_strings(&[
-            index_label,
-            "D=A",
-            mem_seg,
-            "D=M+D",            // D=RAM[<mem_seg>]+index
-            "@R13",
-            "M=D",              // store mem_seg in R13
-            "@SP",
-            "A=M-1",
-            "D=M"
-        ]);
-        self.modify_SP(false);  
-        self.write_strings(&[
-            "@R13",
-            "A=M",
-            "M=D"
-        ]);
+    fn write_strings(&mut self, strings: &[&str]) {
+        // Write every string in strings on a newline
+        for string in strings {
+            self.write_string(string);
+        }
     }
+}
 
 
-    fn pop_base_index(&mut self, index: &str, base: &str) {
-        let index_label = &format!("@{}", index);
-        self.write_strings(&[
-            index_label,
-            "D=A",
-            base,
-            "D=D+A",
-            "@R13",
-            "M=D",
-            "@SP",
-            "A=M-1",
-            "D=M"
-        ]);
-        self.modify_SP(false);
-        self.write_strings(&[
-            "@R13",
-            "A=M",
-            "M=D"
-        ])
+fn arithmetic_comp(op: &str) -> &'static str {
+    // x op y, with y in D and x in M
+    match op {
+        "add" => "M=D+M",
+        "sub" => "M=M-D",
+        "and" => "M=D&M",
+        _     => "M=D|M"
     }
+}
 
 
-    fn modify_SP(&mut self, inc: bool) {
-        // Incrementing / Decrementing the value of stack pointer
-        let mut strings = vec!["@SP"];
-        if inc {
-            // Inc address in SP
-            strings.push("M=M+1");
-        } else {
-            // Decc address in SP
-            strings.push("M=M-1");
-        }
-        self.write_strings(&strings);
+fn check(command: &Command) -> Result<(), String> {
+    /*
+     *  What could stop a command the parser accepted from being translated.
+     *  The rest of the code writer can assume commands have been through here.
+     */
+    let fits = |what: &str| match command.get_arg2().map(|n| n.parse::<u16>()) {
+        Some(Ok(n)) => Ok(n),
+        _ => Err(format!("{} doesn't fit in 16 bits", what))
+    };
+
+    match command.get_command_type() {
+        CommandType::Push | CommandType::Pop => {
+            let index = fits("the index")?;
+            match command.get_arg1().as_str() {
+                "constant" if *command.get_command_type() == CommandType::Pop => {
+                    Err("can't pop to the constant segment".to_string())
+                },
+                "constant" if index > 32767 => Err(format!("constant {} is too large, the most is 32767", index)),
+                _ => Ok(())
+            }
+        },
+        CommandType::Call     => fits("the argument count").map(|_| ()),
+        CommandType::Function => fits("the local variable count").map(|_| ()),
+        _ => Ok(())
     }
+}
 
 
-    fn write_string(&mut self, string: &str) {
-        // Take a string and write that shit to the output file
-        let string = format!("{}\n", string);
-        self.writer.write_all(string.as_bytes())
-                   .expect("Error occurred while writing to output.");
-    }
+fn count(command: &Command) -> u16 {
+    // nArgs of a call or nVars of a function, already checked
+    command.get_arg2().and_then(|n| n.parse().ok()).unwrap_or_default()
+}
 
 
-    fn write_strings(&mut self, strings: &[&str]) {
-        // Write every string in strings on a newline
-        for string in strings {
-            let string = format!("{}\n", string);
-            self.writer.write_all(string.as_bytes())
-                       .expect("Error occurred while writing to output.");
-        }
+fn segment_index(command: &Command) -> &str {
+    // The parser makes sure push and pop have one, check() that it fits
+    command.get_arg2().map(String::as_str).unwrap_or_default()
+}
+
+
+fn direct_address(command: &Command) -> Option<String> {
+    // static is @<file_name>.<index>, temp = RAM[5-12] and pointer = RAM[3-4]
+    let index = segment_index(command);
+    let base = match command.get_arg1().as_str() {
+        "static"  => return Some(format!("@{}.{}", command.get_file_name(), index)),
+        "temp"    => 5,
+        "pointer" => 3,
+        _         => return None
+    };
+    let index: u32 = index.parse().unwrap_or_default();
+    Some(format!("@{}", base + index))
+}
+
+
+fn base_register(command: &Command) -> &'static str {
+    match command.get_arg1().as_str() {
+        "argument" => "@ARG",
+        "local"    => "@LCL",
+        "this"     => "@THIS",
+        "that"     => "@THAT",
+        _          => unreachable!("the parser only makes known memory segments")
     }
 }
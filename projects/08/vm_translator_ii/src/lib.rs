@@ -0,0 +1,15 @@
+// VM translator library.
+// The binary translates a .vm file or a directory given on the command line, other
+// tools can use `translate_files` to choose exactly which files go into the .asm.
+// The whole program is validated first, and functions that can't be reached from
+// Sys.init are left out either way.
+
+pub mod code_writer;
+pub mod peephole;
+pub mod reachability;
+pub mod validation;
+#[allow(non_snake_case)]
+pub mod vm_translator;
+
+pub use hack_core::vm::VmError;
+pub use crate::vm_translator::{translate_files, vm_translate, TranslateOptions};
@@ -1,15 +1,37 @@
-use std::env;
+use std::{env, process};
 
-#[allow(non_snake_case)]
-mod vm_translator;
-mod parser;
-mod code_writer;
+use vm_translator_ii::vm_translator::{self, TranslateOptions};
+
+const USAGE: &str = "Usage: vm_translator_ii [--shared-routines] <path-to-source-file>";
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut options = TranslateOptions::default();
+    let mut files: Vec<String> = Vec::new();
+
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--shared-routines" => options.shared_routines = true,   // One copy of call/return/eq/gt/lt
+            flag if flag.starts_with("--") => {
+                println!("Unknown option {}\n{}", flag, USAGE);
+                process::exit(-1);
+            }
+            _ => files.push(arg)
+        }
+    }
 
-    match args.len() {
-        2 => vm_translator::vm_translate(args[1].clone()),
-        _ => println!("Usage: `cargo run -- <path-to-source-file>")
+    match files.len() {
+        1 => {
+            if let Err(errors) = vm_translator::vm_translate(files[0].clone(), &options) {
+                for e in &errors {
+                    eprintln!("error: {}", e);
+                }
+                eprintln!("Failed to translate {}: {} error(s)", files[0], errors.len());
+                process::exit(-1);
+            }
+        }
+        _ => {
+            println!("{}", USAGE);
+            process::exit(-1);
+        }
     }
 }
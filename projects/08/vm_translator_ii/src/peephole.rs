@@ -0,0 +1,260 @@
+/* ==================================================
+ * Peephole patterns over a short window of VM commands.
+ * The code writer holds commands back while they
+ * could still be the start of one of these, and
+ * translates a whole pattern as one piece of code.
+ * ================================================== */
+
+use hack_core::vm::{Command, CommandType};
+
+
+// The longest pattern, in commands
+pub const WINDOW: usize = 3;
+
+
+pub enum Fused<'a> {
+    Move { from: &'a Command, to: &'a Command },           // push X, pop Y: straight through D
+    ConstantArithmetic { constant: &'a str, op: &'a str }, // push constant N, add/sub/and/or
+    Constant(u16),                                         // push constant N, not/neg: folded
+    CompareJump { jump: &'static str, label: &'a str },    // eq/gt/lt, (not,) if-goto
+    NotJump { label: &'a str }                             // not, if-goto: jump unless x is -1
+}
+
+
+pub fn fuse(window: &[Command]) -> Option<(Fused<'_>, usize)> {
+    // The pattern at the start of the window, and how many commands it takes up
+    match window {
+        [a, b, c, ..] if comparison(a).is_some() && is_op(b, "not") && is_if(c) => {
+            let jump = match comparison(a)? {
+                "JEQ" => "JNE",
+                "JGT" => "JLE",
+                _     => "JGE"
+            };
+            Some((Fused::CompareJump { jump, label: c.get_arg1() }, 3))
+        },
+        [a, b, ..] if comparison(a).is_some() && is_if(b) => {
+            Some((Fused::CompareJump { jump: comparison(a)?, label: b.get_arg1() }, 2))
+        },
+        [a, b, ..] if is_op(a, "not") && is_if(b) => Some((Fused::NotJump { label: b.get_arg1() }, 2)),
+        [a, b, ..] if is_push(a) && is_pop(b) => Some((Fused::Move { from: a, to: b }, 2)),
+        [a, b, ..] if is_constant(a) => {
+            let constant = a.get_arg2()?;
+            match b.get_arg1().as_str() {
+                "add" | "sub" | "and" | "or" if is_arithmetic(b) => {
+                    Some((Fused::ConstantArithmetic { constant, op: b.get_arg1() }, 2))
+                },
+                "not" | "neg" if is_arithmetic(b) => {
+                    let value: u16 = constant.parse().ok()?;
+                    let folded = if b.get_arg1() == "not" { !value } else { value.wrapping_neg() };
+                    Some((Fused::Constant(folded), 2))
+                },
+                _ => None
+            }
+        },
+        _ => None
+    }
+}
+
+
+pub fn could_fuse(window: &[Command]) -> bool {
+    // Whether the window is the start of a pattern that needs more commands
+    match window {
+        [a] => is_push(a) || comparison(a).is_some() || is_op(a, "not"),
+        [a, b] => comparison(a).is_some() && is_op(b, "not"),
+        _ => false
+    }
+}
+
+
+fn comparison(command: &Command) -> Option<&'static str> {
+    // The jump taken when the comparison is true, on D = x - y
+    if !is_arithmetic(command) {
+        return None;
+    }
+    match command.get_arg1().as_str() {
+        "eq" => Some("JEQ"),
+        "gt" => Some("JGT"),
+        "lt" => Some("JLT"),
+        _    => None
+    }
+}
+
+
+fn is_arithmetic(command: &Command) -> bool {
+    *command.get_command_type() == CommandType::Arithmetic
+}
+
+
+fn is_op(command: &Command, op: &str) -> bool {
+    is_arithmetic(command) && command.get_arg1() == op
+}
+
+
+fn is_if(command: &Command) -> bool {
+    *command.get_command_type() == CommandType::If
+}
+
+
+fn is_push(command: &Command) -> bool {
+    *command.get_command_type() == CommandType::Push
+}
+
+
+fn is_pop(command: &Command) -> bool {
+    *command.get_command_type() == CommandType::Pop && command.get_arg1() != "constant"
+}
+
+
+fn is_constant(command: &Command) -> bool {
+    is_push(command) && command.get_arg1() == "constant"
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use cpu_emulator::HackCpu;
+    use hack_core::vm::parse_command;
+
+    use super::*;
+    use crate::code_writer::CodeWriter;
+    use crate::vm_translator::TranslateOptions;
+
+    fn commands(source: &str) -> Vec<Command> {
+        source.lines()
+              .map(str::trim)
+              .filter(|line| !line.is_empty())
+              .map(|line| parse_command(line, "Test").expect("valid VM command"))
+              .collect()
+    }
+
+    fn run(name: &str, source: &str) -> Vec<u16> {
+        // Translates the commands, runs them on the CPU and returns what's left on the stack
+        let asm = env::temp_dir().join(format!("peephole-{}-{}.asm", name, process::id())).display().to_string();
+        let mut writer = CodeWriter::new(&asm, &TranslateOptions::default()).expect("can create the .asm file");
+        for command in commands(&format!("{}\nlabel HALT\ngoto HALT", source)) {
+            writer.translate_command(&command).expect("command translates");
+        }
+        writer.finish().expect("can write the .asm file");
+
+        let cpu = HackCpu::from_file(&asm);
+        fs::remove_file(&asm).ok();
+        let mut cpu = cpu.unwrap_or_else(|_| panic!("the translation of {} doesn't assemble", name));
+
+        // SP, LCL, ARG, THIS, THAT
+        for (register, value) in [256, 300, 400, 3000, 3010].into_iter().enumerate() {
+            cpu.write_ram(register as u16, value);
+        }
+        assert!(cpu.run_until_halt(10_000), "{} should reach its HALT loop", name);
+        (256..cpu.read_ram(0)).map(|address| cpu.read_ram(address)).collect()
+    }
+
+    #[test]
+    fn comparison_then_not_jumps_on_the_opposite() {
+        for (op, jump) in [("eq", "JNE"), ("gt", "JLE"), ("lt", "JGE")] {
+            let window = commands(&format!("{}\nnot\nif-goto L", op));
+            match fuse(&window) {
+                Some((Fused::CompareJump { jump: fused, label: "L" }, 3)) => assert_eq!(fused, jump, "{} not", op),
+                _ => panic!("{}, not, if-goto should fuse into one jump", op)
+            }
+        }
+
+        for (op, jump) in [("eq", "JEQ"), ("gt", "JGT"), ("lt", "JLT")] {
+            let window = commands(&format!("{}\nif-goto L", op));
+            match fuse(&window) {
+                Some((Fused::CompareJump { jump: fused, label: "L" }, 2)) => assert_eq!(fused, jump, "{}", op),
+                _ => panic!("{}, if-goto should fuse into one jump", op)
+            }
+        }
+        assert!(could_fuse(&commands("lt\nnot")));
+    }
+
+    fn push(value: i16) -> String {
+        // A constant can't be negative, so -n is pushed as n then neg
+        let push = format!("push constant {}", value.unsigned_abs());
+        if value < 0 { push + "\nneg" } else { push }
+    }
+
+    #[test]
+    fn comparison_jumps_run() {
+        // 1 if the jump is taken, 0 if not
+        let taken = |name: &str, x: i16, y: i16, ops: &str| {
+            run(name, &format!(
+                "{}\n{}\n{}\nif-goto YES\npush constant 0\ngoto END\nlabel YES\npush constant 1\nlabel END",
+                push(x), push(y), ops
+            ))
+        };
+        assert_eq!(taken("eq", 3, 3, "eq"), [1]);
+        assert_eq!(taken("eq-not", 3, 3, "eq\nnot"), [0]);
+        assert_eq!(taken("gt", -2, 5, "gt"), [0]);
+        assert_eq!(taken("gt-not", -2, 5, "gt\nnot"), [1]);
+        assert_eq!(taken("lt", -2, 5, "lt"), [1]);
+        assert_eq!(taken("lt-not", 5, 5, "lt\nnot"), [1]);
+    }
+
+    #[test]
+    fn not_jump_falls_through_only_on_minus_one() {
+        assert!(matches!(fuse(&commands("not\nif-goto L")), Some((Fused::NotJump { label: "L" }, 2))));
+
+        // Through temp 0, so the push isn't folded into the not
+        let not_jump = |name: &str, push: &str| {
+            run(name, &format!("{}\npop temp 0\npush temp 0\nnot\nif-goto YES\npush constant 111\ngoto END\nlabel YES\npush constant 222\nlabel END", push))
+        };
+        assert_eq!(not_jump("minus-one", &push(-1)), [111]);
+        assert_eq!(not_jump("zero", &push(0)), [222]);
+        assert_eq!(not_jump("five", &push(5)), [222]);
+    }
+
+    #[test]
+    fn not_and_neg_of_a_constant_are_folded() {
+        for (source, folded) in [
+            ("push constant 0\nnot", 0xFFFF),
+            ("push constant 32767\nnot", 0x8000),
+            ("push constant 32767\nneg", 0x8001),
+            ("push constant 0\nneg", 0),
+            ("push constant 1\nneg", 0xFFFF)
+        ] {
+            match fuse(&commands(source)) {
+                Some((Fused::Constant(value), 2)) => assert_eq!(value, folded, "{}", source),
+                _ => panic!("{} should fold into one constant", source)
+            }
+            assert_eq!(run(&format!("fold-{:x}-{}", folded, source.len()), source), [folded], "{}", source);
+        }
+    }
+
+    #[test]
+    fn constant_arithmetic_works_in_place() {
+        for (op, result) in [("add", 12), ("sub", 8), ("and", 2), ("or", 10)] {
+            let source = format!("push constant 10\npush constant 2\n{}", op);
+            assert!(matches!(fuse(&commands(&source)[1..]), Some((Fused::ConstantArithmetic { .. }, 2))));
+            assert_eq!(run(&format!("constant-{}", op), &source), [result], "{}", op);
+        }
+        assert_eq!(run("add-one", "push constant 10\npush constant 1\nadd"), [11]);
+        assert_eq!(run("sub-one", "push constant 10\npush constant 1\nsub"), [9]);
+    }
+
+    #[test]
+    fn push_then_pop_moves_without_the_stack() {
+        assert!(matches!(fuse(&commands("push local 2\npop argument 3")), Some((Fused::Move { .. }, 2))));
+        assert!(fuse(&commands("push local 2\npop constant 3")).is_none());
+
+        let moved = run("move", "\
+            push constant 7\npop local 2\n\
+            push local 2\npop argument 3\n\
+            push argument 3\npop that 5\n\
+            push that 5\npop temp 6\n\
+            push temp 6\npush argument 3\npush that 5\npush local 2");
+        assert_eq!(moved, [7, 7, 7, 7]);
+    }
+
+    #[test]
+    fn a_label_between_push_and_op_stops_fusion() {
+        let window = commands("push constant 9\nlabel L\nadd");
+        assert!(fuse(&window).is_none());
+        assert!(!could_fuse(&window[..2]));
+
+        // Jumping to L has to skip the push but still run the add
+        assert_eq!(run("label", "push constant 5\npush constant 7\ngoto L\npush constant 9\nlabel L\nadd"), [12]);
+    }
+}
@@ -0,0 +1,95 @@
+/* ==================================================
+ * Dead-function elimination.
+ * Starting at Sys.init, follows call commands
+ * across every file to find the functions the
+ * program can reach. Only those are translated, so
+ * linking the whole OS costs nothing for the parts
+ * a program never calls.
+ * ================================================== */
+
+use std::collections::{HashMap, HashSet};
+
+use hack_core::vm::{Command, CommandType};
+
+
+// Where the bootstrap code hands over to the program
+pub const ENTRY_POINT: &str = "Sys.init";
+
+
+pub fn eliminate_dead_functions(commands: Vec<Command>) -> (Vec<Command>, Vec<String>) {
+    /*
+     *  Returns the commands of the reachable functions, in their original order,
+     *  and the names of the functions that were left out.
+     *  Commands that come before the first function of a file aren't in any
+     *  function, so they're always kept and their calls count as reachable.
+     *  Without a Sys.init there's nowhere to start from and nothing is dropped.
+     */
+    let functions = function_names(&commands);
+    if !functions.iter().any(|name| name == ENTRY_POINT) {
+        return (commands, Vec::new());
+    }
+
+    let calls = call_graph(&commands);
+    let mut reachable: HashSet<&str> = HashSet::new();
+    let mut to_visit: Vec<&str> = vec![ENTRY_POINT];
+    to_visit.extend(calls.get(&None).into_iter().flatten());
+
+    while let Some(function) = to_visit.pop() {
+        if reachable.insert(function) {
+            to_visit.extend(calls.get(&Some(function)).into_iter().flatten());
+        }
+    }
+
+    let dropped: Vec<String> = functions.iter()
+                                        .filter(|name| !reachable.contains(name.as_str()))
+                                        .cloned()
+                                        .collect();
+
+    let mut current: Option<&str> = None;
+    let mut file_name: Option<&str> = None;
+    let mut kept = Vec::new();
+    for command in &commands {
+        update_function(command, &mut current, &mut file_name);
+        if current.is_none_or(|function| reachable.contains(function)) {
+            kept.push(command.clone());
+        }
+    }
+    (kept, dropped)
+}
+
+
+fn function_names(commands: &[Command]) -> Vec<String> {
+    // Every function defined, in the order they appear
+    commands.iter()
+            .filter(|command| *command.get_command_type() == CommandType::Function)
+            .map(|command| command.get_arg1().clone())
+            .collect()
+}
+
+
+fn call_graph(commands: &[Command]) -> HashMap<Option<&str>, Vec<&str>> {
+    // The functions each function calls. None is the code outside of any function.
+    let mut calls: HashMap<Option<&str>, Vec<&str>> = HashMap::new();
+
+    let mut current: Option<&str> = None;
+    let mut file_name: Option<&str> = None;
+    for command in commands {
+        update_function(command, &mut current, &mut file_name);
+        if *command.get_command_type() == CommandType::Call {
+            calls.entry(current).or_default().push(command.get_arg1());
+        }
+    }
+    calls
+}
+
+
+pub(crate) fn update_function<'a>(command: &'a Command, current: &mut Option<&'a str>, file_name: &mut Option<&'a str>) {
+    // A function runs until the next one starts or its file ends
+    if *file_name != Some(command.get_file_name().as_str()) {
+        *file_name = Some(command.get_file_name());
+        *current = None;
+    }
+    if *command.get_command_type() == CommandType::Function {
+        *current = Some(command.get_arg1());
+    }
+}
@@ -0,0 +1,149 @@
+/* ==================================================
+ * Checks a whole VM program before it's translated.
+ * The parser only looks at one line at a time, so
+ * `push temp 9`, a goto to a label that isn't there
+ * or a call to a function no file defines would
+ * otherwise turn into broken assembly.
+ * ================================================== */
+
+use std::collections::{HashMap, HashSet};
+
+use hack_core::vm::{Command, CommandType, VmError};
+
+use crate::reachability::update_function;
+
+
+pub fn validate(commands: &[Command]) -> Vec<VmError> {
+    /*
+     *  Every problem in the program, in the order the commands come in:
+     *  - segment indexes out of range and pop constant
+     *  - goto/if-goto to a label that isn't in the same function
+     *  - labels and functions defined twice
+     *  - calls to functions that aren't defined anywhere
+     *  - calls with fewer arguments than the function uses, or a different
+     *    number than the other calls to it
+     */
+    let mut errors: Vec<(usize, VmError)> = Vec::new();
+    let mut error = |index: usize, message: String| errors.push((index, commands[index].error(&message)));
+
+    // Functions first, calls can come before the function they call
+    let mut functions: HashMap<&str, usize> = HashMap::new();
+    for (index, command) in commands.iter().enumerate() {
+        if *command.get_command_type() == CommandType::Function {
+            let name = command.get_arg1().as_str();
+            match functions.get(name) {
+                Some(&first) => error(index, format!("{} is already defined at {}", name, location(&commands[first]))),
+                None => { functions.insert(name, index); }
+            }
+        }
+    }
+    let arguments_used = arguments_used(commands);
+
+    let mut labels: HashMap<(&str, Option<&str>), HashSet<&str>> = HashMap::new();
+    let mut jumps: Vec<(usize, (&str, Option<&str>))> = Vec::new();
+    let mut first_calls: HashMap<&str, usize> = HashMap::new();
+
+    let mut current: Option<&str> = None;
+    let mut file_name: Option<&str> = None;
+    for (index, command) in commands.iter().enumerate() {
+        update_function(command, &mut current, &mut file_name);
+        let scope = (command.get_file_name().as_str(), current);
+
+        match command.get_command_type() {
+            CommandType::Push | CommandType::Pop => {
+                if let Err(message) = check_segment(command) {
+                    error(index, message);
+                }
+            },
+            CommandType::Label => {
+                let is_new = labels.entry(scope).or_default().insert(command.get_arg1());
+                if !is_new {
+                    error(index, format!("label {} is already defined in this function", command.get_arg1()));
+                }
+            },
+            CommandType::Goto | CommandType::If => jumps.push((index, scope)),
+            CommandType::Call => {
+                let name = command.get_arg1().as_str();
+                let args = arg_count(command);
+
+                if !functions.contains_key(name) {
+                    error(index, format!("{} isn't defined in any file", name));
+                } else if args < arguments_used[name] {
+                    error(index, format!(
+                        "{} uses argument {}, so it needs at least {} arguments but is called with {}",
+                        name, arguments_used[name] - 1, arguments_used[name], args
+                    ));
+                } else if let Some(&first) = first_calls.get(name) {
+                    if arg_count(&commands[first]) != args {
+                        error(index, format!(
+                            "{} is called with {} arguments here but {} at {}",
+                            name, args, arg_count(&commands[first]), location(&commands[first])
+                        ));
+                    }
+                } else {
+                    first_calls.insert(name, index);
+                }
+            },
+            _ => ()
+        }
+    }
+
+    // Labels can be defined after the jumps to them
+    for (index, scope) in jumps {
+        let label = commands[index].get_arg1().as_str();
+        if !labels.get(&scope).is_some_and(|labels| labels.contains(label)) {
+            error(index, format!("label {} isn't defined in this function", label));
+        }
+    }
+
+    errors.sort_by_key(|(index, _)| *index);
+    errors.into_iter().map(|(_, error)| error).collect()
+}
+
+
+fn check_segment(command: &Command) -> Result<(), String> {
+    // temp is RAM[5-12] and pointer RAM[3-4], a constant has to fit in an A-instruction
+    let index: u32 = command.get_arg2().and_then(|i| i.parse().ok()).unwrap_or_default();
+    let is_pop = *command.get_command_type() == CommandType::Pop;
+
+    match command.get_arg1().as_str() {
+        "constant" if is_pop      => Err("can't pop to the constant segment".to_string()),
+        "constant" if index > 32767 => Err(format!("constant {} is too large, the most is 32767", index)),
+        "temp" if index > 7       => Err(format!("temp {} is out of range, temp only goes from 0 to 7", index)),
+        "pointer" if index > 1    => Err(format!("pointer {} is out of range, pointer is only 0 or 1", index)),
+        _ => Ok(())
+    }
+}
+
+
+fn arguments_used(commands: &[Command]) -> HashMap<&str, u32> {
+    // The fewest arguments each function can be called with, one more than the highest it pushes or pops
+    let mut used: HashMap<&str, u32> = HashMap::new();
+
+    let mut current: Option<&str> = None;
+    let mut file_name: Option<&str> = None;
+    for command in commands {
+        update_function(command, &mut current, &mut file_name);
+        let Some(function) = current else {
+            continue;
+        };
+
+        let count = used.entry(function).or_default();
+        let is_push_pop = matches!(command.get_command_type(), CommandType::Push | CommandType::Pop);
+        if is_push_pop && command.get_arg1() == "argument" {
+            let index: u32 = command.get_arg2().and_then(|i| i.parse().ok()).unwrap_or_default();
+            *count = (*count).max(index.saturating_add(1));
+        }
+    }
+    used
+}
+
+
+fn arg_count(command: &Command) -> u32 {
+    command.get_arg2().and_then(|n| n.parse().ok()).unwrap_or_default()
+}
+
+
+fn location(command: &Command) -> String {
+    format!("{}:{}", command.get_file_name(), command.get_line())
+}
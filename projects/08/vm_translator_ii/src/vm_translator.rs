@@ -1,11 +1,19 @@
-use std::fs;
+use std::io;
 use std::path::Path;
-use std::process;
 
-use crate::parser;
+use hack_core::vm::{Command, Parser, VmError};
+
 use crate::code_writer;
+use crate::reachability::eliminate_dead_functions;
+use crate::validation::validate;
+
+// Options for the generated assembly
+#[derive(Default)]
+pub struct TranslateOptions {
+    pub shared_routines: bool   // One copy of the call, return and eq/gt/lt code, which every site jumps to
+}
 
-pub fn vm_translate(input: String) {
+pub fn vm_translate(input: String, options: &TranslateOptions) -> Result<(), Vec<VmError>> {
     /* ==================================================================
      *
      *  input  -> fileName.vm
@@ -16,97 +24,121 @@ pub fn vm_translate(input: String) {
      *  - Constructs codeWriter to handle the output file
      *  - Marches through the file, parsing each line and generating code.
      * 
-     *  Translator is extremely temperamental and will panic at when 
-     *  encountering any bug.
-     *  Probs better to handle these with Result<> but idk...
+     *  Every bad line is reported, not just the first one.
      * 
      * ================================================================== */
 
-    let files: Vec<String> = handle_input(&input);
+    let files: Vec<String> = handle_input(&input).map_err(|e| vec![e])?;
      
     if files.is_empty() {
-        translation_error("Couldn't find any .vm files to translate...")
+        return Err(vec![VmError::NoSources { input }]);
     }
     
-    let output_file: String = create_output_file(&input);
-    let mut code_writer     = code_writer::CodeWriter::new(&output_file);   // One Code Writer for every input file
-    
-    code_writer.init();
+    let output_file: String = create_output_file(&input).map_err(|e| vec![e])?;
+    let dropped = translate_files(&files, &output_file, options)?;
+
+    if !dropped.is_empty() {
+        println!("Dropped {} functions that are never called:", dropped.len());
+        for function in &dropped {
+            println!("    {}", function);
+        }
+    }
+    println!("Finished translating all VM files!\nOutput -> {}", output_file);
+    Ok(())
+}
+
+
+pub fn translate_files(files: &[String], output_file: &String, options: &TranslateOptions) -> Result<Vec<String>, Vec<VmError>> {
+    /*
+     *  Translates the given .vm files, bootstrap code first, into a single output_file.
+     *  Every file is parsed and the whole program validated before anything is
+     *  written, so that only the functions reachable from Sys.init are translated.
+     *  Returns the ones that weren't.
+     */
+    let mut commands: Vec<Command> = Vec::new();
+    let mut errors: Vec<VmError> = Vec::new();
     for vm_file in files {
         println!("Translating {}...", &vm_file);
 
-        let mut parser = parser::Parser::new(vm_file);
+        let mut parser = match Parser::new(vm_file) {
+            Ok(parser) => parser,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
         
         while parser.has_more_commands() {
-            parser.advance();  // Update parser.currentCommand
-            
-            if let Some(command) = parser.get_current_command() {
-                // println!("{}", command);
-                code_writer.translate_command(command)
+            // Update parser.currentCommand, a bad line is noted and skipped
+            match parser.advance() {
+                Ok(()) => commands.extend(parser.get_current_command().cloned()),
+                Err(e @ VmError::Io { .. }) => {
+                    errors.push(e);
+                    break;
+                },
+                Err(e) => errors.push(e)
             }
         }
     }
+    if errors.is_empty() {
+        errors = validate(&commands).into_iter().map(|e| with_path(e, files)).collect();
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    let (commands, dropped) = eliminate_dead_functions(commands);
+
+    let mut code_writer = code_writer::CodeWriter::new(output_file, options).map_err(|e| vec![e])?;   // One Code Writer for every input file
+    code_writer.init();
+    for command in &commands {
+        if let Err(e) = code_writer.translate_command(command) {
+            errors.push(with_path(e, files));
+        }
+    }
+    code_writer.finish().map_err(|e| vec![e])?;
 
-    println!("Finished translating all VM files!\nOutput -> {}", output_file);
+    if errors.is_empty() { Ok(dropped) } else { Err(errors) }
 }
 
 
-fn handle_input(input: &String) -> Vec<String> {
-    let mut files: Vec<String> = Vec::new();
+fn with_path(error: VmError, files: &[String]) -> VmError {
+    // Commands only know the name of their file, errors give the whole path like the parser's do
+    match error {
+        VmError::Translation { file, line, text, message } => {
+            let path = files.iter()
+                            .find(|path| Path::new(path).file_name().is_some_and(|name| *name == *file))
+                            .map_or(file, String::clone);
+            VmError::Translation { file: path, line, text, message }
+        },
+        error => error
+    }
+}
 
-    if input.rfind(".vm").is_some() {
-        // Just one vm file
-        files.push(input.clone());
-    } else {
-        // Folder
-        // -- Sureleh there's a better way...
-        for element in fs::read_dir(input)
-                          .unwrap_or_else(|err| translation_error(&err.to_string())) {
-
-                              
-            let path = element.unwrap().path();
-
-            if let Some(extension) = path.extension() {
-                if extension == "vm" {
-                    files.push(path.into_os_string().into_string().unwrap());
-                }
-            }
-        }
-    };
 
-    files
+fn handle_input(input: &String) -> Result<Vec<String>, VmError> {
+    // Just one vm file, or every vm file in a folder
+    let files = hack_core::handle_input(Path::new(input), "vm")
+        .map_err(|error| VmError::Io { file: input.clone(), error })?;
+
+    Ok(files.into_iter()
+            .map(|path| path.display().to_string())
+            .collect())
 }
 
 
-fn create_output_file(vm_file: &String) -> String {
+fn create_output_file(vm_file: &String) -> Result<String, VmError> {
     if let Some(index) = vm_file.rfind(".vm") {
-        vm_file[..index].to_string() + ".asm"
+        Ok(vm_file[..index].to_string() + ".asm")
     } else {
-        // Create output file from folder
+        // Create output file from folder, named after the last directory
         let path = Path::new(vm_file);
 
-        // Get the value of the last string
-        let last_dir = if let Some(last_dir) = path.file_name() {
-            if let Some(last_dir_str) = last_dir.to_str() {
-                format!("{}.asm", last_dir_str)
-            } else {
-                translation_error(&format!("Could not deduce output file name from {}", vm_file));
-            }
-        } else {
-            translation_error(&format!("Could not deduce output file name from {}", vm_file));
-        };
-
-        let path = path.join(last_dir);
-        if let Some(str) = path.to_str() {
-            str.to_string()
-        } else {
-            translation_error(&format!("Couldn't create output file from {}", vm_file))
+        match path.file_name().and_then(|last_dir| last_dir.to_str()) {
+            Some(last_dir) => Ok(path.join(format!("{}.asm", last_dir)).display().to_string()),
+            None => Err(VmError::Io {
+                file: vm_file.clone(),
+                error: io::Error::new(io::ErrorKind::InvalidInput, "couldn't name the output file after it")
+            })
         }
     }
 }
-
-
-pub fn translation_error(msg: &str) -> ! {
-    eprintln!("VM Translation Error: {}", msg);
-    process::exit(-1);
-}
\ No newline at end of file
@@ -0,0 +1,40 @@
+// FibonacciElement from the FunctionCalls tests, translated and run with the CPU emulator's .tst runner
+use std::{env, fs, process};
+use std::path::PathBuf;
+
+use cpu_emulator::{HackCpu, Script};
+use vm_translator_ii::{translate_files, TranslateOptions};
+
+const FIBONACCI_ELEMENT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../FunctionCalls/FibonacciElement");
+
+fn fibonacci_element(options: &TranslateOptions, name: &str) {
+    // The script loads FibonacciElement.asm next to it, so the script and .cmp are copied to a scratch directory
+    let dir: PathBuf = env::temp_dir().join(format!("{}-{}", name, process::id()));
+    fs::create_dir_all(&dir).expect("can create the scratch directory");
+    for file in ["FibonacciElement.tst", "FibonacciElement.cmp"] {
+        fs::copy(format!("{}/{}", FIBONACCI_ELEMENT, file), dir.join(file)).expect("can copy the test files");
+    }
+
+    let sources: Vec<String> = ["Main.vm", "Sys.vm"].iter().map(|file| format!("{}/{}", FIBONACCI_ELEMENT, file)).collect();
+    let asm = dir.join("FibonacciElement.asm").display().to_string();
+    let translated = translate_files(&sources, &asm, options);
+
+    let script = dir.join("FibonacciElement.tst").display().to_string();
+    let result = Script::from_file(&script).and_then(|script| script.run(&mut HackCpu::new()));
+    fs::remove_dir_all(&dir).ok();
+
+    assert!(translated.is_ok(), "FibonacciElement should translate");
+    if let Err(e) = result {
+        panic!("FibonacciElement.tst failed: {}", e);
+    }
+}
+
+#[test]
+fn fibonacci_element_passes() {
+    fibonacci_element(&TranslateOptions::default(), "fibonacci-element");
+}
+
+#[test]
+fn fibonacci_element_passes_with_shared_routines() {
+    fibonacci_element(&TranslateOptions { shared_routines: true }, "fibonacci-element-shared");
+}
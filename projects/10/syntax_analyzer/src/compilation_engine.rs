@@ -3,7 +3,7 @@ use std::io::{self, BufWriter, ErrorKind, Write};
 use std::path::PathBuf;
 use std::fs::File;
 
-use crate::tokenizer::{Tokenizer, TokenType};
+use hack_core::jack::{Tokenizer, TokenType};
 
 
 pub struct CompilationEngine {
@@ -18,7 +18,7 @@ impl CompilationEngine {
         let output_file = File::create(&output)?;
         
         let writer = BufWriter::new(output_file);
-        let mut tokenizer = Tokenizer::new(source_file)?;
+        let mut tokenizer = Tokenizer::new(&source_file)?;
 
         tokenizer.advance()?;
 
@@ -467,7 +467,7 @@ impl CompilationEngine {
         let token_value = ct.get_value();
         let ctoken_type  = ct.get_token_type();
 
-        let value_check = values.map_or(true, |vals| vals.contains(&token_value.as_str()));
+        let value_check = values.is_none_or(|vals| vals.contains(&token_value.as_str()));
 
         if token_type != *ctoken_type || !value_check {
             return Err(
@@ -5,7 +5,6 @@ use std::process::exit;
 
 mod compilation_engine;
 mod syntax_analyzer;
-mod tokenizer;
 
 fn main() -> std::io::Result<()> {
     let args: Vec<String> = env::args().collect();
@@ -1,5 +1,4 @@
 // Top level driver that sets up and invokes other modules
-use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
 
@@ -7,17 +6,15 @@ use crate::compilation_engine::CompilationEngine;
 
 pub struct SyntaxAnalyzer {
     pub source_files: Vec<PathBuf>,
-    pub output_files: Vec<PathBuf>,
 }
 
 impl SyntaxAnalyzer {
     pub fn new(input: &str) -> Result<SyntaxAnalyzer, io::Error> {
         let input_path = Path::new(input);
-        let files = Self::handle_input(input_path)?;
+        let files = hack_core::handle_input(input_path, "jack")?;
 
         Ok(SyntaxAnalyzer {
             source_files: files,
-            output_files: Vec::new(),
         })
     }
 
@@ -33,34 +30,4 @@ impl SyntaxAnalyzer {
 
         Ok(())
     }
-
-    fn handle_input(input: &Path) -> Result<Vec<PathBuf>, io::Error> {
-        // Method to collect vm files for compilation
-        let mut files = Vec::new();
-
-        // Input is either a file <file_name>.vm, or a directory containing multiple jack files
-        if Self::is_jack_file(input) {
-            files.push(input.to_path_buf())
-        } else if input.is_dir() {
-            for entry in fs::read_dir(input)? {
-                let entry = entry?;
-                let path = entry.path();
-
-                if Self::is_jack_file(&path) {
-                    files.push(path)
-                }
-            }
-        } else {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "No jack files found. Invalid input.",
-            ));
-        }
-
-        Ok(files)
-    }
-
-    fn is_jack_file(path: &Path) -> bool {
-        path.is_file() && path.extension().map_or(false, |ext| ext == "jack")
-    }
 }
@@ -3,9 +3,10 @@ use std::fs::File;
 use std::io::{self, ErrorKind};
 use std::path::PathBuf;
 
+use hack_core::jack::{TokenType, Tokenizer};
+
 use crate::symbol_table::SymbolKind;
 use crate::symbol_table::SymbolTable;
-use crate::tokenizer::{TokenType, Tokenizer};
 use crate::vm_writer::VMWriter;
 
 #[derive(PartialEq, Eq)]
@@ -30,10 +31,7 @@ pub struct CompilationEngine {
 }
 
 impl CompilationEngine {
-    pub fn new(source_file: PathBuf) -> Result<CompilationEngine, io::Error> {
-        let mut output = source_file.clone();
-
-        output.set_extension("vm");
+    pub fn new(source_file: PathBuf, output: PathBuf) -> Result<CompilationEngine, io::Error> {
         let output_file = File::create(&output)?;
 
         let vm_writer = VMWriter::new(output_file);
@@ -738,7 +736,7 @@ impl CompilationEngine {
         let token_value = ct.get_value();
         let ctoken_type = ct.get_token_type();
 
-        let value_check = values.map_or(true, |vals| vals.contains(&token_value.as_str()));
+        let value_check = values.is_none_or(|vals| vals.contains(&token_value.as_str()));
 
         if token_type != *ctoken_type || !value_check {
             return Err(
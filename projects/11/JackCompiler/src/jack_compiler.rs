@@ -1,5 +1,4 @@
 // Top level driver that sets up and invokes other modules
-use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
 
@@ -7,57 +6,46 @@ use crate::compilation_engine::CompilationEngine;
 
 pub struct JackCompiler {
     pub source_files: Vec<PathBuf>,
+    pub output_dir: Option<PathBuf>,
 }
 
 impl JackCompiler {
     pub fn new(input: &str) -> Result<JackCompiler, io::Error> {
         let input_path = Path::new(input);
-        let files = Self::handle_input(input_path)?;
+        let files = hack_core::handle_input(input_path, "jack")?;
 
         Ok(JackCompiler {
             source_files: files,
+            output_dir: None,
         })
     }
 
-    pub fn compile(self) -> Result<(), io::Error> {
+    pub fn with_output_dir(mut self, dir: &Path) -> JackCompiler {
+        // Write the .vm files to dir instead of next to each source file
+        self.output_dir = Some(dir.to_path_buf());
+        self
+    }
+
+    pub fn compile(self) -> Result<Vec<PathBuf>, io::Error> {
+        // Returns the .vm files written
+        let mut outputs = Vec::new();
+
         // Loop through each of the source files
         for source_file in self.source_files {
             println!("Compiling {:?}", source_file);
-            // Initialise a new tokeniser for each source file
-            let mut ce = CompilationEngine::new(source_file)?;
-            ce.compile_class()?;
-        }
 
-        Ok(())
-    }
+            let mut output = match &self.output_dir {
+                Some(dir) => dir.join(source_file.file_name().unwrap_or_default()),
+                None => source_file.clone(),
+            };
+            output.set_extension("vm");
 
-    fn handle_input(input: &Path) -> Result<Vec<PathBuf>, io::Error> {
-        // Method to collect vm files for compilation
-        let mut files = Vec::new();
-
-        // Input is either a file <file_name>.vm, or a directory containing multiple jack files
-        if Self::is_jack_file(input) {
-            files.push(input.to_path_buf())
-        } else if input.is_dir() {
-            for entry in fs::read_dir(input)? {
-                let entry = entry?;
-                let path = entry.path();
-
-                if Self::is_jack_file(&path) {
-                    files.push(path)
-                }
-            }
-        } else {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "No jack files found. Invalid input.",
-            ));
+            // Initialise a new tokeniser for each source file
+            let mut ce = CompilationEngine::new(source_file, output.clone())?;
+            ce.compile_class()?;
+            outputs.push(output);
         }
 
-        Ok(files)
-    }
-
-    fn is_jack_file(path: &Path) -> bool {
-        path.is_file() && path.extension().map_or(false, |ext| ext == "jack")
+        Ok(outputs)
     }
 }
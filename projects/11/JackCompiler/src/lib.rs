@@ -0,0 +1,13 @@
+#![allow(non_snake_case)]
+
+// Jack compiler library.
+// The `JackCompiler` binary is a thin wrapper around `JackCompiler::compile`, other
+// tools can use it to compile a directory of .jack files into a directory of their own.
+// The tokenizer lives in hack-core, where the syntax analyzer from project 10 shares it.
+
+pub mod compilation_engine;
+pub mod jack_compiler;
+pub mod symbol_table;
+pub mod vm_writer;
+
+pub use crate::jack_compiler::JackCompiler;
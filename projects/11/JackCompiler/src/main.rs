@@ -3,11 +3,7 @@
 use std::env;
 use std::process::exit;
 
-mod compilation_engine;
-mod jack_compiler;
-mod symbol_table;
-mod tokenizer;
-mod vm_writer;
+use jack_compiler::JackCompiler;
 
 fn main() -> std::io::Result<()> {
     let args: Vec<String> = env::args().collect();
@@ -19,7 +15,7 @@ fn main() -> std::io::Result<()> {
         exit(-1)
     };
 
-    let c = jack_compiler::JackCompiler::new(input)?;
+    let c = JackCompiler::new(input)?;
 
     match c.compile() {
         Ok(_) => println!("Successfully compiled all source files."),
@@ -17,6 +17,7 @@ struct Symbol {
     kind: SymbolKind,
 }
 
+#[derive(Default)]
 pub struct SymbolTable {
     class_symbols: Vec<Symbol>,
     subroutine_symbols: Vec<Symbol>,
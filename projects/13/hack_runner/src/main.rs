@@ -0,0 +1,120 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::{env, process};
+
+use cpu_emulator::cpu::KBD;
+use cpu_emulator::{screen, HackCpu, KeyboardScript};
+use vm_translator_ii::TranslateOptions;
+
+mod pipeline;
+
+const USAGE: &str = "Usage: hack_runner [--os <dir>]... [--build <dir>] [--emit <file.hack>] [--keys <script>] [--screen <file.png|file.pbm>] [--shared-routines] <jack-dir> [max-cycles]";
+
+// Jack programs are slow, the OS alone takes a while to initialise
+const DEFAULT_MAX_CYCLES: u64 = 50_000_000;
+
+fn main() {
+    let mut os_dirs: Vec<PathBuf> = Vec::new();
+    let mut build_dir: Option<PathBuf> = None;
+    let mut emit: Option<String> = None;
+    let mut keys: Option<String> = None;
+    let mut screen_file: Option<String> = None;
+    let mut options = TranslateOptions::default();
+    let mut positional: Vec<String> = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--os"     => match args.next() {          // OS classes to use instead of tools/OS
+                Some(dir) => os_dirs.push(PathBuf::from(dir)),
+                None => usage_error("Expected a directory after --os")
+            },
+            "--build"  => build_dir = args.next().map(PathBuf::from),   // Where the .vm, .asm and .hack go
+            "--emit"   => emit = args.next(),          // Write the .hack here instead of running it
+            "--keys"   => keys = args.next(),          // Keystroke timeline for KBD
+            "--screen" => screen_file = args.next(),   // Save the screen when the program stops
+            "--shared-routines" => options.shared_routines = true,   // Smaller code, see README
+            flag if flag.starts_with("--") => usage_error(&format!("Unknown option {}", flag)),
+            _ => positional.push(arg)
+        }
+    }
+
+    let (jack_dir, max_cycles) = match positional.len() {
+        1 => (&positional[0], DEFAULT_MAX_CYCLES),
+        2 => match positional[1].parse() {
+            Ok(cycles) => (&positional[0], cycles),
+            Err(_) => usage_error(&format!("Invalid cycle count {}", positional[1]))
+        },
+        _ => usage_error("Expected a directory of .jack files")
+    };
+
+    let build = pipeline::build(Path::new(jack_dir), build_dir.as_deref(), &os_dirs, &options)
+        .unwrap_or_else(|e| exit_with_error(&e));
+
+    println!("{}: linked {} classes", build.name, build.classes.len());
+    for class in &build.classes {
+        println!("    {:<12} {}", class.name, class.source.display());
+    }
+    println!("VM code -> {} ({} unused functions dropped)", build.asm_file.display(), build.dropped.len());
+    println!("{} words -> {}", build.program.words.len(), build.hack_file.display());
+
+    if let Some(emit) = emit {
+        if let Err(e) = fs::copy(&build.hack_file, &emit) {
+            exit_with_error(&e);
+        }
+        println!("Program -> {}", emit);
+        return;
+    }
+
+    let keys = keys.map(|keys| KeyboardScript::from_file(&keys).unwrap_or_else(|e| usage_error(&e)));
+    let mut cpu = HackCpu::with_program(&build.program.words);
+    let halt = build.halt_address();
+
+    /*
+     *  Runs until Sys.halt is reached or max_cycles instructions have been executed.
+     *  Programs waiting on the keyboard never halt, so give them --keys and a cycle count.
+     */
+    let mut halted = false;
+    while cpu.cycles() < max_cycles {
+        if Some(cpu.pc) == halt || cpu.is_halted() {
+            halted = true;
+            break;
+        }
+        if let Some(keys) = &keys {
+            cpu.write_ram(KBD, keys.key_at(cpu.cycles()));
+        }
+        cpu.step();
+    }
+
+    println!("{} after {} cycles", if halted { "Halted" } else { "Stopped" }, cpu.cycles());
+
+    if let Some(screen_file) = screen_file {
+        if let Err(e) = save_screen(&screen_file, &cpu) {
+            eprintln!("Couldn't save the screen to {}: {}", screen_file, e);
+            process::exit(-1);
+        }
+        println!("Screen -> {}", screen_file);
+    }
+}
+
+fn save_screen(screen_file: &str, cpu: &HackCpu) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(screen_file)?);
+
+    if screen_file.ends_with(".pbm") {
+        screen::write_pbm(&mut writer, cpu.ram())?;
+    } else {
+        screen::write_png(&mut writer, cpu.ram())?;
+    }
+    writer.flush()
+}
+
+fn exit_with_error(e: &dyn std::error::Error) -> ! {
+    eprintln!("error: {}", e);
+    process::exit(-1);
+}
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("{}\n{}", msg, USAGE);
+    process::exit(-1);
+}
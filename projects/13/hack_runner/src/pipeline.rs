@@ -0,0 +1,201 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use assembler::symbol_table::find_address;
+use assembler::{assemble_source, AsmError, OutputFormat, Program};
+use jack_compiler::JackCompiler;
+use vm_translator_ii::{translate_files, TranslateOptions, VmError};
+
+// Where the stock OS .vm files are, relative to this crate
+const STOCK_OS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../../tools/OS");
+
+#[derive(Debug)]
+pub enum BuildError {
+    Io { file: String, error: io::Error },
+    Compile { file: String, error: io::Error },
+    NoSources { dir: String },
+    MissingClass { name: String },
+    Translate(Vec<VmError>),
+    Assemble(Vec<AsmError>),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::Io { file, error } => write!(f, "{}: {}", file, error),
+            BuildError::Compile { file, error } => write!(f, "couldn't compile {}: {}", file, error),
+            BuildError::NoSources { dir } => write!(f, "{}: no .jack files to compile", dir),
+            BuildError::MissingClass { name } => {
+                write!(f, "class '{}' is used but isn't in the program or any OS directory", name)
+            }
+            BuildError::Translate(errors) => {
+                let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "{}", errors.join("\n"))
+            }
+            BuildError::Assemble(errors) => {
+                let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "{}", errors.join("\n"))
+            }
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+// Where a class in the linked program came from
+pub struct Class {
+    pub name: String,
+    pub source: PathBuf,
+}
+
+// A Jack program built all the way down to machine code
+pub struct Build {
+    pub name: String,
+    pub classes: Vec<Class>,
+    pub asm_file: PathBuf,
+    pub dropped: Vec<String>,   // Functions left out since nothing calls them
+    pub hack_file: PathBuf,
+    pub program: Program,
+}
+
+impl Build {
+    pub fn halt_address(&self) -> Option<u16> {
+        // Sys.halt loops forever, so reaching it is the end of the program
+        find_address("Sys.halt", &self.program.sym_table).map(|address| address as u16)
+    }
+}
+
+pub fn build(jack_dir: &Path, build_dir: Option<&Path>, os_dirs: &[PathBuf], options: &TranslateOptions) -> Result<Build, BuildError> {
+    /*
+     *  <Class>.jack -> build/<Class>.vm -> build/<name>.asm -> build/<name>.hack
+     *
+     *  The program's own classes come first, then any OS class that's called
+     *  (and Sys, which the bootstrap code calls) from the first of os_dirs that
+     *  has it, compiled if it's .jack or copied if it's .vm. The stock OS in
+     *  tools/OS fills in whatever classes are still missing.
+     */
+    let io_error = |file: &Path| {
+        let file = file.display().to_string();
+        move |error| BuildError::Io { file, error }
+    };
+
+    let full_path = fs::canonicalize(jack_dir).map_err(io_error(jack_dir))?;
+    let name = full_path.file_name()
+                        .and_then(|name| name.to_str())
+                        .unwrap_or("Main")
+                        .to_string();
+
+    let dir = build_dir.map_or_else(|| jack_dir.join("build"), Path::to_path_buf);
+    fs::create_dir_all(&dir).map_err(io_error(&dir))?;
+    clean(&dir).map_err(io_error(&dir))?;
+
+    let mut classes = compile(jack_dir, &dir, None, &[])?;
+    if classes.is_empty() {
+        return Err(BuildError::NoSources { dir: jack_dir.display().to_string() });
+    }
+
+    // Only the OS classes something calls are linked, which can pull in more of them
+    let stock_os = PathBuf::from(STOCK_OS);
+    loop {
+        let missing = missing_classes(&dir, &classes)?;
+        if missing.is_empty() {
+            break;
+        }
+
+        for os_dir in os_dirs.iter().chain([&stock_os]) {
+            let compiled = compile(os_dir, &dir, Some(&missing), &classes)?;
+            classes.extend(compiled);
+            let copied = copy_vm_files(os_dir, &dir, &missing, &classes).map_err(io_error(os_dir))?;
+            classes.extend(copied);
+        }
+
+        if let Some(name) = missing.iter().find(|name| !classes.iter().any(|c| c.name == **name)) {
+            return Err(BuildError::MissingClass { name: name.clone() });
+        }
+    }
+
+    // Bootstrap code, then every class in the order it was linked
+    let asm_file = dir.join(format!("{}.asm", name));
+    let vm_files: Vec<String> = classes.iter()
+                                       .map(|class| dir.join(format!("{}.vm", class.name)).display().to_string())
+                                       .collect();
+    let dropped = translate_files(&vm_files, &asm_file.display().to_string(), options).map_err(BuildError::Translate)?;
+
+    let source = fs::read_to_string(&asm_file).map_err(io_error(&asm_file))?;
+    let program = assemble_source(&asm_file.display().to_string(), &source).map_err(BuildError::Assemble)?;
+
+    let hack_file = dir.join(format!("{}.hack", name));
+    let mut hack = Vec::new();
+    OutputFormat::Text.writer().write_words(&mut hack, &program.words).map_err(io_error(&hack_file))?;
+    fs::write(&hack_file, hack).map_err(io_error(&hack_file))?;
+
+    Ok(Build { name, classes, asm_file, dropped, hack_file, program })
+}
+
+fn clean(dir: &Path) -> Result<(), io::Error> {
+    // Leftovers from an earlier build would otherwise get linked in
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "vm" || ext == "asm" || ext == "hack") {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+fn class_name(path: &Path) -> String {
+    path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default().to_string()
+}
+
+fn missing_classes(dir: &Path, linked: &[Class]) -> Result<Vec<String>, BuildError> {
+    // Classes named in a `call` in any linked .vm file that aren't linked yet
+    let mut missing = vec!["Sys".to_string()];
+    for class in linked {
+        let file = dir.join(format!("{}.vm", class.name));
+        let source = fs::read_to_string(&file).map_err(|error| BuildError::Io { file: file.display().to_string(), error })?;
+
+        for line in source.lines() {
+            let mut words = line.split_whitespace();
+            if let (Some("call"), Some(function)) = (words.next(), words.next()) {
+                missing.push(function.split('.').next().unwrap_or_default().to_string());
+            }
+        }
+    }
+
+    missing.sort();
+    missing.dedup();
+    missing.retain(|name| !linked.iter().any(|c| c.name == *name));
+    Ok(missing)
+}
+
+fn compile(source_dir: &Path, dir: &Path, wanted: Option<&[String]>, linked: &[Class]) -> Result<Vec<Class>, BuildError> {
+    // Compiles the .jack files in source_dir into dir, skipping classes that aren't wanted or are already linked
+    let compile_error = |error| BuildError::Compile { file: source_dir.display().to_string(), error };
+
+    let mut compiler = JackCompiler::new(&source_dir.display().to_string()).map_err(compile_error)?;
+    compiler.source_files.retain(|file| {
+        let name = class_name(file);
+        wanted.is_none_or(|wanted| wanted.contains(&name)) && !linked.iter().any(|c| c.name == name)
+    });
+    compiler.source_files.sort();
+
+    let sources = compiler.source_files.clone();
+    compiler.with_output_dir(dir).compile().map_err(compile_error)?;
+
+    Ok(sources.into_iter().map(|source| Class { name: class_name(&source), source }).collect())
+}
+
+fn copy_vm_files(source_dir: &Path, dir: &Path, wanted: &[String], linked: &[Class]) -> Result<Vec<Class>, io::Error> {
+    let mut copied = Vec::new();
+    for name in wanted {
+        let source = source_dir.join(format!("{}.vm", name));
+        if !source.is_file() || linked.iter().any(|c| c.name == *name) {
+            continue;
+        }
+        fs::copy(&source, dir.join(format!("{}.vm", name)))?;
+        copied.push(Class { name: name.clone(), source });
+    }
+    Ok(copied)
+}